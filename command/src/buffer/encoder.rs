@@ -7,8 +7,9 @@ use {
         CommandBuffer,
     },
     crate::{
-        capability::{Capability, Compute, Graphics, Supports, Transfer},
+        capability::{Capability, Compute, Execute, Graphics, Supports, Transfer},
         family::FamilyId,
+        query::{Occlusion, PipelineStatistics, QueryKind, QueryPool, Timestamp},
     },
 };
 
@@ -239,6 +240,83 @@ where
         )
     }
 
+    /// Write timestamp into the query when all previous commands reach the `stage`.
+    ///
+    /// # Safety
+    ///
+    /// Query must be reset before and not written since.
+    /// Queue family must have non-zero timestamp valid bits.
+    ///
+    /// See: https://www.khronos.org/registry/vulkan/specs/1.1-extensions/man/html/vkCmdWriteTimestamp.html
+    pub unsafe fn write_timestamp(
+        &mut self,
+        stage: rendy_core::hal::pso::PipelineStage,
+        pool: &QueryPool<B, Timestamp>,
+        id: rendy_core::hal::query::Id,
+    ) {
+        assert!(id < pool.count(), "Query is out of range");
+
+        rendy_core::hal::command::CommandBuffer::write_timestamp(
+            self.raw,
+            stage,
+            rendy_core::hal::query::Query {
+                pool: pool.raw(),
+                id,
+            },
+        )
+    }
+
+    /// Open debug label region.
+    /// Commands recorded until matching [`pop_debug_label`] are grouped under `name`
+    /// in graphics debuggers and validation messages.
+    /// Region may be closed in another command buffer submitted later to the same queue.
+    /// `color` is RGBA color packed into `u32` or `0` to leave it to the tool.
+    ///
+    /// Does nothing if backend doesn't support debug labels.
+    ///
+    /// [`pop_debug_label`]: #method.pop_debug_label
+    ///
+    /// # Safety
+    ///
+    /// Region must be closed in this command buffer
+    /// or in command buffer submitted later to the same queue.
+    /// `name` must not contain nul characters.
+    ///
+    /// See: https://www.khronos.org/registry/vulkan/specs/1.1-extensions/man/html/vkCmdBeginDebugUtilsLabelEXT.html
+    pub unsafe fn push_debug_label(&mut self, name: &str, color: u32) {
+        rendy_core::hal::command::CommandBuffer::begin_debug_marker(self.raw, name, color);
+    }
+
+    /// Close debug label region opened by [`push_debug_label`].
+    ///
+    /// Does nothing if backend doesn't support debug labels.
+    ///
+    /// [`push_debug_label`]: #method.push_debug_label
+    ///
+    /// # Safety
+    ///
+    /// There must be open region in this command buffer
+    /// or in command buffers submitted earlier to the same queue.
+    ///
+    /// See: https://www.khronos.org/registry/vulkan/specs/1.1-extensions/man/html/vkCmdEndDebugUtilsLabelEXT.html
+    pub unsafe fn pop_debug_label(&mut self) {
+        rendy_core::hal::command::CommandBuffer::end_debug_marker(self.raw);
+    }
+
+    /// Insert single debug label.
+    /// `color` is RGBA color packed into `u32` or `0` to leave it to the tool.
+    ///
+    /// Does nothing if backend doesn't support debug labels.
+    ///
+    /// # Safety
+    ///
+    /// `name` must not contain nul characters.
+    ///
+    /// See: https://www.khronos.org/registry/vulkan/specs/1.1-extensions/man/html/vkCmdInsertDebugUtilsLabelEXT.html
+    pub unsafe fn insert_debug_marker(&mut self, name: &str, color: u32) {
+        rendy_core::hal::command::CommandBuffer::insert_debug_marker(self.raw, name, color);
+    }
+
     /// Push graphics constants.
     ///
     /// # Safety
@@ -435,6 +513,64 @@ where
         rendy_core::hal::command::CommandBuffer::clear_attachments(self.inner.raw, clears, rects);
     }
 
+    /// Begin occlusion query.
+    /// Precise query counts passed samples exactly,
+    /// otherwise it is only guaranteed to be non-zero if any sample passed.
+    ///
+    /// # Safety
+    ///
+    /// Query must be reset before and not written since.
+    /// Query must be ended in the same subpass.
+    /// `precise` requires `Features::PRECISE_OCCLUSION_QUERY`.
+    ///
+    /// See: https://www.khronos.org/registry/vulkan/specs/1.1-extensions/man/html/vkCmdBeginQuery.html
+    pub unsafe fn begin_query(
+        &mut self,
+        pool: &QueryPool<B, Occlusion>,
+        id: rendy_core::hal::query::Id,
+        precise: bool,
+    ) {
+        assert!(id < pool.count(), "Query is out of range");
+
+        let flags = if precise {
+            rendy_core::hal::query::ControlFlags::PRECISE
+        } else {
+            rendy_core::hal::query::ControlFlags::empty()
+        };
+
+        rendy_core::hal::command::CommandBuffer::begin_query(
+            self.inner.raw,
+            rendy_core::hal::query::Query {
+                pool: pool.raw(),
+                id,
+            },
+            flags,
+        )
+    }
+
+    /// End occlusion query.
+    ///
+    /// # Safety
+    ///
+    /// Query must be active.
+    ///
+    /// See: https://www.khronos.org/registry/vulkan/specs/1.1-extensions/man/html/vkCmdEndQuery.html
+    pub unsafe fn end_query(
+        &mut self,
+        pool: &QueryPool<B, Occlusion>,
+        id: rendy_core::hal::query::Id,
+    ) {
+        assert!(id < pool.count(), "Query is out of range");
+
+        rendy_core::hal::command::CommandBuffer::end_query(
+            self.inner.raw,
+            rendy_core::hal::query::Query {
+                pool: pool.raw(),
+                id,
+            },
+        )
+    }
+
     /// Draw.
     ///
     /// # Safety
@@ -902,6 +1038,54 @@ where
         )
     }
 
+    /// Fill buffer range with repeated 4-byte `data`.
+    ///
+    /// # Safety
+    ///
+    /// `range` must be within the buffer.
+    /// Offset and size of the `range` must be multiples of 4.
+    ///
+    /// See: https://www.khronos.org/registry/vulkan/specs/1.1-extensions/man/html/vkCmdFillBuffer.html
+    pub unsafe fn fill_buffer(&mut self, buffer: &B::Buffer, range: std::ops::Range<u64>, data: u32)
+    where
+        C: Supports<Transfer>,
+    {
+        self.capability.assert();
+
+        rendy_core::hal::command::CommandBuffer::fill_buffer(self.inner.raw, buffer, range, data)
+    }
+
+    /// Clear image subresource ranges outside of render pass.
+    /// `value` is interpreted as color or depth-stencil value
+    /// according to aspects of the ranges.
+    ///
+    /// # Safety
+    ///
+    /// `layout` must be either `General` or `TransferDstOptimal`
+    /// and match the layout of the image subresources.
+    ///
+    /// See: https://www.khronos.org/registry/vulkan/specs/1.1-extensions/man/html/vkCmdClearColorImage.html
+    /// and https://www.khronos.org/registry/vulkan/specs/1.1-extensions/man/html/vkCmdClearDepthStencilImage.html
+    pub unsafe fn clear_image(
+        &mut self,
+        image: &B::Image,
+        layout: rendy_core::hal::image::Layout,
+        value: rendy_core::hal::command::ClearValue,
+        ranges: impl IntoIterator<Item = rendy_core::hal::image::SubresourceRange>,
+    ) where
+        C: Supports<Graphics>,
+    {
+        self.capability.assert();
+
+        rendy_core::hal::command::CommandBuffer::clear_image(
+            self.inner.raw,
+            image,
+            layout,
+            value,
+            ranges,
+        )
+    }
+
     /// Blit image regions, potentially using specified filter when resize is necessary.
     ///
     /// # Safety
@@ -933,6 +1117,120 @@ where
         )
     }
 
+    /// Reset queries so that they can be written again.
+    ///
+    /// # Safety
+    ///
+    /// Queries must not be used by pending commands.
+    ///
+    /// See: https://www.khronos.org/registry/vulkan/specs/1.1-extensions/man/html/vkCmdResetQueryPool.html
+    pub unsafe fn reset_query_pool(
+        &mut self,
+        pool: &QueryPool<B, impl QueryKind>,
+        queries: std::ops::Range<rendy_core::hal::query::Id>,
+    ) where
+        C: Supports<Execute>,
+    {
+        self.capability.assert();
+        assert!(queries.end <= pool.count(), "Queries are out of range");
+
+        rendy_core::hal::command::CommandBuffer::reset_query_pool(
+            self.inner.raw,
+            pool.raw(),
+            queries,
+        )
+    }
+
+    /// Begin pipeline statistics query.
+    /// Render passes can be recorded while query is active.
+    ///
+    /// # Safety
+    ///
+    /// Query must be reset before and not written since.
+    /// Requires `Features::PIPELINE_STATISTICS_QUERY`.
+    ///
+    /// See: https://www.khronos.org/registry/vulkan/specs/1.1-extensions/man/html/vkCmdBeginQuery.html
+    pub unsafe fn begin_pipeline_statistics_query(
+        &mut self,
+        pool: &QueryPool<B, PipelineStatistics>,
+        id: rendy_core::hal::query::Id,
+    ) where
+        C: Supports<Execute>,
+    {
+        self.capability.assert();
+        assert!(id < pool.count(), "Query is out of range");
+
+        rendy_core::hal::command::CommandBuffer::begin_query(
+            self.inner.raw,
+            rendy_core::hal::query::Query {
+                pool: pool.raw(),
+                id,
+            },
+            rendy_core::hal::query::ControlFlags::empty(),
+        )
+    }
+
+    /// End pipeline statistics query.
+    ///
+    /// # Safety
+    ///
+    /// Query must be active.
+    ///
+    /// See: https://www.khronos.org/registry/vulkan/specs/1.1-extensions/man/html/vkCmdEndQuery.html
+    pub unsafe fn end_pipeline_statistics_query(
+        &mut self,
+        pool: &QueryPool<B, PipelineStatistics>,
+        id: rendy_core::hal::query::Id,
+    ) where
+        C: Supports<Execute>,
+    {
+        self.capability.assert();
+        assert!(id < pool.count(), "Query is out of range");
+
+        rendy_core::hal::command::CommandBuffer::end_query(
+            self.inner.raw,
+            rendy_core::hal::query::Query {
+                pool: pool.raw(),
+                id,
+            },
+        )
+    }
+
+    /// Copy results of the queries into buffer.
+    /// Results of each query are written at `stride` bytes from results of the previous one.
+    ///
+    /// # Safety
+    ///
+    /// `buffer` must be large enough to hold results.
+    /// `offset` and `stride` must be multiples of 4,
+    /// or 8 if `flags` contain `ResultFlags::BITS_64`.
+    ///
+    /// See: https://www.khronos.org/registry/vulkan/specs/1.1-extensions/man/html/vkCmdCopyQueryPoolResults.html
+    pub unsafe fn copy_query_pool_results(
+        &mut self,
+        pool: &QueryPool<B, impl QueryKind>,
+        queries: std::ops::Range<rendy_core::hal::query::Id>,
+        buffer: &B::Buffer,
+        offset: u64,
+        stride: u64,
+        flags: rendy_core::hal::query::ResultFlags,
+    ) where
+        C: Supports<Execute>,
+    {
+        self.capability.assert();
+        assert!(queries.end <= pool.count(), "Queries are out of range");
+
+        rendy_core::hal::command::CommandBuffer::copy_query_pool_results(
+            self.inner.raw,
+            pool.raw(),
+            queries,
+            buffer,
+            offset,
+            stride,
+            flags,
+        )
+    }
+
     /// Dispatch compute.
     ///
     /// # Safety
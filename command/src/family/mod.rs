@@ -259,7 +259,9 @@ where
         .map(|(id, count)| Family::from_device(queue_groups, id, count, &queue_types[id.index]))
         .collect();
 
-    let mut families_indices = vec![!0; families.len()];
+    // Family ids may be sparse if not all families of the device are used.
+    let len = families.iter().map(|f| f.id.index + 1).max().unwrap_or(0);
+    let mut families_indices = vec![!0; len];
     for (index, family) in families.iter().enumerate() {
         families_indices[family.id.index] = index;
     }
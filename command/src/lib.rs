@@ -84,5 +84,6 @@ mod capability;
 mod family;
 mod fence;
 mod pool;
+mod query;
 
-pub use crate::{buffer::*, capability::*, family::*, fence::*, pool::*};
+pub use crate::{buffer::*, capability::*, family::*, fence::*, pool::*, query::*};
@@ -0,0 +1,282 @@
+//! Query pools.
+
+use {
+    crate::{
+        core::{device_owned, Device, DeviceId},
+        fence::FenceEpoch,
+    },
+    rendy_core::hal::{
+        device::{Device as _, OomOrDeviceLost},
+        query, Backend,
+    },
+};
+
+/// Kind of queries in the pool.
+pub trait QueryKind: Copy + std::fmt::Debug + 'static {
+    /// Get raw query type.
+    fn query_type(&self) -> query::Type;
+
+    /// Get number of values each query of this kind produces.
+    fn values(&self) -> u32;
+}
+
+/// Queries that record time when all previous commands reached specified pipeline stage.
+/// Results are in device ticks, see [`timestamp_to_ns`].
+///
+/// [`timestamp_to_ns`]: fn.timestamp_to_ns.html
+#[derive(Clone, Copy, Debug)]
+pub struct Timestamp;
+
+impl QueryKind for Timestamp {
+    fn query_type(&self) -> query::Type {
+        query::Type::Timestamp
+    }
+
+    fn values(&self) -> u32 {
+        1
+    }
+}
+
+/// Queries that count samples passing depth and stencil tests
+/// while the query is active in render pass.
+/// Non-precise queries may report any non-zero value if some samples passed.
+#[derive(Clone, Copy, Debug)]
+pub struct Occlusion;
+
+impl QueryKind for Occlusion {
+    fn query_type(&self) -> query::Type {
+        query::Type::Occlusion
+    }
+
+    fn values(&self) -> u32 {
+        1
+    }
+}
+
+/// Queries that count pipeline statistics while the query is active.
+/// Each query produces one value per statistic in the set,
+/// ordered by the bit position of the statistic.
+#[derive(Clone, Copy, Debug)]
+pub struct PipelineStatistics(pub query::PipelineStatistic);
+
+impl QueryKind for PipelineStatistics {
+    fn query_type(&self) -> query::Type {
+        query::Type::PipelineStatistics(self.0)
+    }
+
+    fn values(&self) -> u32 {
+        self.0.bits().count_ones()
+    }
+}
+
+/// Convert timestamp query result into nanoseconds.
+/// `valid_bits` is number of meaningful bits in timestamps written by the queue family,
+/// higher bits of `ticks` are undefined and are masked off.
+/// Values of 64 and above keep all bits.
+/// `timestamp_period` is number of nanoseconds per timestamp tick
+/// as reported by the device limits.
+pub fn timestamp_to_ns(ticks: u64, valid_bits: u32, timestamp_period: f32) -> u64 {
+    let mask = if valid_bits >= 64 {
+        !0
+    } else {
+        (1 << valid_bits) - 1
+    };
+    ((ticks & mask) as f64 * timestamp_period as f64) as u64
+}
+
+/// Query pool wrapper.
+/// Tracks fence epoch of the last submission that writes the queries,
+/// so that results are read only after device finished writing them.
+#[derive(Debug)]
+pub struct QueryPool<B: Backend, K> {
+    device: DeviceId,
+    raw: B::QueryPool,
+    kind: K,
+    count: u32,
+    epoch: Option<FenceEpoch>,
+    relevant: relevant::Relevant,
+}
+
+device_owned!(QueryPool<B, K>);
+
+impl<B, K> QueryPool<B, K>
+where
+    B: Backend,
+    K: QueryKind,
+{
+    /// Create query pool with `count` queries of specified kind.
+    pub fn create(device: &Device<B>, kind: K, count: u32) -> Result<Self, query::CreationError> {
+        let raw = unsafe { device.create_query_pool(kind.query_type(), count) }?;
+        Ok(QueryPool {
+            device: device.id(),
+            raw,
+            kind,
+            count,
+            epoch: None,
+            relevant: relevant::Relevant,
+        })
+    }
+
+    /// Destroy query pool.
+    ///
+    /// # Safety
+    ///
+    /// Pool must not be used by pending commands.
+    pub unsafe fn dispose(self, device: &Device<B>) {
+        self.assert_device_owner(device);
+        device.destroy_query_pool(self.raw);
+        self.relevant.dispose();
+    }
+
+    /// Get raw query pool reference.
+    pub fn raw(&self) -> &B::QueryPool {
+        &self.raw
+    }
+
+    /// Get kind of the queries.
+    pub fn kind(&self) -> K {
+        self.kind
+    }
+
+    /// Get number of queries in the pool.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Record epoch of the fence submitted with commands that write queries of the pool.
+    /// Results are not read until this epoch is complete.
+    pub fn mark_submitted(&mut self, epoch: FenceEpoch) {
+        self.epoch = Some(epoch);
+    }
+
+    /// Check if commands that write queries are complete
+    /// given the epoch of the signaled fence.
+    /// Fence epoch is returned by [`Fence::wait_signaled`] and [`Fence::check_signaled`].
+    ///
+    /// [`Fence::wait_signaled`]: struct.Fence.html#method.wait_signaled
+    /// [`Fence::check_signaled`]: struct.Fence.html#method.check_signaled
+    pub fn is_complete(&self, complete: FenceEpoch) -> bool {
+        match self.epoch {
+            Some(epoch) => epoch.queue == complete.queue && epoch.epoch <= complete.epoch,
+            None => false,
+        }
+    }
+
+    /// Read results of the queries without waiting.
+    /// Each query produces `kind().values()` consecutive values.
+    /// Poll this each frame to get results without stalling.
+    ///
+    /// Returns `None` if pool was not submitted, submission is not complete
+    /// or some of the queries are not written.
+    pub fn results(
+        &self,
+        device: &Device<B>,
+        queries: std::ops::Range<query::Id>,
+        complete: FenceEpoch,
+    ) -> Result<Option<Vec<u64>>, OomOrDeviceLost> {
+        self.assert_device_owner(device);
+        assert!(queries.end <= self.count, "Queries are out of range");
+
+        if !self.is_complete(complete) {
+            return Ok(None);
+        }
+
+        let values = self.kind.values() as usize;
+        let mut results = vec![0u64; (queries.end - queries.start) as usize * values];
+        let stride = std::mem::size_of::<u64>() * values;
+
+        let ready = unsafe {
+            device.get_query_pool_results(
+                &self.raw,
+                queries,
+                std::slice::from_raw_parts_mut(
+                    results.as_mut_ptr() as *mut u8,
+                    results.len() * std::mem::size_of::<u64>(),
+                ),
+                stride as u64,
+                query::ResultFlags::BITS_64,
+            )
+        }?;
+
+        if ready {
+            Ok(Some(results))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<B> QueryPool<B, Timestamp>
+where
+    B: Backend,
+{
+    /// Read timestamps converted to nanoseconds without waiting.
+    /// See [`results`] and [`timestamp_to_ns`].
+    ///
+    /// [`results`]: #method.results
+    /// [`timestamp_to_ns`]: fn.timestamp_to_ns.html
+    pub fn timestamps_ns(
+        &self,
+        device: &Device<B>,
+        queries: std::ops::Range<query::Id>,
+        complete: FenceEpoch,
+        timestamp_period: f32,
+    ) -> Result<Option<Vec<u64>>, OomOrDeviceLost> {
+        Ok(self.results(device, queries, complete)?.map(|ticks| {
+            ticks
+                .into_iter()
+                .map(|ticks| timestamp_to_ns(ticks, timestamp_period))
+                .collect()
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{timestamp_to_ns, PipelineStatistics, QueryKind as _},
+        rendy_core::hal::query::PipelineStatistic,
+    };
+
+    #[test]
+    fn timestamp_period() {
+        assert_eq!(timestamp_to_ns(1000, 64, 1.0), 1000);
+        assert_eq!(timestamp_to_ns(1000, 64, 52.08), 52080);
+        assert_eq!(timestamp_to_ns(0, 64, 52.08), 0);
+    }
+
+    #[test]
+    fn timestamp_valid_bits() {
+        let ticks = 0xdead_0000_0000_1000;
+        assert_eq!(timestamp_to_ns(ticks, 36, 1.0), 0x1000);
+        assert_eq!(timestamp_to_ns(ticks, 36, 2.0), 0x2000);
+        assert_eq!(timestamp_to_ns(0xf_ffff_ffff, 32, 1.0), 0xffff_ffff);
+        assert_eq!(timestamp_to_ns(0x1000, 0, 1.0), 0);
+
+        // All bits are valid.
+        assert_eq!(timestamp_to_ns(1 << 63, 64, 1.0), 1 << 63);
+        assert_eq!(timestamp_to_ns(1 << 63, 100, 1.0), 1 << 63);
+    }
+
+    #[test]
+    fn pipeline_statistics_values() {
+        assert_eq!(PipelineStatistics(PipelineStatistic::empty()).values(), 0);
+        assert_eq!(
+            PipelineStatistics(PipelineStatistic::VERTEX_SHADER_INVOCATIONS).values(),
+            1
+        );
+        assert_eq!(
+            PipelineStatistics(
+                PipelineStatistic::INPUT_ASSEMBLY_VERTICES
+                    | PipelineStatistic::FRAGMENT_SHADER_INVOCATIONS
+                    | PipelineStatistic::COMPUTE_SHADER_INVOCATIONS
+            )
+            .values(),
+            3
+        );
+        assert_eq!(
+            PipelineStatistics(PipelineStatistic::all()).values(),
+            PipelineStatistic::all().bits().count_ones()
+        );
+    }
+}
@@ -9,6 +9,7 @@ use {
         core::Device,
         resource::{Handle, Image},
         upload::ImageState,
+        UploadError,
     },
     rendy_core::hal::device::{Device as _, OutOfMemory},
     smallvec::SmallVec,
@@ -31,6 +32,45 @@ fn subresource_to_range(
     }
 }
 
+/// Failure filling mip levels of an image.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MipsError {
+    /// Format of the image can be neither blitted nor downsampled on the host.
+    Unsupported(rendy_core::hal::format::Format),
+    /// Content of the first level is too short for the image extent and layers.
+    InsufficientContent,
+    /// Failed to record blits.
+    Blit(OutOfMemory),
+    /// Failed to upload downsampled levels.
+    Upload(UploadError),
+}
+
+impl std::fmt::Display for MipsError {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MipsError::Unsupported(format) => {
+                write!(fmt, "Mip levels generation unsupported for {:?}", format)
+            }
+            MipsError::InsufficientContent => write!(
+                fmt,
+                "Mip levels generation failed: content is too short for the image"
+            ),
+            MipsError::Blit(err) => write!(fmt, "Mip levels generation failed: {:?}", err),
+            MipsError::Upload(err) => write!(fmt, "Mip levels generation failed: {:?}", err),
+        }
+    }
+}
+
+impl std::error::Error for MipsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MipsError::Unsupported(_) | MipsError::InsufficientContent => None,
+            MipsError::Blit(err) => Some(err),
+            MipsError::Upload(err) => Some(err),
+        }
+    }
+}
+
 /// A region to be blitted including the source and destination images and states,
 #[derive(Debug, Clone)]
 pub struct BlitRegion {
@@ -172,6 +212,11 @@ where
                 family_ops.push(None);
             }
 
+            // Blits require graphics capability.
+            if !family.capability().supports_graphics() {
+                continue;
+            }
+
             family_ops[family.id().index] = Some(parking_lot::Mutex::new(FamilyGraphicsOps {
                 pool: family
                     .create_pool(device)
@@ -194,12 +239,22 @@ where
 
         Ok(Blitter { family_ops })
     }
+
     /// Fill all mip levels from the first level of provided image.
     ///
+    /// `Blitter` has no access to format properties and can't check that image format
+    /// supports blitting, so it is left to the caller.
+    /// Use [`Factory::supports_blit`] to check it
+    /// or [`Factory::fill_mips`] that falls back to downsampling on the host.
+    ///
+    /// [`Factory::supports_blit`]: struct.Factory.html#method.supports_blit
+    /// [`Factory::fill_mips`]: struct.Factory.html#method.fill_mips
+    ///
     /// # Safety
     ///
     /// `device` must be the same that was used to create this `Blitter`.
     /// `image` must belong to the `device`.
+    /// `image` format must support blitting with `filter`.
     /// `last` state must be valid for corresponding image layer at the time of command execution (after memory transfers).
     /// `last` and `next` should contain at least `image.levels()` elements.
     /// `image.levels()` must be greater than 1
@@ -239,7 +294,7 @@ where
     ) -> Result<(), OutOfMemory> {
         let mut family_ops = self.family_ops[queue_id.family.index]
             .as_ref()
-            .unwrap()
+            .expect("Blits can be recorded only on graphics families")
             .lock();
 
         family_ops.next_ops(device, queue_id.index)?;
@@ -275,10 +330,9 @@ where
     ///
     pub(crate) unsafe fn flush(&mut self, families: &mut Families<B>) {
         for family in families.as_slice_mut() {
-            let blitter = self.family_ops[family.id().index]
-                .as_mut()
-                .expect("Blitter must be initialized for all families");
-            blitter.get_mut().flush(family);
+            if let Some(blitter) = &mut self.family_ops[family.id().index] {
+                blitter.get_mut().flush(family);
+            }
         }
     }
 
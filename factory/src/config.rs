@@ -1,9 +1,10 @@
+use smallvec::SmallVec;
 use std::cmp::min;
 
 use crate::{
     command::FamilyId,
     core::DeviceId,
-    memory::{DynamicConfig, HeapsConfig, LinearConfig},
+    memory::{BlockCacheConfig, DynamicConfig, HeapBudget, HeapsConfig, LinearConfig},
 };
 
 /// Factory initialization config.
@@ -14,6 +15,7 @@ use crate::{
 /// [`BasicHeapsConfigure`] can be used as sane default.
 /// `queues` - [`QueuesConfigure`] implementation to configure device queues creation.
 /// [`OneGraphicsQueue`] can be used if only one graphics queue will satisfy requirements.
+/// `uploads` - [`UploadsConfig`] to configure staging memory used for uploads.
 ///
 /// [`DeviceConfigure`]: trait.DevicesConfigure.html
 /// [`BasicDevicesConfigure`]: struct.BasicDevicesConfigure.html
@@ -21,6 +23,7 @@ use crate::{
 /// [`BasicHeapsConfigure`]: struct.BasicHeapsConfigure.html
 /// [`QueuesConfigure`]: trait.QueuesConfigure.html
 /// [`OneGraphicsQueue`]: struct.OneGraphicsQueue.html
+/// [`UploadsConfig`]: struct.UploadsConfig.html
 #[derive(Clone, Debug, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Config<D = BasicDevicesConfigure, H = BasicHeapsConfigure, Q = OneGraphicsQueue> {
@@ -32,12 +35,44 @@ pub struct Config<D = BasicDevicesConfigure, H = BasicHeapsConfigure, Q = OneGra
 
     /// Config for queue families.
     pub queues: Q,
+
+    /// Config for staging memory of uploads.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub uploads: UploadsConfig,
+}
+
+/// Config for staging memory used by `Factory` to upload content.
+///
+/// Content is copied to regions of staging chunks that are reused
+/// once uploads that read from them are complete.
+/// Content larger than a chunk or that doesn't fit the budget
+/// is copied to dedicated staging buffer.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UploadsConfig {
+    /// Size of one staging chunk.
+    pub staging_chunk_size: u64,
+
+    /// Maximum total size of staging chunks per queue family.
+    /// Zero disables reuse of staging memory.
+    pub staging_budget: u64,
+}
+
+impl Default for UploadsConfig {
+    fn default() -> Self {
+        UploadsConfig {
+            staging_chunk_size: 4 * 1024 * 1024,
+            staging_budget: 64 * 1024 * 1024,
+        }
+    }
 }
 
 /// Queues configuration.
 ///
 /// Method [`configure`] receives collection of queue families and
 /// returns an iterator over family ids and number of queues.
+/// Each family must be returned at most once,
+/// with at least one and at most `max_queues` priorities.
 ///
 /// [`configure`]: trait.QueuesConfigure.html#tymethod.configure
 pub unsafe trait QueuesConfigure {
@@ -53,16 +88,28 @@ pub unsafe trait QueuesConfigure {
         device: DeviceId,
         families: &[impl rendy_core::hal::queue::QueueFamily],
     ) -> Self::Families;
+
+    /// Check if `Factory` should record uploads on transfer-only family if one is configured.
+    /// Ownership of uploaded resources is then transferred
+    /// to the family of the queue they are used on next.
+    /// Only resources not used by device yet are uploaded this way.
+    fn transfer_uploads(&self) -> bool {
+        false
+    }
 }
 
 /// QueuePicker that picks first graphics queue family.
 ///
-/// TODO: Try to pick family that is capable of presenting
-/// This is possible in platform-dependent way for some platforms.
+/// Family capable of presenting is not preferred as queues are configured
+/// before any surface can be created with the `Factory`.
+/// Presentation support is checked against the surface with [`Factory::surface_support`]
+/// when presenting node is built.
 ///
 /// To pick multiple families with require number of queues
-/// a custom [`QueuesConfigure`] implementation can be used instead.
+/// [`MultipleQueues`] or a custom [`QueuesConfigure`] implementation can be used instead.
 ///
+/// [`Factory::surface_support`]: struct.Factory.html#method.surface_support
+/// [`MultipleQueues`]: struct.MultipleQueues.html
 /// [`QueuesConfigure`]: trait.QueuesConfigure.html
 #[derive(Clone, Copy, Debug, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -91,6 +138,138 @@ unsafe impl QueuesConfigure for OneGraphicsQueue {
     }
 }
 
+/// QueuePicker that picks first graphics queue family
+/// and first transfer-only queue family if there is one.
+/// `Factory` records uploads on the transfer family
+/// to not interfere with work submitted to graphics queue.
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GraphicsWithTransferQueue;
+
+unsafe impl QueuesConfigure for GraphicsWithTransferQueue {
+    type Priorities = [f32; 1];
+    type Families = SmallVec<[(FamilyId, [f32; 1]); 2]>;
+    fn configure(
+        &self,
+        device: DeviceId,
+        families: &[impl rendy_core::hal::queue::QueueFamily],
+    ) -> SmallVec<[(FamilyId, [f32; 1]); 2]> {
+        let graphics = families
+            .iter()
+            .find(|f| f.queue_type().supports_graphics() && f.max_queues() > 0);
+        let transfer = families.iter().find(|f| {
+            f.queue_type() == rendy_core::hal::queue::QueueType::Transfer && f.max_queues() > 0
+        });
+
+        graphics
+            .into_iter()
+            .chain(transfer)
+            .map(|f| {
+                (
+                    FamilyId {
+                        device,
+                        index: f.id().0,
+                    },
+                    [1.0],
+                )
+            })
+            .collect()
+    }
+
+    fn transfer_uploads(&self) -> bool {
+        true
+    }
+}
+
+/// QueuePicker that picks first graphics queue family,
+/// first dedicated compute family and first transfer-only family
+/// if there are ones.
+///
+/// Number of queues created in each family is the number of priorities,
+/// capped by number of queues family has.
+/// Family with empty priorities is not picked.
+/// If there is no dedicated compute family, compute work can be done on the graphics one.
+///
+/// Picked families may have non-adjacent indices, `Families` maps them.
+/// Nodes of the graph run on the first family with required capability.
+/// Custom `NodeBuilder` can pick the compute family with `Families::find`
+/// to run it asynchronously to graphics work.
+/// Graph schedules nodes of one family across all its queues.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct MultipleQueues {
+    /// Priorities of graphics queues.
+    pub graphics: Vec<f32>,
+
+    /// Priorities of dedicated compute queues.
+    pub compute: Vec<f32>,
+
+    /// Priorities of transfer-only queues.
+    pub transfer: Vec<f32>,
+
+    /// Record uploads on transfer-only family.
+    /// See [`QueuesConfigure::transfer_uploads`].
+    ///
+    /// [`QueuesConfigure::transfer_uploads`]: trait.QueuesConfigure.html#method.transfer_uploads
+    pub transfer_uploads: bool,
+}
+
+impl Default for MultipleQueues {
+    fn default() -> Self {
+        MultipleQueues {
+            graphics: vec![1.0],
+            compute: vec![1.0],
+            transfer: vec![1.0],
+            transfer_uploads: true,
+        }
+    }
+}
+
+unsafe impl QueuesConfigure for MultipleQueues {
+    type Priorities = Vec<f32>;
+    type Families = SmallVec<[(FamilyId, Vec<f32>); 3]>;
+    fn configure(
+        &self,
+        device: DeviceId,
+        families: &[impl rendy_core::hal::queue::QueueFamily],
+    ) -> SmallVec<[(FamilyId, Vec<f32>); 3]> {
+        use rendy_core::hal::queue::QueueType;
+
+        let graphics = families
+            .iter()
+            .find(|f| f.queue_type().supports_graphics() && f.max_queues() > 0);
+        let compute = families
+            .iter()
+            .find(|f| f.queue_type() == QueueType::Compute && f.max_queues() > 0);
+        let transfer = families
+            .iter()
+            .find(|f| f.queue_type() == QueueType::Transfer && f.max_queues() > 0);
+
+        graphics
+            .map(|f| (f, &self.graphics))
+            .into_iter()
+            .chain(compute.map(|f| (f, &self.compute)))
+            .chain(transfer.map(|f| (f, &self.transfer)))
+            .filter(|(_, priorities)| !priorities.is_empty())
+            .map(|(f, priorities)| {
+                let count = min(priorities.len(), f.max_queues());
+                (
+                    FamilyId {
+                        device,
+                        index: f.id().0,
+                    },
+                    priorities[..count].to_vec(),
+                )
+            })
+            .collect()
+    }
+
+    fn transfer_uploads(&self) -> bool {
+        self.transfer_uploads
+    }
+}
+
 /// Saved config for queues.
 /// This config can be loaded from config files
 /// in any format supported by serde ecosystem.
@@ -133,6 +312,25 @@ pub unsafe trait HeapsConfigure {
         &self,
         properties: &rendy_core::hal::adapter::MemoryProperties,
     ) -> (Self::Types, Self::Heaps);
+
+    /// Budgets for heaps.
+    /// Heap with index `i` gets budget at index `i`.
+    /// Heaps without budget are limited only by their size.
+    fn budgets(&self, _properties: &rendy_core::hal::adapter::MemoryProperties) -> Vec<HeapBudget> {
+        Vec::new()
+    }
+
+    /// Config for per-thread caches of small blocks.
+    /// Caches are disabled if `None` is returned.
+    ///
+    /// Caches round sizes up to power of two and keep blocks reserved,
+    /// so they trade memory for less contention on `Heaps` lock.
+    /// See [`CachedHeapsConfigure`] to enable them.
+    ///
+    /// [`CachedHeapsConfigure`]: struct.CachedHeapsConfigure.html
+    fn block_cache(&self) -> Option<BlockCacheConfig> {
+        None
+    }
 }
 
 /// Basic heaps config.
@@ -185,6 +383,7 @@ unsafe impl HeapsConfigure for BasicHeapsConfigure {
                             (properties.memory_heaps[mt.heap_index] / 128).next_power_of_two(),
                         ),
                     }),
+                    buddy: None,
                 };
 
                 (mt.properties, mt.heap_index as u32, config)
@@ -205,6 +404,10 @@ unsafe impl HeapsConfigure for BasicHeapsConfigure {
 pub struct SavedHeapsConfig {
     types: Vec<(rendy_core::hal::memory::Properties, u32, HeapsConfig)>,
     heaps: Vec<u64>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    budgets: Vec<HeapBudget>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    block_cache: Option<BlockCacheConfig>,
 }
 
 unsafe impl HeapsConfigure for SavedHeapsConfig {
@@ -217,6 +420,90 @@ unsafe impl HeapsConfigure for SavedHeapsConfig {
     ) -> (Self::Types, Self::Heaps) {
         (self.types.clone(), self.heaps.clone())
     }
+
+    fn budgets(&self, _properties: &rendy_core::hal::adapter::MemoryProperties) -> Vec<HeapBudget> {
+        self.budgets.clone()
+    }
+
+    fn block_cache(&self) -> Option<BlockCacheConfig> {
+        self.block_cache
+    }
+}
+
+/// Heaps config with budgets.
+/// Uses wrapped [`HeapsConfigure`] implementation to configure allocators
+/// and limits heaps with budgets provided.
+///
+/// [`HeapsConfigure`]: trait.HeapsConfigure.html
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BudgetedHeapsConfigure<H = BasicHeapsConfigure> {
+    /// Wrapped heaps config.
+    pub heaps: H,
+
+    /// Budgets for heaps.
+    pub budgets: Vec<HeapBudget>,
+}
+
+unsafe impl<H> HeapsConfigure for BudgetedHeapsConfigure<H>
+where
+    H: HeapsConfigure,
+{
+    type Types = H::Types;
+    type Heaps = H::Heaps;
+
+    fn configure(
+        &self,
+        properties: &rendy_core::hal::adapter::MemoryProperties,
+    ) -> (Self::Types, Self::Heaps) {
+        self.heaps.configure(properties)
+    }
+
+    fn budgets(&self, _properties: &rendy_core::hal::adapter::MemoryProperties) -> Vec<HeapBudget> {
+        self.budgets.clone()
+    }
+
+    fn block_cache(&self) -> Option<BlockCacheConfig> {
+        self.heaps.block_cache()
+    }
+}
+
+/// Heaps config with per-thread block caches.
+/// Uses wrapped [`HeapsConfigure`] implementation to configure allocators and budgets
+/// and enables caches with config provided.
+///
+/// [`HeapsConfigure`]: trait.HeapsConfigure.html
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CachedHeapsConfigure<H = BasicHeapsConfigure> {
+    /// Wrapped heaps config.
+    pub heaps: H,
+
+    /// Config for block caches.
+    pub block_cache: BlockCacheConfig,
+}
+
+unsafe impl<H> HeapsConfigure for CachedHeapsConfigure<H>
+where
+    H: HeapsConfigure,
+{
+    type Types = H::Types;
+    type Heaps = H::Heaps;
+
+    fn configure(
+        &self,
+        properties: &rendy_core::hal::adapter::MemoryProperties,
+    ) -> (Self::Types, Self::Heaps) {
+        self.heaps.configure(properties)
+    }
+
+    fn budgets(&self, properties: &rendy_core::hal::adapter::MemoryProperties) -> Vec<HeapBudget> {
+        self.heaps.budgets(properties)
+    }
+
+    fn block_cache(&self) -> Option<BlockCacheConfig> {
+        Some(self.block_cache)
+    }
 }
 
 /// Devices configuration.
@@ -240,9 +527,12 @@ pub trait DevicesConfigure {
 ///
 /// To pick among presented discret GPUs,
 /// or to intentionally pick integrated GPU when discrete GPU is available
-/// a custom [`DeviceConfigure`] implementationcan be used instead.
+/// [`ScoredDevicesConfigure`], [`SavedDevicesConfig`]
+/// or a custom [`DeviceConfigure`] implementationcan be used instead.
 ///
 /// [`DeviceConfigure`]: trait.DevicesConfigure.html
+/// [`ScoredDevicesConfigure`]: struct.ScoredDevicesConfigure.html
+/// [`SavedDevicesConfig`]: struct.SavedDevicesConfig.html
 #[derive(Clone, Copy, Debug, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BasicDevicesConfigure;
@@ -266,3 +556,478 @@ impl DevicesConfigure for BasicDevicesConfigure {
             .0
     }
 }
+
+/// Limits adapter must satisfy to be picked by [`ScoredDevicesConfigure`].
+/// Zero means no requirement.
+///
+/// [`ScoredDevicesConfigure`]: struct.ScoredDevicesConfigure.html
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct RequiredLimits {
+    /// Minimal `max_image_2d_size`.
+    pub max_image_2d_size: u64,
+
+    /// Minimal `max_image_array_layers`.
+    pub max_image_array_layers: u64,
+
+    /// Minimal `max_uniform_buffer_range`.
+    pub max_uniform_buffer_range: u64,
+
+    /// Minimal `max_storage_buffer_range`.
+    pub max_storage_buffer_range: u64,
+
+    /// Minimal `max_push_constants_size`.
+    pub max_push_constants_size: u64,
+
+    /// Minimal `max_bound_descriptor_sets`.
+    pub max_bound_descriptor_sets: u64,
+}
+
+impl RequiredLimits {
+    /// Check if `limits` satisfy requirements.
+    pub fn satisfied_by(&self, limits: &rendy_core::hal::Limits) -> bool {
+        limits.max_image_2d_size as u64 >= self.max_image_2d_size
+            && limits.max_image_array_layers as u64 >= self.max_image_array_layers
+            && limits.max_uniform_buffer_range as u64 >= self.max_uniform_buffer_range
+            && limits.max_storage_buffer_range as u64 >= self.max_storage_buffer_range
+            && limits.max_push_constants_size as u64 >= self.max_push_constants_size
+            && limits.max_bound_descriptor_sets as u64 >= self.max_bound_descriptor_sets
+    }
+}
+
+/// Get total size of memory heaps that have device-local memory types.
+pub fn device_local_memory<B>(adapter: &rendy_core::hal::adapter::Adapter<B>) -> u64
+where
+    B: rendy_core::hal::Backend,
+{
+    use rendy_core::hal::adapter::PhysicalDevice;
+
+    let properties = adapter.physical_device.memory_properties();
+    let mut heaps = SmallVec::<[usize; 16]>::new();
+    for memory_type in &properties.memory_types {
+        if memory_type
+            .properties
+            .contains(rendy_core::hal::memory::Properties::DEVICE_LOCAL)
+            && !heaps.contains(&memory_type.heap_index)
+        {
+            heaps.push(memory_type.heap_index);
+        }
+    }
+    heaps
+        .into_iter()
+        .map(|heap| properties.memory_heaps[heap])
+        .sum()
+}
+
+/// Adapters config that ranks adapters.
+///
+/// Adapters that don't support `required_features` or don't satisfy `required_limits`
+/// are considered only if no adapter does.
+/// The rest are ranked by position of their type in `device_types`
+/// and then by total size of device-local memory.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ScoredDevicesConfigure {
+    /// Adapter types from most preferred to least preferred.
+    /// Types not listed are preferred least.
+    pub device_types: Vec<rendy_core::hal::adapter::DeviceType>,
+
+    /// Features adapter must support.
+    pub required_features: rendy_core::hal::Features,
+
+    /// Limits adapter must satisfy.
+    pub required_limits: RequiredLimits,
+}
+
+impl Default for ScoredDevicesConfigure {
+    fn default() -> Self {
+        use rendy_core::hal::adapter::DeviceType;
+
+        ScoredDevicesConfigure {
+            device_types: vec![
+                DeviceType::DiscreteGpu,
+                DeviceType::IntegratedGpu,
+                DeviceType::VirtualGpu,
+                DeviceType::Cpu,
+            ],
+            required_features: rendy_core::hal::Features::empty(),
+            required_limits: RequiredLimits::default(),
+        }
+    }
+}
+
+impl ScoredDevicesConfigure {
+    /// Check if adapter satisfies requirements.
+    pub fn is_suitable<B>(&self, adapter: &rendy_core::hal::adapter::Adapter<B>) -> bool
+    where
+        B: rendy_core::hal::Backend,
+    {
+        use rendy_core::hal::adapter::PhysicalDevice;
+
+        self.supports(
+            adapter.physical_device.features(),
+            &adapter.physical_device.limits(),
+        )
+    }
+
+    /// Check if `features` and `limits` satisfy requirements.
+    fn supports(
+        &self,
+        features: rendy_core::hal::Features,
+        limits: &rendy_core::hal::Limits,
+    ) -> bool {
+        features.contains(self.required_features) && self.required_limits.satisfied_by(limits)
+    }
+
+    /// Get score of the adapter. Greater is better.
+    fn score<B>(&self, adapter: &rendy_core::hal::adapter::Adapter<B>) -> (bool, usize, u64)
+    where
+        B: rendy_core::hal::Backend,
+    {
+        self.rank(
+            self.is_suitable(adapter),
+            adapter.info.device_type,
+            device_local_memory(adapter),
+        )
+    }
+
+    /// Get score of the adapter from its properties. Greater is better.
+    fn rank(
+        &self,
+        suitable: bool,
+        device_type: rendy_core::hal::adapter::DeviceType,
+        memory: u64,
+    ) -> (bool, usize, u64) {
+        let type_rank = self
+            .device_types
+            .iter()
+            .position(|&ty| ty == device_type)
+            .unwrap_or(self.device_types.len());
+
+        (suitable, self.device_types.len() - type_rank, memory)
+    }
+}
+
+/// Get index and value of the greatest score, preferring earlier one on equal scores.
+fn best_score<S: Ord>(scores: Vec<S>) -> Option<(usize, S)> {
+    scores
+        .into_iter()
+        .enumerate()
+        .rev()
+        .max_by(|(_, a), (_, b)| a.cmp(b))
+}
+
+impl DevicesConfigure for ScoredDevicesConfigure {
+    fn pick<B>(&self, adapters: &[rendy_core::hal::adapter::Adapter<B>]) -> usize
+    where
+        B: rendy_core::hal::Backend,
+    {
+        let (index, score) =
+            best_score(adapters.iter().map(|adapter| self.score(adapter)).collect())
+                .expect("No adapters present");
+
+        if !score.0 {
+            log::warn!(
+                "No adapter satisfies requirements. Picking `{}` anyway",
+                adapters[index].info.name
+            );
+        }
+        index
+    }
+}
+
+/// Saved config to pick adapter.
+/// This config can be loaded from config files
+/// in any format supported by serde ecosystem.
+///
+/// Picks first adapter that matches all specified `vendor` and `device` ids
+/// and has `name` substring in its name, ignoring case.
+/// If no adapter matches uses `fallback` config.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct SavedDevicesConfig {
+    /// PCI vendor id of the adapter.
+    pub vendor: Option<usize>,
+
+    /// PCI device id of the adapter.
+    pub device: Option<usize>,
+
+    /// Substring of the adapter name.
+    pub name: Option<String>,
+
+    /// Config to pick adapter if none matches.
+    pub fallback: ScoredDevicesConfigure,
+}
+
+impl SavedDevicesConfig {
+    fn matches(&self, info: &rendy_core::hal::adapter::AdapterInfo) -> bool {
+        self.vendor.map_or(true, |vendor| vendor == info.vendor)
+            && self.device.map_or(true, |device| device == info.device)
+            && self.name.as_ref().map_or(true, |name| {
+                info.name.to_lowercase().contains(&name.to_lowercase())
+            })
+    }
+}
+
+impl DevicesConfigure for SavedDevicesConfig {
+    fn pick<B>(&self, adapters: &[rendy_core::hal::adapter::Adapter<B>]) -> usize
+    where
+        B: rendy_core::hal::Backend,
+    {
+        match adapters
+            .iter()
+            .position(|adapter| self.matches(&adapter.info))
+        {
+            Some(index) => index,
+            None => {
+                log::warn!("No adapter matches saved config {:?}", self);
+                self.fallback.pick(adapters)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::core::InstanceId,
+        rendy_core::hal::{
+            adapter::{AdapterInfo, DeviceType},
+            queue::{QueueFamily, QueueFamilyId, QueueType},
+            Features, Limits,
+        },
+    };
+
+    #[derive(Debug)]
+    struct TestFamily(QueueType, usize, usize);
+
+    impl QueueFamily for TestFamily {
+        fn queue_type(&self) -> QueueType {
+            self.0
+        }
+        fn max_queues(&self) -> usize {
+            self.1
+        }
+        fn id(&self) -> QueueFamilyId {
+            QueueFamilyId(self.2)
+        }
+    }
+
+    fn indices<P: AsRef<[f32]>>(
+        families: impl IntoIterator<Item = (FamilyId, P)>,
+    ) -> Vec<(usize, usize)> {
+        families
+            .into_iter()
+            .map(|(id, priorities)| (id.index, priorities.as_ref().len()))
+            .collect()
+    }
+
+    #[test]
+    fn multiple_queues_picks_dedicated_families() {
+        let device = DeviceId::new(InstanceId::new());
+        let families = [
+            TestFamily(QueueType::General, 16, 0),
+            TestFamily(QueueType::Transfer, 2, 1),
+            TestFamily(QueueType::Compute, 8, 2),
+        ];
+        let config = MultipleQueues {
+            graphics: vec![1.0, 0.5],
+            compute: vec![1.0; 10],
+            transfer: vec![1.0],
+            transfer_uploads: true,
+        };
+
+        assert_eq!(
+            indices(config.configure(device, &families)),
+            vec![(0, 2), (2, 8), (1, 1)]
+        );
+    }
+
+    #[test]
+    fn multiple_queues_skips_missing_families() {
+        let device = DeviceId::new(InstanceId::new());
+        let families = [
+            TestFamily(QueueType::Compute, 8, 0),
+            TestFamily(QueueType::General, 16, 1),
+            TestFamily(QueueType::Transfer, 2, 2),
+        ];
+        let config = MultipleQueues {
+            transfer: Vec::new(),
+            ..MultipleQueues::default()
+        };
+
+        // Family ids are sparse if some families are not picked.
+        assert_eq!(
+            indices(config.configure(device, &families)),
+            vec![(1, 1), (0, 1)]
+        );
+        assert_eq!(
+            indices(config.configure(device, &families[1..2])),
+            vec![(1, 1)]
+        );
+    }
+
+    #[test]
+    fn one_graphics_queue() {
+        let device = DeviceId::new(InstanceId::new());
+        let families = [
+            TestFamily(QueueType::Transfer, 2, 0),
+            TestFamily(QueueType::Graphics, 0, 1),
+            TestFamily(QueueType::General, 16, 2),
+        ];
+        assert_eq!(
+            indices(OneGraphicsQueue.configure(device, &families)),
+            vec![(2, 1)]
+        );
+    }
+
+    fn adapter_info(vendor: usize, device: usize, name: &str) -> AdapterInfo {
+        AdapterInfo {
+            name: name.into(),
+            vendor,
+            device,
+            device_type: DeviceType::DiscreteGpu,
+        }
+    }
+
+    #[test]
+    fn required_limits() {
+        let limits = Limits {
+            max_image_2d_size: 4096,
+            max_push_constants_size: 128,
+            ..Limits::default()
+        };
+        let mut required = RequiredLimits {
+            max_image_2d_size: 4096,
+            max_push_constants_size: 128,
+            ..RequiredLimits::default()
+        };
+        assert!(RequiredLimits::default().satisfied_by(&limits));
+        assert!(required.satisfied_by(&limits));
+
+        required.max_push_constants_size = 256;
+        assert!(!required.satisfied_by(&limits));
+    }
+
+    #[test]
+    fn scored_ranking() {
+        let config = ScoredDevicesConfigure::default();
+        let scores = vec![
+            config.rank(true, DeviceType::Cpu, 0),
+            config.rank(true, DeviceType::IntegratedGpu, 1 << 30),
+            config.rank(true, DeviceType::DiscreteGpu, 1 << 30),
+            config.rank(true, DeviceType::DiscreteGpu, 4 << 30),
+            config.rank(false, DeviceType::DiscreteGpu, 8 << 30),
+        ];
+
+        // Type goes before memory size, unsuitable adapter goes last.
+        let mut order: Vec<_> = (0..scores.len()).collect();
+        order.sort_by_key(|&index| std::cmp::Reverse(scores[index]));
+        assert_eq!(order, vec![3, 2, 1, 0, 4]);
+        assert_eq!(best_score(scores).map(|(index, _)| index), Some(3));
+
+        // Earlier adapter wins on equal score.
+        let equal = vec![config.rank(true, DeviceType::DiscreteGpu, 1 << 30); 3];
+        assert_eq!(best_score(equal).map(|(index, _)| index), Some(0));
+
+        // Unsuitable adapter is picked if no adapter is suitable.
+        let unsuitable = vec![
+            config.rank(false, DeviceType::Cpu, 0),
+            config.rank(false, DeviceType::DiscreteGpu, 0),
+        ];
+        assert_eq!(best_score(unsuitable).map(|(index, _)| index), Some(1));
+    }
+
+    #[test]
+    fn scored_failing_limit() {
+        let config = ScoredDevicesConfigure {
+            required_limits: RequiredLimits {
+                max_bound_descriptor_sets: 8,
+                ..RequiredLimits::default()
+            },
+            ..ScoredDevicesConfigure::default()
+        };
+        let limits = Limits {
+            max_bound_descriptor_sets: 4,
+            ..Limits::default()
+        };
+        assert!(!config.supports(Features::empty(), &limits));
+        assert!(config.supports(
+            Features::empty(),
+            &Limits {
+                max_bound_descriptor_sets: 8,
+                ..limits
+            }
+        ));
+    }
+
+    #[test]
+    fn saved_matches_ids() {
+        let info = adapter_info(0x10de, 0x1b80, "GeForce GTX 1080");
+        let mut config = SavedDevicesConfig::default();
+        assert!(config.matches(&info));
+
+        config.vendor = Some(0x10de);
+        assert!(config.matches(&info));
+
+        config.device = Some(0x1b81);
+        assert!(!config.matches(&info));
+
+        config.device = Some(0x1b80);
+        assert!(config.matches(&info));
+    }
+
+    #[test]
+    fn saved_matches_name_substring() {
+        let info = adapter_info(0x10de, 0x1b80, "GeForce GTX 1080");
+        let mut config = SavedDevicesConfig {
+            name: Some("gtx 10".into()),
+            ..SavedDevicesConfig::default()
+        };
+        assert!(config.matches(&info));
+
+        config.name = Some("Radeon".into());
+        assert!(!config.matches(&info));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn saved_serde_round_trip() {
+        let config = SavedDevicesConfig {
+            vendor: Some(0x1002),
+            device: None,
+            name: Some("Radeon".into()),
+            fallback: ScoredDevicesConfigure {
+                device_types: vec![DeviceType::IntegratedGpu],
+                required_limits: RequiredLimits {
+                    max_image_2d_size: 8192,
+                    ..RequiredLimits::default()
+                },
+                ..ScoredDevicesConfigure::default()
+            },
+        };
+        let json = serde_json::to_string(&config).unwrap();
+        let loaded: SavedDevicesConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.vendor, config.vendor);
+        assert_eq!(loaded.device, config.device);
+        assert_eq!(loaded.name, config.name);
+        assert_eq!(loaded.fallback.device_types, config.fallback.device_types);
+        assert_eq!(
+            loaded.fallback.required_limits.max_image_2d_size,
+            config.fallback.required_limits.max_image_2d_size
+        );
+
+        // Omitted fields take defaults.
+        let loaded: SavedDevicesConfig = serde_json::from_str(r#"{"name":"GTX"}"#).unwrap();
+        assert_eq!(loaded.name.as_ref().map(String::as_str), Some("GTX"));
+        assert_eq!(loaded.vendor, None);
+        assert_eq!(
+            loaded.fallback.device_types,
+            ScoredDevicesConfigure::default().device_types
+        );
+    }
+}
@@ -1,15 +1,21 @@
 use {
     crate::{
-        blitter::Blitter,
+        blitter::{Blitter, MipsError},
         command::{
-            families_from_device, CommandPool, Families, Family, FamilyId, Fence, QueueType, Reset,
+            families_from_device, CommandPool, Families, Family, FamilyId, Fence, QueueId,
+            QueueType, Reset,
         },
         config::{Config, DevicesConfigure, HeapsConfigure, QueuesConfigure},
         core::{rendy_with_slow_safety_checks, Device, DeviceId, Instance, InstanceId},
         descriptor::DescriptorAllocator,
-        memory::{self, Heaps, MemoryUsage, TotalMemoryUtilization, Write},
+        live::LiveResourceInfo,
+        memory::{self, BlockCache, Heaps, MemoryUsage, TotalMemoryUtilization, Write},
+        mips, pipeline_cache,
         resource::*,
-        upload::{BufferState, ImageState, ImageStateOrLayout, Uploader},
+        upload::{
+            check_queue, BufferState, DownloadLayout, ImageState, ImageStateOrLayout,
+            PendingDownload, SharedStaging, UploadToken, Uploader,
+        },
         wsi::{Surface, SwapchainError, Target},
     },
     rendy_core::{
@@ -21,7 +27,8 @@ use {
                 OutOfMemory, WaitFor,
             },
             format, image,
-            pso::DescriptorSetLayoutBinding,
+            pso::{ComputePipelineDesc, DescriptorSetLayoutBinding, GraphicsPipelineDesc},
+            queue::QueueFamily as _,
             window::{Extent2D, InitError, Surface as GfxSurface},
             Backend, Features, Instance as _, Limits,
         },
@@ -29,9 +36,13 @@ use {
     },
     smallvec::SmallVec,
     std::{borrow::BorrowMut, cmp::max, mem::ManuallyDrop},
+    thread_local::ThreadLocal,
     thread_profiler::profile_scope,
 };
 
+#[cfg(feature = "live-resources")]
+use crate::live::{LiveResource, LiveResources};
+
 #[derive(Debug)]
 struct ResourceHub<B: Backend> {
     buffers: ResourceTracker<Buffer<B>>,
@@ -69,20 +80,67 @@ where
         device: &Device<B>,
         heaps: &mut Heaps<B>,
         allocator: &mut DescriptorAllocator<B>,
+        cache: Option<&mut BlockCache<B>>,
+        mut untrack: impl FnMut(ResourceId),
         next: Epochs,
         complete: Epochs,
     ) {
-        self.sets
-            .cleanup(|s| s.dispose(allocator), &next, &complete);
-        self.views.cleanup(|v| v.dispose(device), &next, &complete);
-        self.layouts
-            .cleanup(|l| l.dispose(device), &next, &complete);
-        self.buffers
-            .cleanup(|b| b.dispose(device, heaps), &next, &complete);
-        self.images
-            .cleanup(|i| i.dispose(device, heaps), &next, &complete);
-        self.samplers
-            .cleanup(|i| i.dispose(device), &next, &complete);
+        let mut cache = cache;
+        let mut free = |block: memory::MemoryBlock<B>| {
+            let heaps = &mut *heaps;
+            match &mut cache {
+                Some(cache) => cache.free(device, move || heaps, block),
+                None => heaps.free(device, block),
+            }
+        };
+        self.sets.cleanup(
+            |s| {
+                untrack(s.id());
+                s.dispose(allocator)
+            },
+            &next,
+            &complete,
+        );
+        self.views.cleanup(
+            |v| {
+                untrack(v.id());
+                v.dispose(device)
+            },
+            &next,
+            &complete,
+        );
+        self.layouts.cleanup(
+            |l| {
+                untrack(l.id());
+                l.dispose(device)
+            },
+            &next,
+            &complete,
+        );
+        self.buffers.cleanup(
+            |b| {
+                untrack(b.id());
+                b.dispose_with(device, &mut free)
+            },
+            &next,
+            &complete,
+        );
+        self.images.cleanup(
+            |i| {
+                untrack(i.id());
+                i.dispose_with(device, &mut free)
+            },
+            &next,
+            &complete,
+        );
+        self.samplers.cleanup(
+            |i| {
+                untrack(i.id());
+                i.dispose(device)
+            },
+            &next,
+            &complete,
+        );
     }
 
     unsafe fn dispose(
@@ -110,6 +168,15 @@ pub enum UploadError {
     Map(MapError),
     /// Failed to upload the data.
     Upload(OutOfMemory),
+    /// Resource can't be synchronized between queues.
+    QueueMismatch {
+        /// Queue that used the resource last.
+        last: QueueId,
+        /// Queue that will use the resource next.
+        next: QueueId,
+    },
+    /// Family of the queue doesn't support the operation.
+    UnsupportedQueue(QueueId),
 }
 
 impl std::fmt::Display for UploadError {
@@ -118,6 +185,16 @@ impl std::fmt::Display for UploadError {
             UploadError::Create(err) => write!(fmt, "Upload failed: {:?}", err),
             UploadError::Map(err) => write!(fmt, "Upload failed: {:?}", err),
             UploadError::Upload(err) => write!(fmt, "Upload failed: {:?}", err),
+            UploadError::QueueMismatch { last, next } => write!(
+                fmt,
+                "Upload failed: can't sync resource from {:?} to {:?}",
+                last, next
+            ),
+            UploadError::UnsupportedQueue(queue) => write!(
+                fmt,
+                "Upload failed: operation is not supported by {:?}",
+                queue
+            ),
         }
     }
 }
@@ -128,6 +205,7 @@ impl std::error::Error for UploadError {
             UploadError::Create(err) => Some(err),
             UploadError::Map(err) => Some(err),
             UploadError::Upload(err) => Some(err),
+            UploadError::QueueMismatch { .. } | UploadError::UnsupportedQueue(_) => None,
         }
     }
 }
@@ -163,16 +241,37 @@ where
 pub struct Factory<B: Backend> {
     descriptor_allocator: ManuallyDrop<parking_lot::Mutex<DescriptorAllocator<B>>>,
     heaps: ManuallyDrop<parking_lot::Mutex<Heaps<B>>>,
+    block_caches: BlockCaches<B>,
     resources: ManuallyDrop<ResourceHub<B>>,
+    #[cfg(feature = "live-resources")]
+    live: parking_lot::Mutex<LiveResources>,
+    cleanup_epoch: u64,
     epochs: Vec<parking_lot::RwLock<Vec<u64>>>,
     uploader: Uploader<B>,
     blitter: Blitter<B>,
+    pipeline_cache: Option<B::PipelineCache>,
     families_indices: Vec<usize>,
     device: Device<B>,
     adapter: Adapter<B>,
     instance: InstanceOrId<B>,
 }
 
+/// Per-thread block caches with their config.
+type BlockCaches<B> = Option<(
+    memory::BlockCacheConfig,
+    ThreadLocal<parking_lot::Mutex<BlockCache<B>>>,
+)>;
+
+/// Get block cache of the calling thread if caches are enabled.
+fn thread_block_cache<'a, B: Backend>(
+    caches: &'a BlockCaches<B>,
+    heaps: &parking_lot::Mutex<Heaps<B>>,
+) -> Option<&'a parking_lot::Mutex<BlockCache<B>>> {
+    caches.as_ref().map(|(config, caches)| {
+        caches.get_or(|| parking_lot::Mutex::new(BlockCache::new(&heaps.lock(), *config)))
+    })
+}
+
 #[allow(unused)]
 fn factory_is_send_sync<B: Backend>() {
     fn is_send_sync<T: Send + Sync>() {}
@@ -193,6 +292,10 @@ where
             log::trace!("Uploader disposed");
             self.blitter.dispose(&self.device);
             log::trace!("Blitter disposed");
+            if let Some(cache) = self.pipeline_cache.take() {
+                self.device.destroy_pipeline_cache(cache);
+                log::trace!("Pipeline cache destroyed");
+            }
             std::ptr::read(&mut *self.resources).dispose(
                 &self.device,
                 self.heaps.get_mut(),
@@ -200,6 +303,9 @@ where
             );
 
             log::trace!("Resources disposed");
+
+            self.trim_block_caches();
+            log::trace!("Block caches trimmed");
         }
 
         unsafe {
@@ -249,7 +355,83 @@ where
     ) -> Result<Buffer<B>, BufferCreationError> {
         profile_scope!("create_relevant_buffer");
 
-        unsafe { Buffer::create(&self.device, &mut self.heaps.lock(), info, memory_usage) }
+        let buffer = match thread_block_cache(&self.block_caches, &self.heaps) {
+            Some(cache) => unsafe {
+                log::trace!("{:#?}@{:#?}", info, memory_usage);
+                Buffer::create_with(&self.device, info, |reqs| {
+                    cache.lock().allocate(
+                        &self.device,
+                        || self.heaps.lock(),
+                        reqs.type_mask as u32,
+                        memory_usage,
+                        reqs.size,
+                        reqs.alignment,
+                    )
+                })
+            },
+            None => unsafe {
+                Buffer::create(&self.device, &mut self.heaps.lock(), info, memory_usage)
+            },
+        }?;
+
+        self.track(
+            buffer.id(),
+            || LiveResourceInfo::Buffer(buffer.info().clone()),
+            Some(buffer.block()),
+        );
+        Ok(buffer)
+    }
+
+    /// Record resource in the registry of live resources.
+    /// `info` is called only if registry is enabled.
+    fn track(
+        &self,
+        id: ResourceId,
+        info: impl FnOnce() -> LiveResourceInfo,
+        block: Option<&memory::MemoryBlock<B>>,
+    ) {
+        #[cfg(feature = "live-resources")]
+        self.live
+            .lock()
+            .track(id, info(), block, self.cleanup_epoch);
+
+        #[cfg(not(feature = "live-resources"))]
+        let _ = (id, info, block);
+    }
+
+    /// Remove destroyed resource from the registry of live resources.
+    fn untrack(&self, id: ResourceId) {
+        #[cfg(feature = "live-resources")]
+        self.live.lock().untrack(id);
+
+        #[cfg(not(feature = "live-resources"))]
+        let _ = id;
+    }
+
+    /// Free memory block through the calling thread's block cache if caches are enabled.
+    fn free_block(&self, block: memory::MemoryBlock<B>) {
+        match thread_block_cache(&self.block_caches, &self.heaps) {
+            Some(cache) => cache.lock().free(&self.device, || self.heaps.lock(), block),
+            None => self.heaps.lock().free(&self.device, block),
+        }
+    }
+
+    /// Return blocks reserved by per-thread block caches back to the heaps.
+    /// Caches are enabled with [`HeapsConfigure::block_cache`].
+    ///
+    /// Blocks freed through caches are returned by [`cleanup`] anyway,
+    /// this function also releases blocks reserved for future allocations,
+    /// e.g. when memory budget is tight.
+    ///
+    /// [`HeapsConfigure::block_cache`]: trait.HeapsConfigure.html#method.block_cache
+    /// [`cleanup`]: #method.cleanup
+    pub fn trim_block_caches(&self) {
+        if let Some((_, caches)) = &self.block_caches {
+            for cache in caches.iter() {
+                // Cache is always locked before heaps.
+                cache.lock().trim(&self.device, &mut self.heaps.lock());
+            }
+        }
     }
 
     /// Destroy buffer.
@@ -263,14 +445,17 @@ where
     ///
     /// [`create_buffer`]: #method.create_buffer
     pub unsafe fn destroy_relevant_buffer(&self, buffer: Buffer<B>) {
-        buffer.dispose(&self.device, &mut self.heaps.lock());
+        self.untrack(buffer.id());
+        buffer.dispose_with(&self.device, |block| self.free_block(block));
     }
 
     /// Creates a buffer with the specified properties.
     ///
     /// This function (unlike [`create_relevant_buffer`]) returns value that can be dropped.
+    /// See [`DedicatedHint`] to request or forbid dedicated memory object.
     ///
     /// [`create_relevant_buffer`]: #method.create_relevant_buffer
+    /// [`DedicatedHint`]: ../rendy_memory/enum.DedicatedHint.html
     pub fn create_buffer(
         &self,
         info: BufferInfo,
@@ -280,6 +465,24 @@ where
         Ok(self.resources.buffers.escape(buffer))
     }
 
+    /// Creates a buffer with the specified properties and debug name.
+    ///
+    /// Same as [`create_buffer`] followed by [`set_name`],
+    /// so buffer is named before it can be shared with `Escape::share`.
+    ///
+    /// [`create_buffer`]: #method.create_buffer
+    /// [`set_name`]: #method.set_name
+    pub fn create_named_buffer(
+        &self,
+        info: BufferInfo,
+        memory_usage: impl MemoryUsage,
+        name: &str,
+    ) -> Result<Escape<Buffer<B>>, BufferCreationError> {
+        let mut buffer = self.create_buffer(info, memory_usage)?;
+        self.set_name(&mut buffer, name);
+        Ok(buffer)
+    }
+
     /// Creates an image with the specified properties.
     ///
     /// This function returns relevant value, that is, the value cannot be dropped.
@@ -293,7 +496,33 @@ where
     ) -> Result<Image<B>, ImageCreationError> {
         profile_scope!("create_relevant_image");
 
-        unsafe { Image::create(&self.device, &mut self.heaps.lock(), info, memory_usage) }
+        let image = match thread_block_cache(&self.block_caches, &self.heaps) {
+            Some(cache) => unsafe {
+                log::trace!("{:#?}@{:#?}", info, memory_usage);
+                Image::create_with(&self.device, info, |reqs| {
+                    let block = cache.lock().allocate(
+                        &self.device,
+                        || self.heaps.lock(),
+                        reqs.type_mask as u32,
+                        memory_usage,
+                        reqs.size,
+                        reqs.alignment,
+                    )?;
+                    // Images are never relocated.
+                    self.heaps.lock().pin(&block);
+                    Ok(block)
+                })
+            },
+            None => unsafe {
+                Image::create(&self.device, &mut self.heaps.lock(), info, memory_usage)
+            },
+        }?;
+        self.track(
+            image.id(),
+            || LiveResourceInfo::Image(image.info().clone()),
+            image.block(),
+        );
+        Ok(image)
     }
 
     /// Destroy image.
@@ -307,14 +536,17 @@ where
     ///
     /// [`create_image`]: #method.create_image
     pub unsafe fn destroy_relevant_image(&self, image: Image<B>) {
-        image.dispose(&self.device, &mut self.heaps.lock());
+        self.untrack(image.id());
+        image.dispose_with(&self.device, |block| self.free_block(block));
     }
 
     /// Creates an image with the specified properties.
     ///
     /// This function (unlike [`create_relevant_image`]) returns value that can be dropped.
+    /// See [`DedicatedHint`] to request or forbid dedicated memory object.
     ///
     /// [`create_relevant_image`]: #method.create_relevant_image
+    /// [`DedicatedHint`]: ../rendy_memory/enum.DedicatedHint.html
     pub fn create_image(
         &self,
         info: ImageInfo,
@@ -324,6 +556,24 @@ where
         Ok(self.resources.images.escape(image))
     }
 
+    /// Creates an image with the specified properties and debug name.
+    ///
+    /// Same as [`create_image`] followed by [`set_name`],
+    /// so image is named before it can be shared with `Escape::share`.
+    ///
+    /// [`create_image`]: #method.create_image
+    /// [`set_name`]: #method.set_name
+    pub fn create_named_image(
+        &self,
+        info: ImageInfo,
+        memory_usage: impl MemoryUsage,
+        name: &str,
+    ) -> Result<Escape<Image<B>>, ImageCreationError> {
+        let mut image = self.create_image(info, memory_usage)?;
+        self.set_name(&mut image, name);
+        Ok(image)
+    }
+
     /// Fetch image format details for a particular `ImageInfo`.
     pub fn image_format_properties(&self, info: ImageInfo) -> Option<FormatProperties> {
         self.physical().image_format_properties(
@@ -339,6 +589,28 @@ where
         )
     }
 
+    /// Check if image with `info` can be blitted with `filter`,
+    /// as required by [`Blitter::fill_mips`].
+    ///
+    /// [`Blitter::fill_mips`]: struct.Blitter.html#method.fill_mips
+    pub fn supports_blit(&self, mut info: ImageInfo, filter: image::Filter) -> bool {
+        info.usage |= image::Usage::TRANSFER_SRC | image::Usage::TRANSFER_DST;
+        if self.image_format_properties(info).is_none() {
+            return false;
+        }
+
+        let properties = self.physical().format_properties(Some(info.format));
+        let features = match info.tiling {
+            image::Tiling::Optimal => properties.optimal_tiling,
+            image::Tiling::Linear => properties.linear_tiling,
+        };
+        let mut required = format::ImageFeature::BLIT_SRC | format::ImageFeature::BLIT_DST;
+        if filter == image::Filter::Linear {
+            required |= format::ImageFeature::SAMPLED_LINEAR;
+        }
+        features.contains(required)
+    }
+
     /// Create an image view with the specified properties
     ///
     /// This function returns relevant value, that is, the value cannot be dropped.
@@ -350,7 +622,13 @@ where
         image: Handle<Image<B>>,
         info: ImageViewInfo,
     ) -> Result<ImageView<B>, ImageViewCreationError> {
-        ImageView::create(&self.device, info, image)
+        let view = ImageView::create(&self.device, info, image)?;
+        self.track(
+            view.id(),
+            || LiveResourceInfo::ImageView(view.info().clone()),
+            None,
+        );
+        Ok(view)
     }
 
     /// Destroy image view.
@@ -364,6 +642,7 @@ where
     ///
     /// [`create_image_view`]: #method.create_image_view
     pub unsafe fn destroy_relevant_image_view(&self, view: ImageView<B>) {
+        self.untrack(view.id());
         view.dispose(&self.device);
     }
 
@@ -391,7 +670,13 @@ where
         &self,
         info: SamplerDesc,
     ) -> Result<Sampler<B>, AllocationError> {
-        Sampler::create(&self.device, info)
+        let sampler = Sampler::create(&self.device, info)?;
+        self.track(
+            sampler.id(),
+            || LiveResourceInfo::Sampler(sampler.info().clone()),
+            None,
+        );
+        Ok(sampler)
     }
 
     /// Destroy sampler.
@@ -408,6 +693,7 @@ where
     /// [`create_sampler`]: #method.create_sampler
     /// [`get_sampler`]: #method.get_sampler
     pub unsafe fn destroy_relevant_sampler(&self, sampler: Sampler<B>) {
+        self.untrack(sampler.id());
         sampler.dispose(&self.device);
     }
 
@@ -429,13 +715,12 @@ where
     /// [`create_relevant_sampler`]: #method.create_relevant_sampler
     pub fn get_sampler(&self, info: SamplerDesc) -> Result<Handle<Sampler<B>>, AllocationError> {
         let samplers = &self.resources.samplers;
-        let device = &self.device;
 
         SamplerCache::get_with_upgradable_lock(
             self.resources.samplers_cache.upgradable_read(),
             parking_lot::RwLockUpgradableReadGuard::upgrade,
             info.clone(),
-            || Ok(samplers.handle(Sampler::create(device, info)?)),
+            || Ok(samplers.handle(self.create_relevant_sampler(info)?)),
         )
     }
 
@@ -478,6 +763,22 @@ where
         Ok(())
     }
 
+    /// Map whole buffer as typed slice that tracks host writes
+    /// and flushes them on `commit` respecting `non_coherent_atom_size` of the device.
+    /// Buffer must be bound to CPU visible memory.
+    pub fn map_buffer<'a, T>(
+        &self,
+        buffer: &'a mut Buffer<B>,
+    ) -> Result<MappedBuffer<'a, B, T>, MapError>
+    where
+        T: Copy,
+    {
+        buffer.map_typed(
+            &self.device,
+            self.physical().limits().non_coherent_atom_size as u64,
+        )
+    }
+
     /// Update buffer range content with provided data.
     ///
     /// Update operation will actually be submitted to the graphics device queue
@@ -493,6 +794,9 @@ where
     ///
     /// `#[repr(C)]` can be used to guarantee defined memory layout of struct fields.
     ///
+    /// Returned `UploadToken` can be used to check when the content becomes resident
+    /// with [`is_upload_complete`](#method.is_upload_complete).
+    ///
     /// # Safety
     ///
     /// If buffer is used by device then `last` state must match the last usage state of the buffer
@@ -506,13 +810,27 @@ where
         content: &[T],
         last: Option<BufferState>,
         next: BufferState,
-    ) -> Result<(), UploadError>
+    ) -> Result<UploadToken, UploadError>
     where
         T: 'static + Copy,
     {
         assert!(buffer.info().usage.contains(buffer::Usage::TRANSFER_DST));
 
         let content_size = content.len() as u64 * std::mem::size_of::<T>() as u64;
+        let bytes =
+            std::slice::from_raw_parts(content.as_ptr() as *const u8, content_size as usize);
+        if let Some(token) = self.uploader.upload_buffer_content(
+            &self.device,
+            buffer,
+            offset,
+            bytes,
+            last,
+            next,
+            |size| self.create_staging_chunk(size),
+        )? {
+            return Ok(token);
+        }
+
         let mut staging = self
             .create_buffer(
                 BufferInfo {
@@ -531,6 +849,19 @@ where
             .map_err(UploadError::Upload)
     }
 
+    /// Create buffer for the staging ring of the uploader.
+    /// Chunks live as long as the `Factory`,
+    /// so they get dedicated memory instead of pinning memory of linear allocator.
+    fn create_staging_chunk(&self, size: u64) -> Result<Escape<Buffer<B>>, BufferCreationError> {
+        self.create_buffer(
+            BufferInfo {
+                size,
+                usage: buffer::Usage::TRANSFER_SRC,
+            },
+            memory::Upload.prefer_dedicated(),
+        )
+    }
+
     /// Update buffer content with provided staging buffer.
     ///
     /// Update operation will actually be submitted to the graphics device queue
@@ -539,6 +870,9 @@ where
     /// submitted to the same graphics queue on this `Factory` since last
     /// [`flush_uploads`] or [`maintain`] call
     ///
+    /// Returned `UploadToken` can be used to check when the content becomes resident
+    /// with [`is_upload_complete`](#method.is_upload_complete).
+    ///
     /// # Safety
     ///
     /// If buffer is used by device then `last` state must match the last usage state of the buffer
@@ -552,7 +886,7 @@ where
         staging: Escape<Buffer<B>>,
         last: Option<BufferState>,
         next: BufferState,
-    ) -> Result<(), OutOfMemory> {
+    ) -> Result<UploadToken, OutOfMemory> {
         assert!(buffer.info().usage.contains(buffer::Usage::TRANSFER_DST));
         assert!(staging.info().usage.contains(buffer::Usage::TRANSFER_SRC));
         self.uploader
@@ -584,6 +918,97 @@ where
             .transition_image(image, image_range, last.into(), next);
     }
 
+    /// Clear part of image with color or depth-stencil `value`
+    /// and transition it to `next` state.
+    /// Which of `value` fields is used depends on aspects of the `image_range`.
+    ///
+    /// Clear operation will actually be submitted to the device queue
+    /// upon next [`flush_uploads`] or [`maintain`] call to this `Factory`
+    /// together with uploads to the same queue.
+    ///
+    /// Returned `UploadToken` can be used to check when the clear is complete
+    /// with [`is_upload_complete`](#method.is_upload_complete).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`UploadError::UnsupportedQueue`] if family of the `next` queue doesn't support graphics.
+    /// Returns [`UploadError::QueueMismatch`] if `last` state is on a queue other than the `next` one.
+    ///
+    /// # Safety
+    ///
+    /// Image must be created by this `Factory`.
+    /// If image is used by device then `last` state must match the last usage state of the image
+    /// before clearing.
+    ///
+    /// [`UploadError::UnsupportedQueue`]: enum.UploadError.html#variant.UnsupportedQueue
+    /// [`UploadError::QueueMismatch`]: enum.UploadError.html#variant.QueueMismatch
+    pub unsafe fn clear_image(
+        &self,
+        image: Handle<Image<B>>,
+        image_range: SubresourceRange,
+        value: rendy_core::hal::command::ClearValue,
+        last: impl Into<ImageStateOrLayout>,
+        next: ImageState,
+    ) -> Result<UploadToken, UploadError> {
+        assert!(image.info().usage.contains(image::Usage::TRANSFER_DST));
+        assert!(image_range.levels.end <= image.levels());
+        assert!(image_range.layers.end <= image.layers());
+
+        // Clear commands require graphics capability.
+        if !self.adapter.queue_families[next.queue.family.index]
+            .queue_type()
+            .supports_graphics()
+        {
+            return Err(UploadError::UnsupportedQueue(next.queue));
+        }
+
+        let last = last.into();
+        check_queue(last.queue(), next.queue)?;
+
+        self.uploader
+            .clear_image(&self.device, image, image_range, value, last, next)
+            .map_err(UploadError::Upload)
+    }
+
+    /// Fill buffer range with repeated 4-byte `data`.
+    ///
+    /// Fill operation will actually be submitted to the device queue
+    /// upon next [`flush_uploads`] or [`maintain`] call to this `Factory`
+    /// together with uploads to the same queue.
+    ///
+    /// Returned `UploadToken` can be used to check when the fill is complete
+    /// with [`is_upload_complete`](#method.is_upload_complete).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`UploadError::QueueMismatch`] if `last` state is on a queue other than the `next` one.
+    ///
+    /// # Safety
+    ///
+    /// Buffer must be created by this `Factory`.
+    /// If buffer is used by device then `last` state must match the last usage state of the buffer
+    /// before filling.
+    ///
+    /// [`UploadError::QueueMismatch`]: enum.UploadError.html#variant.QueueMismatch
+    pub unsafe fn fill_buffer(
+        &self,
+        buffer: &Buffer<B>,
+        range: std::ops::Range<u64>,
+        data: u32,
+        last: Option<BufferState>,
+        next: BufferState,
+    ) -> Result<UploadToken, UploadError> {
+        assert!(buffer.info().usage.contains(buffer::Usage::TRANSFER_DST));
+        assert!(range.start <= range.end && range.end <= buffer.size());
+        assert_eq!(range.start % 4, 0, "Fill offset must be multiple of 4");
+        assert_eq!(range.end % 4, 0, "Fill size must be multiple of 4");
+        check_queue(last.map(|l| l.queue), next.queue)?;
+
+        self.uploader
+            .fill_buffer(&self.device, buffer, range, data, last, next)
+            .map_err(UploadError::Upload)
+    }
+
     /// Update image layers content with provided data.
     ///
     /// Update operation will actually be submitted to the graphics device queue
@@ -598,6 +1023,9 @@ where
     /// It usually should be compatible type of pixel or channel.
     /// For example `&[[u8; 4]]` or `&[u8]` for `Rgba8Unorm` format.
     ///
+    /// Returned `UploadToken` can be used to check when the content becomes resident
+    /// with [`is_upload_complete`](#method.is_upload_complete).
+    ///
     /// # Safety
     ///
     /// Image must be created by this `Factory`.
@@ -616,7 +1044,7 @@ where
         content: &[T],
         last: impl Into<ImageStateOrLayout>,
         next: ImageState,
-    ) -> Result<(), UploadError>
+    ) -> Result<UploadToken, UploadError>
     where
         T: 'static + Copy,
     {
@@ -638,6 +1066,25 @@ where
             "Size of must match size of the image region"
         );
 
+        let last = last.into();
+        let bytes =
+            std::slice::from_raw_parts(content.as_ptr() as *const u8, content_size as usize);
+        if let Some(token) = self.uploader.upload_image_content(
+            &self.device,
+            image.clone(),
+            data_width,
+            data_height,
+            image_layers.clone(),
+            image_offset,
+            image_extent,
+            bytes,
+            last,
+            next,
+            |size| self.create_staging_chunk(size),
+        )? {
+            return Ok(token);
+        }
+
         let mut staging = self
             .create_buffer(
                 BufferInfo {
@@ -661,12 +1108,428 @@ where
                 image_offset,
                 image_extent,
                 staging,
-                last.into(),
+                last,
                 next,
             )
             .map_err(UploadError::Upload)
     }
 
+    /// Fill all mip levels from the first level of provided image.
+    ///
+    /// Levels are blitted with [`Blitter::fill_mips`] if image format supports it.
+    /// Otherwise levels are downsampled on the host from `content` of the first level
+    /// and uploaded, which is slower but works for integer and other formats that can't be blitted.
+    /// `content` must be the same that was uploaded to the first level of all image layers,
+    /// with rows of `data_width` texels and images of `data_height` rows as in [`upload_image`].
+    /// Fails with `MipsError::InsufficientContent` if `content` is too short for the image,
+    /// and with `MipsError::Unsupported` if format can be neither blitted nor downsampled.
+    ///
+    /// [`Blitter::fill_mips`]: struct.Blitter.html#method.fill_mips
+    /// [`upload_image`]: #method.upload_image
+    ///
+    /// # Safety
+    ///
+    /// Image must be created by this `Factory`.
+    /// `last` state must be valid for corresponding image level at the time of command execution.
+    /// `last` and `next` should contain at least `image.levels()` elements.
+    /// `image.levels()` must be greater than 1.
+    pub unsafe fn fill_mips(
+        &self,
+        image: Handle<Image<B>>,
+        filter: image::Filter,
+        data_width: u32,
+        data_height: u32,
+        content: &[u8],
+        last: impl IntoIterator<Item = ImageState>,
+        next: impl IntoIterator<Item = ImageState>,
+    ) -> Result<(), MipsError> {
+        let info = *image.info();
+        assert!(info.levels > 1);
+
+        if self.supports_blit(info, filter) {
+            return self
+                .blitter
+                .fill_mips(&self.device, image, filter, last, next)
+                .map_err(MipsError::Blit);
+        }
+
+        if !mips::can_downsample(info.format) {
+            return Err(MipsError::Unsupported(info.format));
+        }
+
+        log::trace!(
+            "Format {:?} can't be blitted. Downsample mip levels on host",
+            info.format
+        );
+
+        let aspects = info.format.surface_desc().aspects;
+        let layers = info.kind.num_layers();
+        let mut extent = info.kind.extent();
+        let mut level_content = mips::repack(
+            info.format,
+            data_width,
+            data_height,
+            extent,
+            layers,
+            content,
+        )
+        .ok_or(MipsError::InsufficientContent)?;
+
+        let mut levels = last.into_iter().zip(next).take(info.levels as usize);
+        let (first_last, first_next) = levels.next().unwrap();
+        self.transition_image(
+            image.clone(),
+            image::SubresourceRange {
+                aspects,
+                levels: 0..1,
+                layers: 0..layers,
+            },
+            first_last,
+            first_next,
+        );
+
+        for (level, (last, next)) in (1..info.levels).zip(levels) {
+            level_content =
+                mips::downsample(info.format, extent, layers, filter, &level_content).unwrap();
+            extent = mips::next_extent(extent);
+
+            self.upload_image(
+                image.clone(),
+                0,
+                0,
+                image::SubresourceLayers {
+                    aspects,
+                    level,
+                    layers: 0..layers,
+                },
+                image::Offset::ZERO,
+                extent,
+                &level_content,
+                last,
+                next,
+            )
+            .map_err(MipsError::Upload)?;
+        }
+
+        Ok(())
+    }
+
+    /// Download buffer range content.
+    ///
+    /// Copying to the staging buffer will actually be submitted to the device queue
+    /// upon next [`flush_uploads`] or [`maintain`] call to this `Factory`
+    /// together with uploads.
+    /// Returned [`PendingDownload`] can be polled with [`is_download_complete`]
+    /// or waited with [`wait_download`].
+    ///
+    /// # Safety
+    ///
+    /// Buffer must be created by this `Factory`.
+    /// If buffer is used by device then `last` state must match the last usage state of the buffer
+    /// before downloading happen.
+    /// `next` must match buffer usage state in the next device operation.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`UploadError::QueueMismatch`] if `last` state is on a queue other than the `next` one,
+    /// as ownership transfer between queues is not supported.
+    ///
+    /// [`flush_uploads`]: #method.flush_uploads
+    /// [`maintain`]: #method.maintain
+    /// [`PendingDownload`]: struct.PendingDownload.html
+    /// [`is_download_complete`]: #method.is_download_complete
+    /// [`wait_download`]: #method.wait_download
+    /// [`UploadError::QueueMismatch`]: enum.UploadError.html#variant.QueueMismatch
+    pub unsafe fn download_buffer(
+        &self,
+        buffer: &Buffer<B>,
+        offset: u64,
+        size: u64,
+        last: Option<BufferState>,
+        next: BufferState,
+    ) -> Result<PendingDownload<B>, UploadError> {
+        assert!(buffer.info().usage.contains(buffer::Usage::TRANSFER_SRC));
+        assert_ne!(size, 0);
+        assert!(offset + size <= buffer.size());
+        check_queue(last.map(|l| l.queue), next.queue)?;
+
+        let layout = DownloadLayout {
+            row_size: size,
+            row_pitch: size,
+            rows: 1,
+            slices: 1,
+        };
+
+        let staging = self.create_download_staging(layout)?;
+
+        let token = self
+            .uploader
+            .download_buffer(&self.device, buffer, offset, staging.clone(), last, next)
+            .map_err(UploadError::Upload)?;
+
+        Ok(PendingDownload {
+            token,
+            staging,
+            layout,
+        })
+    }
+
+    /// Download image region content.
+    ///
+    /// Copying to the staging buffer will actually be submitted to the device queue
+    /// upon next [`flush_uploads`] or [`maintain`] call to this `Factory`
+    /// together with uploads.
+    /// Rows in the staging buffer are aligned to `optimal_buffer_copy_pitch_alignment`
+    /// where possible. [`read_download`] removes row padding.
+    ///
+    /// # Safety
+    ///
+    /// Image must be created by this `Factory`.
+    /// If image is used by device then `last` state must match the last usage state of the image
+    /// before downloading happen.
+    /// `next` must match image usage state in the next device operation.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`UploadError::QueueMismatch`] if `last` state is on a queue other than the `next` one,
+    /// as ownership transfer between queues is not supported.
+    ///
+    /// [`flush_uploads`]: #method.flush_uploads
+    /// [`maintain`]: #method.maintain
+    /// [`read_download`]: #method.read_download
+    /// [`UploadError::QueueMismatch`]: enum.UploadError.html#variant.QueueMismatch
+    pub unsafe fn download_image(
+        &self,
+        image: Handle<Image<B>>,
+        image_layers: SubresourceLayers,
+        image_offset: image::Offset,
+        image_extent: Extent,
+        last: impl Into<ImageStateOrLayout>,
+        next: ImageState,
+    ) -> Result<PendingDownload<B>, UploadError> {
+        assert!(image.info().usage.contains(image::Usage::TRANSFER_SRC));
+        assert_eq!(image.format().surface_desc().aspects, image_layers.aspects);
+        assert!(image_layers.layers.start < image_layers.layers.end);
+        assert!(image_layers.layers.end <= image.kind().num_layers());
+        assert!(image_layers.level < image.info().levels);
+        let last = last.into();
+        check_queue(last.queue(), next.queue)?;
+
+        let format_desc = image.format().surface_desc();
+        let block_width = format_desc.dim.0 as u32;
+        let block_height = format_desc.dim.1 as u32;
+        let block_size = format_desc.bits as u64 / 8;
+
+        let blocks_in_row = ((image_extent.width + block_width - 1) / block_width) as u64;
+        let row_size = blocks_in_row * block_size;
+        let pitch_alignment = max(
+            self.physical().limits().optimal_buffer_copy_pitch_alignment,
+            1,
+        );
+        let row_pitch = match ((row_size + pitch_alignment - 1) / pitch_alignment) * pitch_alignment
+        {
+            row_pitch if row_pitch % block_size == 0 => row_pitch,
+            _ => row_size,
+        };
+
+        let layout = DownloadLayout {
+            row_size,
+            row_pitch,
+            rows: ((image_extent.height + block_height - 1) / block_height) as u64,
+            slices: image_extent.depth as u64
+                * (image_layers.layers.end - image_layers.layers.start) as u64,
+        };
+
+        let staging = self.create_download_staging(layout)?;
+
+        let token = self
+            .uploader
+            .download_image(
+                &self.device,
+                image,
+                (row_pitch / block_size) as u32 * block_width,
+                image_layers,
+                image_offset,
+                image_extent,
+                staging.clone(),
+                last,
+                next,
+            )
+            .map_err(UploadError::Upload)?;
+
+        Ok(PendingDownload {
+            token,
+            staging,
+            layout,
+        })
+    }
+
+    fn create_download_staging(
+        &self,
+        layout: DownloadLayout,
+    ) -> Result<SharedStaging<B>, UploadError> {
+        let staging = self
+            .create_buffer(
+                BufferInfo {
+                    size: layout.staging_size(),
+                    usage: buffer::Usage::TRANSFER_DST,
+                },
+                memory::Download,
+            )
+            .map_err(UploadError::Create)?;
+
+        Ok(std::sync::Arc::new(parking_lot::Mutex::new(staging)))
+    }
+
+    /// Check if operation the `token` was returned for is complete.
+    /// Uploaded content is resident and downloaded content can be read.
+    pub fn is_upload_complete(&self, token: UploadToken) -> bool {
+        unsafe { self.uploader.is_complete(&self.device, token) }
+    }
+
+    /// Wait for operation the `token` was returned for to complete.
+    /// Returns `false` if timeout expired
+    /// or if operation wasn't flushed with [`flush_uploads`] or [`maintain`] yet.
+    ///
+    /// [`flush_uploads`]: #method.flush_uploads
+    /// [`maintain`]: #method.maintain
+    pub fn wait_upload(
+        &self,
+        token: UploadToken,
+        timeout_ns: u64,
+    ) -> Result<bool, OomOrDeviceLost> {
+        unsafe { self.uploader.wait(&self.device, token, timeout_ns) }
+    }
+
+    /// Check if download is complete and its content can be read.
+    pub fn is_download_complete(&self, download: &PendingDownload<B>) -> bool {
+        self.is_upload_complete(download.token)
+    }
+
+    /// Wait for download to complete.
+    /// Returns `false` if timeout expired
+    /// or if download wasn't flushed with [`flush_uploads`] or [`maintain`] yet.
+    ///
+    /// [`flush_uploads`]: #method.flush_uploads
+    /// [`maintain`]: #method.maintain
+    pub fn wait_download(
+        &self,
+        download: &PendingDownload<B>,
+        timeout_ns: u64,
+    ) -> Result<bool, OomOrDeviceLost> {
+        self.wait_upload(download.token, timeout_ns)
+    }
+
+    /// Read downloaded content with row padding removed.
+    /// Returns `None` if download is not complete yet.
+    pub fn read_download(
+        &self,
+        download: &PendingDownload<B>,
+    ) -> Result<Option<Vec<u8>>, MapError> {
+        if !self.is_download_complete(download) {
+            return Ok(None);
+        }
+
+        let layout = download.layout;
+        let mut staging = download.staging.lock();
+        let mut mapped = self.map_buffer::<u8>(&mut staging)?;
+
+        // Device doesn't access staging buffer after download is complete.
+        let content = unsafe { mapped.read(&self.device, 0..layout.staging_size() as usize)? };
+
+        if layout.row_pitch == layout.row_size {
+            return Ok(Some(content.to_vec()));
+        }
+
+        let mut packed = Vec::with_capacity(layout.packed_size() as usize);
+        for row in content.chunks(layout.row_pitch as usize) {
+            packed.extend_from_slice(&row[..layout.row_size as usize]);
+        }
+        Ok(Some(packed))
+    }
+
+    /// Start memory defragmentation.
+    ///
+    /// Selects sparsely used memory chunks which blocks should be moved elsewhere.
+    /// Selected chunks won't be used for new allocations
+    /// until plan is passed to [`finish_defragmentation`].
+    /// Buffers can be moved out of selected chunks with [`relocate_buffer`].
+    /// Images can't be relocated, so chunks with images are never selected.
+    /// Chunks are freed once all blocks allocated from them are freed.
+    ///
+    /// [`finish_defragmentation`]: #method.finish_defragmentation
+    /// [`relocate_buffer`]: #method.relocate_buffer
+    pub fn plan_defragmentation(&self) -> memory::DefragmentationPlan {
+        profile_scope!("plan_defragmentation");
+
+        self.heaps.lock().plan_defragmentation()
+    }
+
+    /// Finish memory defragmentation.
+    /// Chunks from the `plan` that are still in use return to service.
+    pub fn finish_defragmentation(&self, plan: memory::DefragmentationPlan) {
+        self.heaps.lock().finish_defragmentation(plan)
+    }
+
+    /// Move buffer to the new memory block if its current block is selected by the `plan`.
+    /// Returns `true` if buffer was relocated.
+    ///
+    /// New raw buffer is created and content of the old one is copied into it.
+    /// Copy operation will actually be submitted to the graphics device queue
+    /// upon next [`flush_uploads`] or [`maintain`] call to this `Factory`.
+    /// Old raw buffer and its memory block are destroyed when copying is complete.
+    ///
+    /// Buffers without both `TRANSFER_SRC` and `TRANSFER_DST` usage are never relocated.
+    /// Buffers last used on a queue other than the `next` one can't be relocated
+    /// and [`UploadError::QueueMismatch`] is returned for them.
+    ///
+    /// # Safety
+    ///
+    /// Buffer must be created by this `Factory`.
+    /// Raw buffer changes, so any references to the old raw buffer
+    /// (like descriptor sets) must be updated before next use.
+    /// If buffer is used by device then `last` state must match the last usage state of the buffer
+    /// before relocation happen.
+    /// The `next` must match buffer usage state in the next operation.
+    ///
+    /// [`flush_uploads`]: #method.flush_uploads
+    /// [`maintain`]: #method.maintain
+    /// [`UploadError::QueueMismatch`]: enum.UploadError.html#variant.QueueMismatch
+    pub unsafe fn relocate_buffer(
+        &self,
+        plan: &memory::DefragmentationPlan,
+        buffer: &mut Buffer<B>,
+        last: Option<BufferState>,
+        next: BufferState,
+    ) -> Result<bool, UploadError> {
+        if !buffer
+            .info()
+            .usage
+            .contains(buffer::Usage::TRANSFER_SRC | buffer::Usage::TRANSFER_DST)
+        {
+            return Ok(false);
+        }
+
+        check_queue(last.map(|l| l.queue), next.queue)?;
+
+        let old = buffer
+            .relocate(&self.device, &mut self.heaps.lock(), plan)
+            .map_err(UploadError::Create)?;
+
+        match old {
+            None => Ok(false),
+            Some(old) => {
+                #[cfg(feature = "live-resources")]
+                self.live.lock().relocate(buffer.id(), buffer.block());
+                let old = self.resources.buffers.escape(old);
+                self.uploader
+                    .relocate_buffer(&self.device, buffer, old, last, next)
+                    .map_err(UploadError::Upload)?;
+                Ok(true)
+            }
+        }
+    }
+
     /// Get blitter instance
     pub fn blitter(&self) -> &Blitter<B> {
         &self.blitter
@@ -833,6 +1696,135 @@ where
         &self.adapter.physical_device
     }
 
+    /// Set debug name of the resource created by this `Factory`.
+    /// Name is forwarded to the backend where it supports naming such objects.
+    /// See [`Named`] for details.
+    ///
+    /// Resources shared as `Handle` can't be renamed,
+    /// use [`create_named_buffer`] and [`create_named_image`] to name them at creation.
+    ///
+    /// [`create_named_buffer`]: #method.create_named_buffer
+    /// [`create_named_image`]: #method.create_named_image
+    /// [`Named`]: ../rendy_resource/trait.Named.html
+    pub fn set_name(&self, resource: &mut impl Named<B>, name: &str) {
+        #[cfg(feature = "live-resources")]
+        self.live.lock().set_name(resource.id(), name);
+        unsafe { resource.set_name(&self.device, name) }
+    }
+
+    /// Get snapshot of all resources created by this `Factory` that are not destroyed yet,
+    /// ordered by creation.
+    ///
+    /// Resources dropped by user are listed until they are destroyed
+    /// by [`cleanup`] after device stops using them.
+    /// Resources destroyed bypassing `Factory` are listed forever.
+    ///
+    /// Available with `live-resources` feature enabled,
+    /// as tracking resources costs locking registry on each creation and destruction.
+    ///
+    /// [`cleanup`]: #method.cleanup
+    #[cfg(feature = "live-resources")]
+    pub fn live_resources(&self) -> Vec<LiveResource> {
+        self.live.lock().snapshot()
+    }
+
+    /// Get number of [`cleanup`] calls made so far.
+    /// [`maintain`] calls [`cleanup`] once, so it is usually number of frames.
+    ///
+    /// [`cleanup`]: #method.cleanup
+    /// [`maintain`]: #method.maintain
+    pub fn cleanup_epoch(&self) -> u64 {
+        self.cleanup_epoch
+    }
+
+    /// Get pipeline cache used to create pipelines.
+    pub fn pipeline_cache(&self) -> Option<&B::PipelineCache> {
+        self.pipeline_cache.as_ref()
+    }
+
+    /// Create pipeline cache that is used by [`create_graphics_pipelines`]
+    /// and [`create_compute_pipeline`] from now on.
+    /// Previous pipeline cache is destroyed.
+    ///
+    /// `data` is a blob saved by [`save_pipeline_cache`].
+    /// It is ignored if it was saved for adapter with different vendor, device or name.
+    /// Driver version is not exposed by `gfx-hal` and thus not validated,
+    /// cache data saved with another driver version is rejected by the driver itself
+    /// and empty cache is created instead.
+    ///
+    /// This function takes `&mut self` and thus is meant to be called during initialization,
+    /// before `Factory` is shared with other threads or used by `Graph`.
+    /// Pipelines created before the call are not affected.
+    ///
+    /// [`create_graphics_pipelines`]: #method.create_graphics_pipelines
+    /// [`create_compute_pipeline`]: #method.create_compute_pipeline
+    /// [`save_pipeline_cache`]: #method.save_pipeline_cache
+    pub fn load_pipeline_cache(&mut self, data: Option<&[u8]>) -> Result<(), OutOfMemory> {
+        profile_scope!("load_pipeline_cache");
+
+        let data = data.and_then(|data| {
+            let stripped = pipeline_cache::strip_header(&self.adapter.info, data);
+            if stripped.is_none() {
+                log::warn!("Saved pipeline cache doesn't match the adapter and is ignored");
+            }
+            stripped
+        });
+
+        let cache = unsafe { self.device.create_pipeline_cache(data) }?;
+        if let Some(old) = self.pipeline_cache.replace(cache) {
+            unsafe { self.device.destroy_pipeline_cache(old) };
+        }
+        Ok(())
+    }
+
+    /// Get content of the pipeline cache as a blob
+    /// that can be loaded with [`load_pipeline_cache`] in later runs.
+    /// Returns `None` if factory has no pipeline cache.
+    ///
+    /// [`load_pipeline_cache`]: #method.load_pipeline_cache
+    pub fn save_pipeline_cache(&self) -> Result<Option<Vec<u8>>, OutOfMemory> {
+        profile_scope!("save_pipeline_cache");
+
+        match &self.pipeline_cache {
+            Some(cache) => {
+                let mut blob = pipeline_cache::header(&self.adapter.info);
+                blob.extend(unsafe { self.device.get_pipeline_cache_data(cache) }?);
+                Ok(Some(blob))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Create graphics pipelines using pipeline cache of the factory.
+    ///
+    /// # Safety
+    ///
+    /// Objects referenced by `descs` must be created from this factory.
+    pub unsafe fn create_graphics_pipelines<'a>(
+        &self,
+        descs: impl IntoIterator<Item = impl std::borrow::Borrow<GraphicsPipelineDesc<'a, B>>>,
+    ) -> Vec<Result<B::GraphicsPipeline, rendy_core::hal::pso::CreationError>> {
+        profile_scope!("create_graphics_pipelines");
+
+        self.device
+            .create_graphics_pipelines(descs, self.pipeline_cache.as_ref())
+    }
+
+    /// Create compute pipeline using pipeline cache of the factory.
+    ///
+    /// # Safety
+    ///
+    /// Objects referenced by `desc` must be created from this factory.
+    pub unsafe fn create_compute_pipeline(
+        &self,
+        desc: &ComputePipelineDesc<'_, B>,
+    ) -> Result<B::ComputePipeline, rendy_core::hal::pso::CreationError> {
+        profile_scope!("create_compute_pipeline");
+
+        self.device
+            .create_compute_pipeline(desc, self.pipeline_cache.as_ref())
+    }
+
     /// Create new semaphore.
     pub fn create_semaphore(&self) -> Result<B::Semaphore, OutOfMemory> {
         profile_scope!("create_semaphore");
@@ -1045,16 +2037,39 @@ where
         unsafe {
             self.uploader.cleanup(&self.device);
             self.blitter.cleanup(&self.device);
-            self.resources.cleanup(
-                &self.device,
-                self.heaps.get_mut(),
-                self.descriptor_allocator.get_mut(),
-                next,
-                complete,
-            );
+            {
+                let mut cache = thread_block_cache(&self.block_caches, &self.heaps)
+                    .map(parking_lot::Mutex::lock);
+                #[cfg(feature = "live-resources")]
+                let live = self.live.get_mut();
+                let untrack = |id: ResourceId| {
+                    #[cfg(feature = "live-resources")]
+                    live.untrack(id);
+
+                    #[cfg(not(feature = "live-resources"))]
+                    let _ = id;
+                };
+                self.resources.cleanup(
+                    &self.device,
+                    self.heaps.get_mut(),
+                    self.descriptor_allocator.get_mut(),
+                    cache.as_mut().map(|cache| &mut **cache),
+                    untrack,
+                    next,
+                    complete,
+                );
+            }
+
+            if let Some((_, caches)) = &mut self.block_caches {
+                let heaps = self.heaps.get_mut();
+                for cache in caches.iter_mut() {
+                    cache.get_mut().flush(&self.device, heaps);
+                }
+            }
 
             self.descriptor_allocator.get_mut().cleanup(&self.device);
         }
+        self.cleanup_epoch += 1;
     }
 
     /// Flush uploads
@@ -1079,7 +2094,14 @@ where
         &self,
         bindings: Vec<DescriptorSetLayoutBinding>,
     ) -> Result<DescriptorSetLayout<B>, OutOfMemory> {
-        unsafe { DescriptorSetLayout::create(&self.device, DescriptorSetInfo { bindings }) }
+        let layout =
+            unsafe { DescriptorSetLayout::create(&self.device, DescriptorSetInfo { bindings }) }?;
+        self.track(
+            layout.id(),
+            || LiveResourceInfo::DescriptorSetLayout(layout.info().clone()),
+            None,
+        );
+        Ok(layout)
     }
 
     /// Create descriptor set layout with specified bindings.
@@ -1097,9 +2119,11 @@ where
         layout: Handle<DescriptorSetLayout<B>>,
     ) -> Result<DescriptorSet<B>, OutOfMemory> {
         // TODO: Check `layout` belongs to this factory.
-        unsafe {
+        let set = unsafe {
             DescriptorSet::create(&self.device, &mut self.descriptor_allocator.lock(), layout)
-        }
+        }?;
+        self.track(set.id(), || LiveResourceInfo::DescriptorSet, None);
+        Ok(set)
     }
 
     /// Create descriptor sets with specified layout.
@@ -1140,7 +2164,10 @@ where
 
         Ok(result
             .into_iter()
-            .map(|set| self.resources.sets.escape(set))
+            .map(|set| {
+                self.track(set.id(), || LiveResourceInfo::DescriptorSet, None);
+                self.resources.sets.escape(set)
+            })
             .collect())
     }
 
@@ -1149,6 +2176,41 @@ where
         self.heaps.lock().utilization()
     }
 
+    /// Query memory utilization broken down by allocation tags.
+    /// Resources can be tagged by creating them with [`MemoryUsage::tagged`] usage.
+    ///
+    /// [`MemoryUsage::tagged`]: ../rendy_memory/trait.MemoryUsage.html#method.tagged
+    pub fn memory_utilization_by_tag(&self) -> Vec<memory::TagMemoryUtilization> {
+        self.heaps.lock().utilization_by_tag()
+    }
+
+    /// Take detailed snapshot of the memory allocators state.
+    /// Serializable with `serde-1` feature enabled.
+    pub fn memory_snapshot(&self) -> memory::HeapsSnapshot {
+        self.heaps.lock().snapshot()
+    }
+
+    /// Set budget for the memory heap.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `heap_index` is out of bounds.
+    pub fn set_heap_budget(&self, heap_index: usize, budget: memory::HeapBudget) {
+        self.heaps.lock().set_budget(heap_index, budget)
+    }
+
+    /// Register callback to be called when usage of any memory heap
+    /// crosses soft limit of its budget.
+    ///
+    /// Callback is called during resource creation while memory allocator is locked,
+    /// so it must not create or destroy resources with this `Factory`.
+    pub fn on_memory_pressure(
+        &self,
+        callback: impl Fn(memory::MemoryPressure) + Send + Sync + 'static,
+    ) {
+        self.heaps.lock().on_memory_pressure(callback)
+    }
+
     /// Get Factory's instance id.
     pub fn instance_id(&self) -> InstanceId {
         self.device.id().instance
@@ -1213,6 +2275,17 @@ where
     }
     let adapter = adapters.swap_remove(picked);
 
+    let memory_properties = adapter.physical_device.memory_properties();
+    let budgets = config.heaps.budgets(&memory_properties);
+    if budgets.len() > memory_properties.memory_heaps.len() {
+        log::error!(
+            "Heaps config returned {} budgets for {} memory heaps",
+            budgets.len(),
+            memory_properties.memory_heaps.len()
+        );
+        return Err(rendy_core::hal::device::CreationError::InitializationFailed);
+    }
+
     #[derive(Debug)]
     struct PhysicalDeviceInfo<'a> {
         name: &'a str,
@@ -1237,6 +2310,27 @@ where
             .configure(device_id, &adapter.queue_families)
             .into_iter()
             .collect::<SmallVec<[_; 16]>>();
+
+        for (i, (id, priorities)) in families.iter().enumerate() {
+            let count = priorities.as_ref().len();
+            let valid = adapter
+                .queue_families
+                .get(id.index)
+                .map_or(false, |family| count > 0 && count <= family.max_queues())
+                && families[..i]
+                    .iter()
+                    .all(|(other, _)| other.index != id.index);
+
+            if !valid {
+                log::error!(
+                    "Queues config returned invalid family {:?} with {} queues",
+                    id,
+                    count
+                );
+                return Err(rendy_core::hal::device::CreationError::InitializationFailed);
+            }
+        }
+
         let (create_queues, get_queues): (SmallVec<[_; 32]>, SmallVec<[_; 32]>) = families
             .iter()
             .map(|(index, priorities)| {
@@ -1271,15 +2365,34 @@ where
 
     let device = Device::from_raw(device, device_id);
 
-    let (types, heaps) = config
-        .heaps
-        .configure(&adapter.physical_device.memory_properties());
+    let (types, heaps) = config.heaps.configure(&memory_properties);
     let heaps = heaps.into_iter().collect::<SmallVec<[_; 16]>>();
     let types = types.into_iter().collect::<SmallVec<[_; 32]>>();
 
     log::debug!("Heaps: {:#?}\nTypes: {:#?}", heaps, types);
 
-    let heaps = unsafe { Heaps::new(types, heaps) };
+    let mut heaps = unsafe { Heaps::new(types, heaps) };
+
+    for (index, budget) in budgets.into_iter().enumerate() {
+        heaps.set_budget(index, budget);
+    }
+
+    let block_caches = config
+        .heaps
+        .block_cache()
+        .map(|config| (config, ThreadLocal::new()));
+
+    let transfer = if config.queues.transfer_uploads() {
+        families
+            .as_slice()
+            .iter()
+            .find(|family| family.capability() == QueueType::Transfer)
+            .map(|family| family.id())
+    } else {
+        None
+    };
+
+    log::debug!("Transfer family for uploads: {:?}", transfer);
 
     let epochs = families
         .as_slice()
@@ -1292,11 +2405,24 @@ where
             parking_lot::Mutex::new(DescriptorAllocator::new()),
         ),
         heaps: ManuallyDrop::new(parking_lot::Mutex::new(heaps)),
+        block_caches,
         resources: ManuallyDrop::new(ResourceHub::default()),
-        uploader: unsafe { Uploader::new(&device, &families) }
-            .map_err(rendy_core::hal::device::CreationError::OutOfMemory)?,
+        #[cfg(feature = "live-resources")]
+        live: parking_lot::Mutex::new(LiveResources::default()),
+        cleanup_epoch: 0,
+        uploader: unsafe {
+            Uploader::new(
+                &device,
+                &families,
+                config.uploads,
+                adapter.physical_device.limits().non_coherent_atom_size as u64,
+                transfer,
+            )
+        }
+        .map_err(rendy_core::hal::device::CreationError::OutOfMemory)?,
         blitter: unsafe { Blitter::new(&device, &families) }
             .map_err(rendy_core::hal::device::CreationError::OutOfMemory)?,
+        pipeline_cache: None,
         families_indices: families.indices().into(),
         epochs,
         device,
@@ -21,6 +21,10 @@ mod barriers;
 mod blitter;
 mod config;
 mod factory;
+mod live;
+mod mips;
+mod pipeline_cache;
+mod staging;
 mod upload;
 
-pub use crate::{barriers::*, blitter::*, config::*, factory::*, upload::*};
+pub use crate::{barriers::*, blitter::*, config::*, factory::*, live::*, upload::*};
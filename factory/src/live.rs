@@ -0,0 +1,252 @@
+use {
+    crate::resource::{BufferInfo, DescriptorSetInfo, ImageInfo, ImageViewInfo, ResourceId},
+    rendy_core::hal::image::SamplerDesc,
+};
+
+#[cfg(feature = "live-resources")]
+use {
+    crate::memory::{Block, MemoryBackend, MemoryBlock},
+    std::collections::HashMap,
+};
+
+/// Creation info of the live resource.
+#[derive(Clone, Debug)]
+pub enum LiveResourceInfo {
+    /// Buffer created with the info.
+    Buffer(BufferInfo),
+    /// Image created with the info.
+    Image(ImageInfo),
+    /// Image view created with the info.
+    ImageView(ImageViewInfo),
+    /// Sampler created with the info.
+    Sampler(SamplerDesc),
+    /// Descriptor set layout created with the info.
+    DescriptorSetLayout(DescriptorSetInfo),
+    /// Descriptor set.
+    DescriptorSet,
+}
+
+/// Resource created by `Factory` that is not destroyed yet.
+#[derive(Clone, Debug)]
+pub struct LiveResource {
+    /// Id of the resource.
+    pub id: ResourceId,
+
+    /// Creation info of the resource.
+    pub info: LiveResourceInfo,
+
+    /// Size of the memory block bound to the resource.
+    pub memory_size: Option<u64>,
+
+    /// Memory type of the memory block bound to the resource.
+    pub memory_type: Option<u32>,
+
+    /// Tag memory block was allocated with.
+    pub tag: Option<&'static str>,
+
+    /// Debug name of the resource.
+    pub name: Option<String>,
+
+    /// Value of [`Factory::cleanup_epoch`] when resource was created.
+    ///
+    /// [`Factory::cleanup_epoch`]: struct.Factory.html#method.cleanup_epoch
+    pub epoch: u64,
+}
+
+impl LiveResource {
+    /// Get kind of the resource.
+    pub fn kind(&self) -> &'static str {
+        match self.info {
+            LiveResourceInfo::Buffer(_) => "Buffer",
+            LiveResourceInfo::Image(_) => "Image",
+            LiveResourceInfo::ImageView(_) => "Image view",
+            LiveResourceInfo::Sampler(_) => "Sampler",
+            LiveResourceInfo::DescriptorSetLayout(_) => "Descriptor set layout",
+            LiveResourceInfo::DescriptorSet => "Descriptor set",
+        }
+    }
+}
+
+/// Registry of resources created by `Factory`.
+#[cfg(feature = "live-resources")]
+#[derive(Debug, Default)]
+pub(crate) struct LiveResources {
+    resources: HashMap<ResourceId, LiveResource>,
+}
+
+#[cfg(feature = "live-resources")]
+impl LiveResources {
+    /// Record created resource.
+    pub(crate) fn track<B: MemoryBackend>(
+        &mut self,
+        id: ResourceId,
+        info: LiveResourceInfo,
+        block: Option<&MemoryBlock<B>>,
+        epoch: u64,
+    ) {
+        self.resources.insert(
+            id,
+            LiveResource {
+                id,
+                info,
+                memory_size: block.map(|block| block.size()),
+                memory_type: block.map(|block| block.memory_type()),
+                tag: block.and_then(|block| block.tag()),
+                name: None,
+                epoch,
+            },
+        );
+    }
+
+    /// Forget destroyed resource.
+    /// Resources that weren't tracked are ignored.
+    pub(crate) fn untrack(&mut self, id: ResourceId) {
+        self.resources.remove(&id);
+    }
+
+    /// Update memory block of relocated resource.
+    pub(crate) fn relocate<B: MemoryBackend>(&mut self, id: ResourceId, block: &MemoryBlock<B>) {
+        if let Some(resource) = self.resources.get_mut(&id) {
+            resource.memory_size = Some(block.size());
+            resource.memory_type = Some(block.memory_type());
+            resource.tag = block.tag();
+        }
+    }
+
+    /// Update name of the resource.
+    pub(crate) fn set_name(&mut self, id: ResourceId, name: &str) {
+        if let Some(resource) = self.resources.get_mut(&id) {
+            resource.name = Some(name.to_owned());
+        }
+    }
+
+    /// Get all live resources ordered by creation.
+    pub(crate) fn snapshot(&self) -> Vec<LiveResource> {
+        let mut resources = self.resources.values().cloned().collect::<Vec<_>>();
+        resources.sort_by_key(|resource| resource.id);
+        resources
+    }
+}
+
+#[cfg(all(test, feature = "live-resources"))]
+mod tests {
+    use {
+        super::*,
+        crate::memory::{
+            Data, DynamicConfig, Heaps, HeapsConfig, HostBackend, HostDevice, MemoryUsage as _,
+        },
+        rendy_core::hal::{
+            adapter::{MemoryProperties, MemoryType},
+            buffer, format, image,
+            memory::Properties,
+        },
+    };
+
+    fn create_heaps() -> (HostDevice, Heaps<HostBackend>) {
+        let properties = MemoryProperties {
+            memory_types: vec![MemoryType {
+                properties: Properties::DEVICE_LOCAL,
+                heap_index: 0,
+            }],
+            memory_heaps: vec![1 << 30],
+        };
+        let config = HeapsConfig {
+            linear: None,
+            dynamic: Some(DynamicConfig {
+                block_size_granularity: 256,
+                max_chunk_size: 1 << 20,
+                min_device_allocation: 1 << 16,
+            }),
+            buddy: None,
+        };
+        let heaps = unsafe {
+            Heaps::new(
+                vec![(Properties::DEVICE_LOCAL, 0, config)],
+                properties.memory_heaps.iter().cloned(),
+            )
+        };
+        (HostDevice::new(properties), heaps)
+    }
+
+    #[test]
+    fn track_relocate_untrack() {
+        let (device, mut heaps) = create_heaps();
+        let buffer_block = heaps
+            .allocate(&device, !0, Data.tagged("vertices"), 1024, 256)
+            .unwrap();
+        let image_block = heaps
+            .allocate(&device, !0, Data.tagged("textures"), 4096, 256)
+            .unwrap();
+
+        let buffer = ResourceId::new();
+        let image = ResourceId::new();
+        let mut live = LiveResources::default();
+        live.track(
+            buffer,
+            LiveResourceInfo::Buffer(BufferInfo {
+                size: 1024,
+                usage: buffer::Usage::VERTEX,
+            }),
+            Some(&buffer_block),
+            1,
+        );
+        live.track(
+            image,
+            LiveResourceInfo::Image(ImageInfo {
+                kind: image::Kind::D2(32, 32, 1, 1),
+                levels: 1,
+                format: format::Format::Rgba8Unorm,
+                tiling: image::Tiling::Optimal,
+                view_caps: image::ViewCapabilities::empty(),
+                usage: image::Usage::SAMPLED,
+            }),
+            Some(&image_block),
+            2,
+        );
+        live.set_name(image, "albedo");
+
+        let snapshot = live.snapshot();
+        assert_eq!(
+            snapshot.iter().map(|r| r.id).collect::<Vec<_>>(),
+            vec![buffer, image]
+        );
+        assert_eq!(snapshot[0].kind(), "Buffer");
+        assert_eq!(snapshot[0].memory_size, Some(buffer_block.size()));
+        assert_eq!(snapshot[0].tag, Some("vertices"));
+        assert_eq!(snapshot[0].epoch, 1);
+        assert_eq!(snapshot[1].kind(), "Image");
+        assert_eq!(
+            snapshot[1].name.as_ref().map(String::as_str),
+            Some("albedo")
+        );
+        assert_eq!(snapshot[1].tag, Some("textures"));
+
+        // Relocated buffer reports new block.
+        let relocated = heaps
+            .allocate(&device, !0, Data.tagged("relocated"), 2048, 256)
+            .unwrap();
+        live.relocate(buffer, &relocated);
+        let snapshot = live.snapshot();
+        assert_eq!(snapshot[0].tag, Some("relocated"));
+        assert_eq!(snapshot[0].memory_size, Some(relocated.size()));
+        assert_eq!(snapshot[0].epoch, 1);
+
+        live.untrack(buffer);
+        assert_eq!(
+            live.snapshot().iter().map(|r| r.id).collect::<Vec<_>>(),
+            vec![image]
+        );
+
+        // Untracked resources are ignored.
+        live.relocate(buffer, &relocated);
+        live.set_name(buffer, "gone");
+        live.untrack(buffer);
+        live.untrack(image);
+        assert!(live.snapshot().is_empty());
+
+        heaps.free(&device, buffer_block);
+        heaps.free(&device, image_block);
+        heaps.free(&device, relocated);
+        heaps.dispose(&device);
+    }
+}
@@ -0,0 +1,454 @@
+//! Downsampling of image levels on the host.
+//! Used to generate mip levels of images with formats that can't be blitted.
+
+use rendy_core::hal::{
+    format::{ChannelType, Format, SurfaceType},
+    image::{Extent, Filter},
+};
+
+/// Layout of uncompressed texel with channels of equal size.
+#[derive(Clone, Copy, Debug)]
+struct TexelLayout {
+    channels: usize,
+    channel_size: usize,
+    channel_type: ChannelType,
+}
+
+impl TexelLayout {
+    fn of(format: Format) -> Option<Self> {
+        let base = format.base_format();
+        let (channels, channel_size) = match base.0 {
+            SurfaceType::R8 => (1, 1),
+            SurfaceType::R8_G8 => (2, 1),
+            SurfaceType::R8_G8_B8 | SurfaceType::B8_G8_R8 => (3, 1),
+            SurfaceType::R8_G8_B8_A8 | SurfaceType::B8_G8_R8_A8 | SurfaceType::A8_B8_G8_R8 => {
+                (4, 1)
+            }
+            SurfaceType::R16 => (1, 2),
+            SurfaceType::R16_G16 => (2, 2),
+            SurfaceType::R16_G16_B16 => (3, 2),
+            SurfaceType::R16_G16_B16_A16 => (4, 2),
+            SurfaceType::R32 => (1, 4),
+            SurfaceType::R32_G32 => (2, 4),
+            SurfaceType::R32_G32_B32 => (3, 4),
+            SurfaceType::R32_G32_B32_A32 => (4, 4),
+            _ => return None,
+        };
+
+        match (base.1, channel_size) {
+            (ChannelType::Unorm, 1)
+            | (ChannelType::Unorm, 2)
+            | (ChannelType::Snorm, 1)
+            | (ChannelType::Snorm, 2)
+            | (ChannelType::Srgb, 1)
+            | (ChannelType::Uint, _)
+            | (ChannelType::Sint, _)
+            | (ChannelType::Uscaled, 1)
+            | (ChannelType::Uscaled, 2)
+            | (ChannelType::Sscaled, 1)
+            | (ChannelType::Sscaled, 2)
+            | (ChannelType::Sfloat, 4) => Some(TexelLayout {
+                channels,
+                channel_size,
+                channel_type: base.1,
+            }),
+            _ => None,
+        }
+    }
+
+    fn texel_size(&self) -> usize {
+        self.channels * self.channel_size
+    }
+
+    /// Read channel value.
+    /// Normalized and sRGB values are converted to linear `[0; 1]` or `[-1; 1]` range.
+    fn read(&self, channel: usize, bytes: &[u8]) -> f64 {
+        let raw = match self.channel_size {
+            1 => bytes[0] as u32,
+            2 => u16::from_le_bytes([bytes[0], bytes[1]]) as u32,
+            _ => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        };
+        let signed = match self.channel_size {
+            1 => raw as u8 as i8 as f64,
+            2 => raw as u16 as i16 as f64,
+            _ => raw as i32 as f64,
+        };
+        let max = self.unsigned_max();
+
+        match self.channel_type {
+            ChannelType::Unorm => raw as f64 / max,
+            ChannelType::Snorm => (signed / (max / 2.0).floor()).max(-1.0),
+            ChannelType::Srgb if !self.is_alpha(channel) => srgb_to_linear(raw as f64 / max),
+            ChannelType::Srgb => raw as f64 / max,
+            ChannelType::Uint | ChannelType::Uscaled => raw as f64,
+            ChannelType::Sint | ChannelType::Sscaled => signed,
+            ChannelType::Sfloat => f32::from_bits(raw) as f64,
+            ChannelType::Ufloat => unreachable!(),
+        }
+    }
+
+    /// Write channel value read by `read`.
+    fn write(&self, channel: usize, value: f64, bytes: &mut [u8]) {
+        let max = self.unsigned_max();
+        let signed_max = (max / 2.0).floor();
+        let raw = match self.channel_type {
+            ChannelType::Unorm => (value.max(0.0).min(1.0) * max).round() as u32,
+            ChannelType::Snorm => (value.max(-1.0).min(1.0) * signed_max).round() as i32 as u32,
+            ChannelType::Srgb if !self.is_alpha(channel) => {
+                (linear_to_srgb(value.max(0.0).min(1.0)) * max).round() as u32
+            }
+            ChannelType::Srgb => (value.max(0.0).min(1.0) * max).round() as u32,
+            ChannelType::Uint | ChannelType::Uscaled => value.max(0.0).min(max).round() as u32,
+            ChannelType::Sint | ChannelType::Sscaled => {
+                value.max(-signed_max - 1.0).min(signed_max).round() as i32 as u32
+            }
+            ChannelType::Sfloat => (value as f32).to_bits(),
+            ChannelType::Ufloat => unreachable!(),
+        };
+
+        match self.channel_size {
+            1 => bytes[0] = raw as u8,
+            2 => bytes[..2].copy_from_slice(&(raw as u16).to_le_bytes()),
+            _ => bytes[..4].copy_from_slice(&raw.to_le_bytes()),
+        }
+    }
+
+    fn unsigned_max(&self) -> f64 {
+        match self.channel_size {
+            1 => std::u8::MAX as f64,
+            2 => std::u16::MAX as f64,
+            _ => std::u32::MAX as f64,
+        }
+    }
+
+    /// Alpha channel of sRGB formats is linear.
+    fn is_alpha(&self, channel: usize) -> bool {
+        self.channels == 4 && channel == 3
+    }
+}
+
+fn srgb_to_linear(value: f64) -> f64 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f64) -> f64 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Check if levels of images with `format` can be downsampled on the host.
+pub(crate) fn can_downsample(format: Format) -> bool {
+    TexelLayout::of(format).is_some()
+}
+
+/// Get extent of the next level.
+pub(crate) fn next_extent(extent: Extent) -> Extent {
+    Extent {
+        width: (extent.width / 2).max(1),
+        height: (extent.height / 2).max(1),
+        depth: (extent.depth / 2).max(1),
+    }
+}
+
+/// Downsample tightly packed content of image level with `extent` and `layers`.
+/// Returns tightly packed content of the next level.
+///
+/// `Filter::Nearest` picks first texel of each group,
+/// `Filter::Linear` averages the group.
+///
+/// Returns `None` if `format` is not supported by `can_downsample`.
+pub(crate) fn downsample(
+    format: Format,
+    extent: Extent,
+    layers: u32,
+    filter: Filter,
+    content: &[u8],
+) -> Option<Vec<u8>> {
+    let layout = TexelLayout::of(format)?;
+    let texel_size = layout.texel_size();
+    let src_texels = extent.width as usize * extent.height as usize * extent.depth as usize;
+    assert_eq!(
+        content.len(),
+        src_texels * layers as usize * texel_size,
+        "Content size must match level extent"
+    );
+
+    let dst_extent = next_extent(extent);
+    let dst_texels =
+        dst_extent.width as usize * dst_extent.height as usize * dst_extent.depth as usize;
+    let mut result = vec![0u8; dst_texels * layers as usize * texel_size];
+
+    let src_index = |layer: usize, x: u32, y: u32, z: u32| {
+        let texel = ((z * extent.height + y) * extent.width + x) as usize;
+        (layer * src_texels + texel) * texel_size
+    };
+
+    // Coordinates of the source texels sampled for destination coordinate.
+    let span = |dst: u32, src_size: u32| {
+        let start = dst * 2;
+        match filter {
+            Filter::Linear if start + 1 < src_size => start..start + 2,
+            _ => start.min(src_size - 1)..start.min(src_size - 1) + 1,
+        }
+    };
+
+    for layer in 0..layers as usize {
+        for z in 0..dst_extent.depth {
+            for y in 0..dst_extent.height {
+                for x in 0..dst_extent.width {
+                    let dst_texel = ((z * dst_extent.height + y) * dst_extent.width + x) as usize;
+                    let dst_offset = (layer * dst_texels + dst_texel) * texel_size;
+
+                    for channel in 0..layout.channels {
+                        let channel_offset = channel * layout.channel_size;
+                        let mut sum = 0.0;
+                        let mut count = 0;
+                        for sz in span(z, extent.depth) {
+                            for sy in span(y, extent.height) {
+                                for sx in span(x, extent.width) {
+                                    let offset = src_index(layer, sx, sy, sz) + channel_offset;
+                                    sum += layout.read(channel, &content[offset..]);
+                                    count += 1;
+                                }
+                            }
+                        }
+                        layout.write(
+                            channel,
+                            sum / count as f64,
+                            &mut result[dst_offset + channel_offset..],
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    Some(result)
+}
+
+/// Copy content with rows of `data_width` texels and images of `data_height` rows
+/// into tightly packed content of `extent` and `layers`.
+/// Zero `data_width` or `data_height` means content is tightly packed already.
+///
+/// Returns `None` if `format` is not supported by `can_downsample`
+/// or content is too short for `extent` and `layers`.
+pub(crate) fn repack(
+    format: Format,
+    data_width: u32,
+    data_height: u32,
+    extent: Extent,
+    layers: u32,
+    content: &[u8],
+) -> Option<Vec<u8>> {
+    let texel_size = TexelLayout::of(format)?.texel_size();
+    let data_width = if data_width == 0 {
+        extent.width
+    } else {
+        data_width
+    } as usize;
+    let data_height = if data_height == 0 {
+        extent.height
+    } else {
+        data_height
+    } as usize;
+
+    let images = extent.depth as usize * layers as usize;
+    if images == 0 || extent.width == 0 || extent.height == 0 {
+        return Some(Vec::new());
+    }
+    if data_width < extent.width as usize || data_height < extent.height as usize {
+        return None;
+    }
+
+    let row_size = extent.width as usize * texel_size;
+    let last_row = (images - 1) * data_height + extent.height as usize - 1;
+    if content.len() < last_row * data_width * texel_size + row_size {
+        return None;
+    }
+
+    let mut result = Vec::with_capacity(row_size * extent.height as usize * images);
+    for image in 0..images {
+        for row in 0..extent.height as usize {
+            let offset = (image * data_height + row) * data_width * texel_size;
+            result.extend_from_slice(&content[offset..offset + row_size]);
+        }
+    }
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extent(width: u32, height: u32, depth: u32) -> Extent {
+        Extent {
+            width,
+            height,
+            depth,
+        }
+    }
+
+    #[test]
+    fn srgb_round_trip() {
+        let layout = TexelLayout::of(Format::Rgba8Srgb).unwrap();
+        for value in 0..=std::u8::MAX {
+            for channel in 0..4 {
+                let mut bytes = [0u8];
+                layout.write(channel, layout.read(channel, &[value]), &mut bytes);
+                assert_eq!(bytes[0], value);
+            }
+        }
+    }
+
+    #[test]
+    fn srgb_averages_linear_values() {
+        let content = [0, 0, 0, 0, 255, 255, 255, 255];
+        let result = downsample(
+            Format::Rgba8Srgb,
+            extent(2, 1, 1),
+            1,
+            Filter::Linear,
+            &content,
+        )
+        .unwrap();
+
+        // Color channels are averaged in linear space, alpha is linear already.
+        assert_eq!(result, vec![188, 188, 188, 128]);
+    }
+
+    #[test]
+    fn odd_extent() {
+        assert_eq!(next_extent(extent(5, 3, 1)), extent(2, 1, 1));
+        assert_eq!(next_extent(extent(1, 1, 1)), extent(1, 1, 1));
+
+        let content: Vec<u8> = (0..15).map(|texel| texel * 10).collect();
+        let linear = downsample(
+            Format::R8Unorm,
+            extent(5, 3, 1),
+            1,
+            Filter::Linear,
+            &content,
+        )
+        .unwrap();
+        // Last column and row are not sampled by the last texel.
+        assert_eq!(linear, vec![30, 50]);
+
+        let nearest = downsample(
+            Format::R8Unorm,
+            extent(5, 3, 1),
+            1,
+            Filter::Nearest,
+            &content,
+        )
+        .unwrap();
+        assert_eq!(nearest, vec![0, 20]);
+    }
+
+    #[test]
+    fn single_texel_dimension() {
+        let content = [10, 20, 30, 40];
+        let result = downsample(
+            Format::R8Unorm,
+            extent(1, 4, 1),
+            1,
+            Filter::Linear,
+            &content,
+        )
+        .unwrap();
+        assert_eq!(result, vec![15, 35]);
+    }
+
+    #[test]
+    fn integer_formats() {
+        let result = downsample(
+            Format::R8Uint,
+            extent(2, 2, 1),
+            1,
+            Filter::Linear,
+            &[1, 2, 2, 2],
+        )
+        .unwrap();
+        assert_eq!(result, vec![2]);
+
+        let content: Vec<u8> = [-1i16, -2, -3, -4]
+            .iter()
+            .flat_map(|value| value.to_le_bytes().to_vec())
+            .collect();
+        let result = downsample(
+            Format::R16Sint,
+            extent(2, 2, 1),
+            1,
+            Filter::Linear,
+            &content,
+        )
+        .unwrap();
+        assert_eq!(result, (-3i16).to_le_bytes().to_vec());
+
+        let content: Vec<u8> = [std::u32::MAX; 4]
+            .iter()
+            .flat_map(|value| value.to_le_bytes().to_vec())
+            .collect();
+        let result = downsample(
+            Format::R32Uint,
+            extent(2, 2, 1),
+            1,
+            Filter::Linear,
+            &content,
+        )
+        .unwrap();
+        assert_eq!(result, std::u32::MAX.to_le_bytes().to_vec());
+    }
+
+    #[test]
+    fn layers_are_downsampled_separately() {
+        let result = downsample(
+            Format::R8Unorm,
+            extent(2, 1, 1),
+            2,
+            Filter::Linear,
+            &[0, 2, 100, 200],
+        )
+        .unwrap();
+        assert_eq!(result, vec![1, 150]);
+    }
+
+    #[test]
+    fn unsupported_formats() {
+        assert!(!can_downsample(Format::B10g11r11Ufloat));
+        assert!(!can_downsample(Format::D32Sfloat));
+        assert!(can_downsample(Format::Rgba8Srgb));
+        assert!(downsample(
+            Format::B10g11r11Ufloat,
+            extent(2, 2, 1),
+            1,
+            Filter::Linear,
+            &[0; 16],
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn repack_rows() {
+        let content = [1, 2, 0, 3, 4, 0];
+        let result = repack(Format::R8Unorm, 3, 0, extent(2, 2, 1), 1, &content).unwrap();
+        assert_eq!(result, vec![1, 2, 3, 4]);
+
+        // Padding after the last row is not required.
+        let result = repack(Format::R8Unorm, 3, 0, extent(2, 2, 1), 1, &content[..5]).unwrap();
+        assert_eq!(result, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn repack_short_content() {
+        assert!(repack(Format::R8Unorm, 0, 0, extent(2, 2, 1), 1, &[1, 2, 3]).is_none());
+        assert!(repack(Format::R8Unorm, 0, 0, extent(2, 2, 1), 2, &[1, 2, 3, 4]).is_none());
+        assert!(repack(Format::R8Unorm, 1, 0, extent(2, 2, 1), 1, &[1, 2, 3, 4]).is_none());
+    }
+}
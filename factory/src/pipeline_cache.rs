@@ -0,0 +1,89 @@
+use rendy_core::hal::adapter::AdapterInfo;
+
+/// Magic bytes at the beginning of saved pipeline cache.
+const MAGIC: &[u8; 8] = b"RNDYPIPE";
+
+/// Version of the header layout.
+const VERSION: u32 = 1;
+
+/// Create header that identifies the adapter pipeline cache data is saved for.
+///
+/// Header consists of magic bytes, header version,
+/// vendor and device ids and adapter name, all integers are little-endian.
+/// Driver version is not exposed by the backend,
+/// but backend's own cache data carries it and drivers ignore incompatible data.
+pub(crate) fn header(info: &AdapterInfo) -> Vec<u8> {
+    let mut header = Vec::with_capacity(MAGIC.len() + 16 + info.name.len());
+    header.extend_from_slice(MAGIC);
+    header.extend_from_slice(&VERSION.to_le_bytes());
+    header.extend_from_slice(&(info.vendor as u32).to_le_bytes());
+    header.extend_from_slice(&(info.device as u32).to_le_bytes());
+    header.extend_from_slice(&(info.name.len() as u32).to_le_bytes());
+    header.extend_from_slice(info.name.as_bytes());
+    header
+}
+
+/// Get backend's cache data from saved blob.
+/// Returns `None` if header doesn't match the adapter.
+pub(crate) fn strip_header<'a>(info: &AdapterInfo, data: &'a [u8]) -> Option<&'a [u8]> {
+    let header = header(info);
+    if data.starts_with(&header) {
+        Some(&data[header.len()..])
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, rendy_core::hal::adapter::DeviceType};
+
+    fn adapter_info(vendor: usize, device: usize, name: &str) -> AdapterInfo {
+        AdapterInfo {
+            name: name.into(),
+            vendor,
+            device,
+            device_type: DeviceType::DiscreteGpu,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let info = adapter_info(0x10de, 0x1b80, "GeForce GTX 1080");
+        let mut blob = header(&info);
+        blob.extend_from_slice(&[1, 2, 3]);
+        assert_eq!(strip_header(&info, &blob), Some(&[1, 2, 3][..]));
+
+        let blob = header(&info);
+        assert_eq!(strip_header(&info, &blob), Some(&[][..]));
+    }
+
+    #[test]
+    fn mismatch() {
+        let info = adapter_info(0x10de, 0x1b80, "GeForce GTX 1080");
+        let mut blob = header(&info);
+        blob.extend_from_slice(&[1, 2, 3]);
+
+        let other = [
+            adapter_info(0x1002, 0x1b80, "GeForce GTX 1080"),
+            adapter_info(0x10de, 0x1b81, "GeForce GTX 1080"),
+            adapter_info(0x10de, 0x1b80, "GeForce GTX 1070"),
+            adapter_info(0x10de, 0x1b80, "GeForce GTX 1080 Ti"),
+        ];
+        for other in &other {
+            assert_eq!(strip_header(other, &blob), None);
+        }
+    }
+
+    #[test]
+    fn malformed() {
+        let info = adapter_info(0x10de, 0x1b80, "GeForce GTX 1080");
+        let blob = header(&info);
+        assert_eq!(strip_header(&info, &[]), None);
+        assert_eq!(strip_header(&info, &blob[..blob.len() - 1]), None);
+
+        let mut blob = blob;
+        blob[MAGIC.len()] += 1;
+        assert_eq!(strip_header(&info, &blob), None);
+    }
+}
@@ -0,0 +1,291 @@
+use {
+    crate::{
+        config::UploadsConfig,
+        core::Device,
+        resource::{Buffer, BufferCreationError, Escape},
+        upload::UploadToken,
+        UploadError,
+    },
+    rendy_core::hal::Backend,
+    smallvec::SmallVec,
+};
+
+/// Region of the staging ring that holds uploaded content.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct StagingRegion {
+    chunk: usize,
+    offset: u64,
+}
+
+impl StagingRegion {
+    /// Offset of the region in the staging buffer.
+    pub(crate) fn offset(&self) -> u64 {
+        self.offset
+    }
+}
+
+#[derive(Debug)]
+struct StagingChunk<T> {
+    buffer: T,
+    used: u64,
+
+    /// Latest uploads that read from the chunk. One per queue.
+    uses: SmallVec<[UploadToken; 4]>,
+}
+
+/// Staging memory for uploads made of equally sized staging buffers.
+/// Regions are sub-allocated linearly from the current chunk.
+/// Chunk is reused once all uploads that read from it are complete.
+/// New chunks are created while total size is within budget.
+#[derive(Debug)]
+pub(crate) struct StagingRing<B: Backend> {
+    chunks: Vec<StagingChunk<Escape<Buffer<B>>>>,
+    current: usize,
+    config: UploadsConfig,
+    non_coherent_atom_size: u64,
+}
+
+impl<B> StagingRing<B>
+where
+    B: Backend,
+{
+    pub(crate) fn new(config: UploadsConfig, non_coherent_atom_size: u64) -> Self {
+        StagingRing {
+            chunks: Vec::new(),
+            current: 0,
+            config,
+            non_coherent_atom_size,
+        }
+    }
+
+    /// Get staging buffer that holds the region.
+    pub(crate) fn buffer(&self, region: StagingRegion) -> &Buffer<B> {
+        &self.chunks[region.chunk].buffer
+    }
+
+    /// Copy `content` to the region of the ring
+    /// that will be read by upload with `token`.
+    /// `offset` of the region is multiple of `align`.
+    /// `is_complete` tells if previous uploads are complete.
+    /// `create` creates new staging buffers.
+    ///
+    /// Returns `None` if content is larger than chunk
+    /// or all chunks are in use and budget doesn't allow to create more.
+    ///
+    /// # Safety
+    ///
+    /// `device` must be the one staging buffers are created with.
+    pub(crate) unsafe fn stage(
+        &mut self,
+        device: &Device<B>,
+        content: &[u8],
+        align: u64,
+        token: UploadToken,
+        is_complete: impl Fn(UploadToken) -> bool,
+        create: impl FnOnce(u64) -> Result<Escape<Buffer<B>>, BufferCreationError>,
+    ) -> Result<Option<StagingRegion>, UploadError> {
+        let size = content.len() as u64;
+        let chunk_size = self.config.staging_chunk_size;
+        if size == 0 || size > chunk_size {
+            return Ok(None);
+        }
+
+        let count = self.chunks.len();
+        let found = place(
+            &mut self.chunks,
+            self.current,
+            chunk_size,
+            size,
+            align,
+            is_complete,
+        );
+
+        let (index, offset) = match found {
+            Some(found) => found,
+            None if (count as u64 + 1) * chunk_size <= self.config.staging_budget => {
+                log::trace!("Create staging chunk of size {}", chunk_size);
+                let buffer = create(chunk_size).map_err(UploadError::Create)?;
+                self.chunks.push(StagingChunk {
+                    buffer,
+                    used: 0,
+                    uses: SmallVec::new(),
+                });
+                (count, 0)
+            }
+            None => return Ok(None),
+        };
+
+        let chunk = &mut self.chunks[index];
+        {
+            let mut mapped = chunk
+                .buffer
+                .map_typed::<u8>(device, self.non_coherent_atom_size)
+                .map_err(UploadError::Map)?;
+            mapped.write(offset as usize, content);
+            mapped
+                .commit(device)
+                .map_err(|err| UploadError::Map(err.into()))?;
+        }
+
+        chunk.occupy(offset, size, token);
+        self.current = index;
+
+        Ok(Some(StagingRegion {
+            chunk: index,
+            offset,
+        }))
+    }
+}
+
+impl<T> StagingChunk<T> {
+    /// Mark `size` bytes at `offset` as read by upload with `token`.
+    fn occupy(&mut self, offset: u64, size: u64, token: UploadToken) {
+        self.used = offset + size;
+        match self
+            .uses
+            .iter_mut()
+            .find(|last| last.queue() == token.queue())
+        {
+            Some(last) => *last = token,
+            None => self.uses.push(token),
+        }
+    }
+}
+
+/// Find chunk with room for `size` bytes aligned to `align`, starting from `current` one.
+/// Chunk that has no room is reset if all uploads that read from it are complete.
+///
+/// Returns index of the chunk and offset in it, or `None` if new chunk is required.
+fn place<T>(
+    chunks: &mut [StagingChunk<T>],
+    current: usize,
+    chunk_size: u64,
+    size: u64,
+    align: u64,
+    is_complete: impl Fn(UploadToken) -> bool,
+) -> Option<(usize, u64)> {
+    let count = chunks.len();
+    for step in 0..count {
+        let index = (current + step) % count;
+        let chunk = &mut chunks[index];
+        let offset = aligned(chunk.used, align);
+        if offset + size <= chunk_size {
+            return Some((index, offset));
+        } else if chunk.uses.iter().all(|&token| is_complete(token)) {
+            chunk.used = 0;
+            chunk.uses.clear();
+            return Some((index, 0));
+        }
+    }
+    None
+}
+
+/// Round `value` up to multiple of `align`.
+/// Unlike most alignments in the crate `align` doesn't have to be power of two.
+fn aligned(value: u64, align: u64) -> u64 {
+    ((value + align - 1) / align) * align
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{aligned, place, StagingChunk},
+        crate::{
+            command::{FamilyId, QueueId},
+            core::{DeviceId, InstanceId},
+            upload::UploadToken,
+        },
+        smallvec::SmallVec,
+    };
+
+    fn chunks(count: usize) -> Vec<StagingChunk<()>> {
+        (0..count)
+            .map(|_| StagingChunk {
+                buffer: (),
+                used: 0,
+                uses: SmallVec::new(),
+            })
+            .collect()
+    }
+
+    fn token(device: DeviceId, queue: usize, epoch: u64) -> UploadToken {
+        UploadToken::new(
+            QueueId {
+                family: FamilyId { index: 0, device },
+                index: queue,
+            },
+            epoch,
+        )
+    }
+
+    #[test]
+    fn aligned_values() {
+        assert_eq!(aligned(0, 4), 0);
+        assert_eq!(aligned(1, 4), 4);
+        assert_eq!(aligned(4, 4), 4);
+        assert_eq!(aligned(5, 1), 5);
+        // Texel size of three-component formats is not power of two.
+        assert_eq!(aligned(7, 12), 12);
+        assert_eq!(aligned(25, 12), 36);
+    }
+
+    #[test]
+    fn fill_chunk() {
+        let device = DeviceId::new(InstanceId::new());
+        let mut chunks = chunks(1);
+        let busy = |_| false;
+
+        assert_eq!(place(&mut chunks, 0, 256, 100, 4, busy), Some((0, 0)));
+        chunks[0].occupy(0, 100, token(device, 0, 1));
+        assert_eq!(place(&mut chunks, 0, 256, 100, 64, busy), Some((0, 128)));
+        chunks[0].occupy(128, 100, token(device, 0, 2));
+
+        // Only latest token per queue is kept.
+        assert_eq!(&chunks[0].uses[..], &[token(device, 0, 2)][..]);
+        chunks[0].occupy(228, 4, token(device, 1, 1));
+        assert_eq!(chunks[0].uses.len(), 2);
+
+        // Chunk is full and still in use.
+        assert_eq!(place(&mut chunks, 0, 256, 100, 4, busy), None);
+        assert_eq!(chunks[0].used, 232);
+    }
+
+    #[test]
+    fn wraparound() {
+        let device = DeviceId::new(InstanceId::new());
+        let mut chunks = chunks(3);
+        for (index, chunk) in chunks.iter_mut().enumerate() {
+            chunk.occupy(0, 200, token(device, 0, index as u64 + 1));
+        }
+
+        // Search starts from current chunk and wraps around to the first one.
+        let complete = |token: UploadToken| token.epoch() <= 1;
+        assert_eq!(place(&mut chunks, 2, 256, 100, 4, complete), Some((0, 0)));
+        assert_eq!(chunks[0].used, 0);
+        assert!(chunks[0].uses.is_empty());
+
+        // Chunks still in use are left untouched.
+        assert_eq!(chunks[1].used, 200);
+        assert_eq!(chunks[2].used, 200);
+
+        // Small content fits the current chunk without wrapping.
+        assert_eq!(place(&mut chunks, 2, 256, 16, 4, complete), Some((2, 200)));
+    }
+
+    #[test]
+    fn reuse_after_epoch() {
+        let device = DeviceId::new(InstanceId::new());
+        let mut chunks = chunks(1);
+        chunks[0].occupy(0, 200, token(device, 0, 5));
+        chunks[0].occupy(200, 50, token(device, 1, 3));
+
+        // Chunk is reused only when uploads on all queues are complete.
+        let epoch_5 = |token: UploadToken| token.queue().index == 0 && token.epoch() <= 5;
+        assert_eq!(place(&mut chunks, 0, 256, 100, 4, epoch_5), None);
+        assert_eq!(chunks[0].used, 250);
+
+        let all = |token: UploadToken| token.epoch() <= 5;
+        assert_eq!(place(&mut chunks, 0, 256, 100, 4, all), Some((0, 0)));
+        assert!(chunks[0].uses.is_empty());
+    }
+}
@@ -2,16 +2,26 @@ use {
     crate::{
         barriers::Barriers,
         command::{
-            CommandBuffer, CommandPool, Families, Family, IndividualReset, InitialState, OneShot,
-            PendingOnceState, PrimaryLevel, QueueId, RecordingState, Submission, Transfer,
+            CommandBuffer, CommandPool, Families, Family, FamilyId, IndividualReset, InitialState,
+            OneShot, PendingOnceState, PrimaryLevel, QueueId, QueueType, RecordingState,
+            Submission,
         },
+        config::UploadsConfig,
         core::Device,
-        resource::{Buffer, Escape, Handle, Image},
+        resource::{Buffer, BufferCreationError, Escape, Handle, Image},
+        staging::StagingRing,
+        UploadError,
     },
-    rendy_core::hal::device::{Device as _, OutOfMemory},
-    std::{collections::VecDeque, iter::once},
+    rendy_core::hal::device::{Device as _, OomOrDeviceLost, OutOfMemory},
+    smallvec::SmallVec,
+    std::{collections::VecDeque, iter::once, ops::Range, sync::Arc},
 };
 
+/// Staging buffer that receives downloaded content.
+/// Shared between `PendingDownload` and uploads that write to it
+/// so that it outlives the copy even if download is dropped.
+pub(crate) type SharedStaging<B> = Arc<parking_lot::Mutex<Escape<Buffer<B>>>>;
+
 /// State of the buffer on device.
 #[derive(Clone, Copy, Debug)]
 pub struct BufferState {
@@ -106,6 +116,22 @@ impl ImageStateOrLayout {
     pub fn undefined() -> Self {
         ImageStateOrLayout::Layout(rendy_core::hal::image::Layout::Undefined)
     }
+
+    /// Check if image is not used by device.
+    fn is_idle(&self) -> bool {
+        match self {
+            ImageStateOrLayout::State(_) => false,
+            ImageStateOrLayout::Layout(_) => true,
+        }
+    }
+
+    /// Get queue that used the image last.
+    pub(crate) fn queue(&self) -> Option<QueueId> {
+        match self {
+            ImageStateOrLayout::State(state) => Some(state.queue),
+            ImageStateOrLayout::Layout(_) => None,
+        }
+    }
 }
 
 impl From<ImageState> for ImageStateOrLayout {
@@ -120,45 +146,187 @@ impl From<rendy_core::hal::image::Layout> for ImageStateOrLayout {
     }
 }
 
+/// Token of the operation submitted through `Factory` uploads.
+/// Can be checked with [`Factory::is_upload_complete`]
+/// or waited with [`Factory::wait_upload`].
+///
+/// [`Factory::is_upload_complete`]: struct.Factory.html#method.is_upload_complete
+/// [`Factory::wait_upload`]: struct.Factory.html#method.wait_upload
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct UploadToken {
+    queue: QueueId,
+    epoch: u64,
+}
+
+impl UploadToken {
+    pub(crate) fn new(queue: QueueId, epoch: u64) -> Self {
+        UploadToken { queue, epoch }
+    }
+
+    /// Get queue that performs the operation.
+    pub fn queue(&self) -> QueueId {
+        self.queue
+    }
+
+    /// Get epoch of the uploads submission to the queue that performs the operation.
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+}
+
+/// Layout of downloaded content in the staging buffer.
+/// Content is a sequence of `slices`,
+/// each of `rows` rows of `row_size` bytes
+/// that are `row_pitch` bytes apart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DownloadLayout {
+    /// Number of bytes in one row.
+    pub row_size: u64,
+
+    /// Distance in bytes between beginnings of adjacent rows.
+    pub row_pitch: u64,
+
+    /// Number of rows in one slice.
+    pub rows: u64,
+
+    /// Number of slices.
+    /// For images this is depth multiplied by number of layers.
+    pub slices: u64,
+}
+
+impl DownloadLayout {
+    /// Size of the content with row padding removed.
+    pub fn packed_size(&self) -> u64 {
+        self.row_size * self.rows * self.slices
+    }
+
+    /// Size of the staging buffer.
+    pub fn staging_size(&self) -> u64 {
+        self.row_pitch * self.rows * self.slices
+    }
+}
+
+/// Download requested from `Factory`.
+/// Can be polled with [`Factory::is_download_complete`]
+/// or waited with [`Factory::wait_download`].
+/// Its [`token`] can be used with other tokens returned for uploads.
+/// Content is fetched with [`Factory::read_download`] once download is complete.
+///
+/// Dropping this value doesn't cancel the download.
+///
+/// [`Factory::is_download_complete`]: struct.Factory.html#method.is_download_complete
+/// [`Factory::wait_download`]: struct.Factory.html#method.wait_download
+/// [`Factory::read_download`]: struct.Factory.html#method.read_download
+/// [`token`]: #method.token
+#[derive(Debug)]
+pub struct PendingDownload<B: rendy_core::hal::Backend> {
+    pub(crate) token: UploadToken,
+    pub(crate) staging: SharedStaging<B>,
+    pub(crate) layout: DownloadLayout,
+}
+
+impl<B> PendingDownload<B>
+where
+    B: rendy_core::hal::Backend,
+{
+    /// Get token of the download.
+    pub fn token(&self) -> UploadToken {
+        self.token
+    }
+
+    /// Get layout of the content in the staging buffer.
+    pub fn layout(&self) -> DownloadLayout {
+        self.layout
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct Uploader<B: rendy_core::hal::Backend> {
     family_uploads: Vec<Option<parking_lot::Mutex<FamilyUploads<B>>>>,
+
+    /// Staging memory reused by uploads. Locked after `family_uploads`.
+    staging: Vec<Option<parking_lot::Mutex<StagingRing<B>>>>,
+
+    /// Transfer-only family to record uploads to idle resources on.
+    transfer: Option<FamilyId>,
 }
 
 impl<B> Uploader<B>
 where
     B: rendy_core::hal::Backend,
 {
+    /// If `transfer` family is specified then uploads to resources
+    /// not used by device yet are recorded on its first queue
+    /// and ownership of the resources is transferred to the family of the next queue.
+    ///
     /// # Safety
     ///
     /// `families` must belong to the `device`
+    /// and contain `transfer` family.
     pub(crate) unsafe fn new(
         device: &Device<B>,
         families: &Families<B>,
+        config: UploadsConfig,
+        non_coherent_atom_size: u64,
+        transfer: Option<FamilyId>,
     ) -> Result<Self, OutOfMemory> {
         let mut family_uploads = Vec::new();
+        let mut staging = Vec::new();
         for family in families.as_slice() {
             while family_uploads.len() <= family.id().index {
                 family_uploads.push(None);
+                staging.push(None);
             }
 
+            staging[family.id().index] = Some(parking_lot::Mutex::new(StagingRing::new(
+                config,
+                non_coherent_atom_size,
+            )));
+
             family_uploads[family.id().index] = Some(parking_lot::Mutex::new(FamilyUploads {
                 fences: Vec::new(),
-                pool: family
-                    .create_pool(device)
-                    .map(|pool| pool.with_capability().unwrap())?,
+                pool: family.create_pool(device)?,
                 next: Vec::new(),
                 pending: VecDeque::new(),
+                next_epochs: Vec::new(),
+                complete_epochs: Vec::new(),
                 command_buffers: Vec::new(),
+                semaphores: Vec::new(),
                 barriers: Barriers::new(
                     rendy_core::hal::pso::PipelineStage::TRANSFER,
-                    rendy_core::hal::buffer::Access::TRANSFER_WRITE,
-                    rendy_core::hal::image::Access::TRANSFER_WRITE,
+                    rendy_core::hal::buffer::Access::TRANSFER_READ
+                        | rendy_core::hal::buffer::Access::TRANSFER_WRITE,
+                    rendy_core::hal::image::Access::TRANSFER_READ
+                        | rendy_core::hal::image::Access::TRANSFER_WRITE,
                 ),
             }));
         }
 
-        Ok(Uploader { family_uploads })
+        Ok(Uploader {
+            family_uploads,
+            staging,
+            transfer,
+        })
+    }
+
+    /// Get queue to record upload on for resource that will be used on `next` queue.
+    /// Upload to `idle` resource is recorded on transfer family if one is used
+    /// and it is not the family of the `next` queue.
+    fn upload_queue(&self, next: QueueId, idle: bool) -> QueueId {
+        route_upload(self.transfer, next, idle)
+    }
+
+    /// Lock uploads of the family of the `queue`
+    /// and uploads of the family of the `next` queue if it is different.
+    fn lock_uploads(
+        &self,
+        queue: QueueId,
+        next: QueueId,
+    ) -> (
+        parking_lot::MutexGuard<'_, FamilyUploads<B>>,
+        Option<parking_lot::MutexGuard<'_, FamilyUploads<B>>>,
+    ) {
+        lock_pair(&self.family_uploads, queue, next)
     }
 
     /// # Safety
@@ -174,21 +342,154 @@ where
         staging: Escape<Buffer<B>>,
         last: Option<BufferState>,
         next: BufferState,
+    ) -> Result<UploadToken, OutOfMemory> {
+        let queue = self.upload_queue(next.queue, last.is_none());
+        let (mut family_uploads, target_uploads) = self.lock_uploads(queue, next.queue);
+
+        let region = rendy_core::hal::command::BufferCopy {
+            src: 0,
+            dst: offset,
+            size: staging.size(),
+        };
+
+        let (token, next_upload) = match target_uploads {
+            Some(mut target_uploads) => (
+                target_uploads.next_token(next.queue),
+                family_uploads.record_buffer_transfer(
+                    device,
+                    queue,
+                    &mut target_uploads,
+                    staging.raw(),
+                    region,
+                    buffer,
+                    next,
+                )?,
+            ),
+            None => (
+                family_uploads.next_token(next.queue),
+                family_uploads.record_buffer_upload(
+                    device,
+                    staging.raw(),
+                    region,
+                    buffer,
+                    last,
+                    next,
+                )?,
+            ),
+        };
+        next_upload.staging_buffers.push(staging);
+
+        Ok(token)
+    }
+
+    /// Copy `content` to the staging ring of the family
+    /// and record copying of it to the buffer.
+    /// `create` is used to create new staging chunks.
+    ///
+    /// Returns `None` if content doesn't fit the staging ring.
+    ///
+    /// # Safety
+    ///
+    /// `device` must be the same that was used to create this `Uploader`.
+    /// `buffer` must belong to the `device`.
+    ///
+    pub(crate) unsafe fn upload_buffer_content(
+        &self,
+        device: &Device<B>,
+        buffer: &Buffer<B>,
+        offset: u64,
+        content: &[u8],
+        last: Option<BufferState>,
+        next: BufferState,
+        create: impl FnOnce(u64) -> Result<Escape<Buffer<B>>, BufferCreationError>,
+    ) -> Result<Option<UploadToken>, UploadError> {
+        let queue = self.upload_queue(next.queue, last.is_none());
+        let (mut family_uploads, target_uploads) = self.lock_uploads(queue, next.queue);
+        let mut staging = self.staging[queue.family.index].as_ref().unwrap().lock();
+
+        let staging_token = family_uploads.next_token(queue);
+        let region = {
+            let ref family_uploads = family_uploads;
+            staging.stage(
+                device,
+                content,
+                4,
+                staging_token,
+                |token| family_uploads.is_complete(token),
+                create,
+            )?
+        };
+
+        let region = match region {
+            Some(region) => region,
+            None => return Ok(None),
+        };
+
+        let copy = rendy_core::hal::command::BufferCopy {
+            src: region.offset(),
+            dst: offset,
+            size: content.len() as u64,
+        };
+
+        match target_uploads {
+            Some(mut target_uploads) => {
+                family_uploads
+                    .record_buffer_transfer(
+                        device,
+                        queue,
+                        &mut target_uploads,
+                        staging.buffer(region).raw(),
+                        copy,
+                        buffer,
+                        next,
+                    )
+                    .map_err(UploadError::Upload)?;
+                Ok(Some(target_uploads.next_token(next.queue)))
+            }
+            None => {
+                family_uploads
+                    .record_buffer_upload(
+                        device,
+                        staging.buffer(region).raw(),
+                        copy,
+                        buffer,
+                        last,
+                        next,
+                    )
+                    .map_err(UploadError::Upload)?;
+                Ok(Some(staging_token))
+            }
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `device` must be the same that was used to create this `Uploader`.
+    /// `buffer` and `old` must belong to the `device`.
+    /// `last` queue must be the `next` queue.
+    ///
+    pub(crate) unsafe fn relocate_buffer(
+        &self,
+        device: &Device<B>,
+        buffer: &Buffer<B>,
+        old: Escape<Buffer<B>>,
+        last: Option<BufferState>,
+        next: BufferState,
     ) -> Result<(), OutOfMemory> {
+        debug_assert!(
+            last.map_or(true, |last| last.queue == next.queue),
+            "Can't sync resources across queues"
+        );
+
         let mut family_uploads = self.family_uploads[next.queue.family.index]
             .as_ref()
             .unwrap()
             .lock();
 
-        if let Some(last) = last {
-            if last.queue != next.queue {
-                unimplemented!("Can't sync resources across queues");
-            }
-        }
-
+        // Content of the old buffer is read, so device writes must be made available.
         family_uploads.barriers.add_buffer(
             last.map_or(rendy_core::hal::pso::PipelineStage::empty(), |l| l.stage),
-            rendy_core::hal::buffer::Access::empty(),
+            last.map_or(rendy_core::hal::buffer::Access::empty(), |l| l.access),
             next.stage,
             next.access,
         );
@@ -196,16 +497,17 @@ where
         let next_upload = family_uploads.next_upload(device, next.queue.index)?;
         let mut encoder = next_upload.command_buffer.encoder();
         encoder.copy_buffer(
-            staging.raw(),
+            old.raw(),
             buffer.raw(),
             Some(rendy_core::hal::command::BufferCopy {
                 src: 0,
-                dst: offset,
-                size: staging.size(),
+                dst: 0,
+                size: buffer.size(),
             }),
         );
 
-        next_upload.staging_buffers.push(staging);
+        // Old buffer is kept alive until copying is complete.
+        next_upload.staging_buffers.push(old);
 
         Ok(())
     }
@@ -259,6 +561,78 @@ where
         );
     }
 
+    /// Record clearing of the image subresource range with `value`.
+    /// Previous content of the range is discarded.
+    ///
+    /// # Safety
+    ///
+    /// `device` must be the same that was used to create this `Uploader`.
+    /// `image` must belong to the `device`.
+    /// `last` state must be on the `next` queue, see `check_queue`.
+    /// Family of the `next` queue must support graphics.
+    ///
+    pub(crate) unsafe fn clear_image(
+        &self,
+        device: &Device<B>,
+        image: Handle<Image<B>>,
+        image_range: rendy_core::hal::image::SubresourceRange,
+        value: rendy_core::hal::command::ClearValue,
+        last: ImageStateOrLayout,
+        next: ImageState,
+    ) -> Result<UploadToken, OutOfMemory> {
+        let mut family_uploads = self.family_uploads[next.queue.family.index]
+            .as_ref()
+            .unwrap()
+            .lock();
+
+        let target_layout = family_uploads.add_image_write_barrier(
+            image.clone(),
+            image_range.clone(),
+            last,
+            next,
+            true,
+        );
+
+        let token = family_uploads.next_token(next.queue);
+        let next_upload = family_uploads.next_upload(device, next.queue.index)?;
+        let mut encoder = next_upload.command_buffer.encoder();
+        encoder.clear_image(image.raw(), target_layout, value, Some(image_range));
+
+        Ok(token)
+    }
+
+    /// Record filling of the buffer range with repeated `data`.
+    ///
+    /// # Safety
+    ///
+    /// `device` must be the same that was used to create this `Uploader`.
+    /// `buffer` must belong to the `device`.
+    /// `last` state must be on the `next` queue, see `check_queue`.
+    ///
+    pub(crate) unsafe fn fill_buffer(
+        &self,
+        device: &Device<B>,
+        buffer: &Buffer<B>,
+        range: Range<u64>,
+        data: u32,
+        last: Option<BufferState>,
+        next: BufferState,
+    ) -> Result<UploadToken, OutOfMemory> {
+        let mut family_uploads = self.family_uploads[next.queue.family.index]
+            .as_ref()
+            .unwrap()
+            .lock();
+
+        family_uploads.add_buffer_write_barrier(last, next);
+
+        let token = family_uploads.next_token(next.queue);
+        let next_upload = family_uploads.next_upload(device, next.queue.index)?;
+        let mut encoder = next_upload.command_buffer.encoder();
+        encoder.fill_buffer(buffer.raw(), range, data);
+
+        Ok(token)
+    }
+
     /// # Safety
     ///
     /// `device` must be the same that was used to create this `Uploader`.
@@ -276,22 +650,228 @@ where
         staging: Escape<Buffer<B>>,
         last: ImageStateOrLayout,
         next: ImageState,
-    ) -> Result<(), OutOfMemory> {
-        use rendy_core::hal::image::{Access, Layout};
+    ) -> Result<UploadToken, OutOfMemory> {
+        let queue = self.upload_queue(next.queue, last.is_idle());
+        let (mut family_uploads, target_uploads) = self.lock_uploads(queue, next.queue);
+
+        let region = rendy_core::hal::command::BufferImageCopy {
+            buffer_offset: 0,
+            buffer_width: data_width,
+            buffer_height: data_height,
+            image_layers,
+            image_offset,
+            image_extent,
+        };
+
+        let (token, next_upload) = match target_uploads {
+            Some(mut target_uploads) => (
+                target_uploads.next_token(next.queue),
+                family_uploads.record_image_transfer(
+                    device,
+                    queue,
+                    &mut target_uploads,
+                    staging.raw(),
+                    region,
+                    image,
+                    last,
+                    next,
+                )?,
+            ),
+            None => (
+                family_uploads.next_token(next.queue),
+                family_uploads.record_image_upload(
+                    device,
+                    staging.raw(),
+                    region,
+                    image,
+                    last,
+                    next,
+                )?,
+            ),
+        };
+        next_upload.staging_buffers.push(staging);
 
+        Ok(token)
+    }
+
+    /// Copy `content` to the staging ring of the family
+    /// and record copying of it to the image.
+    /// `create` is used to create new staging chunks.
+    ///
+    /// Returns `None` if content doesn't fit the staging ring.
+    ///
+    /// # Safety
+    ///
+    /// `device` must be the same that was used to create this `Uploader`.
+    /// `image` must belong to the `device`.
+    ///
+    pub(crate) unsafe fn upload_image_content(
+        &self,
+        device: &Device<B>,
+        image: Handle<Image<B>>,
+        data_width: u32,
+        data_height: u32,
+        image_layers: rendy_core::hal::image::SubresourceLayers,
+        image_offset: rendy_core::hal::image::Offset,
+        image_extent: rendy_core::hal::image::Extent,
+        content: &[u8],
+        last: ImageStateOrLayout,
+        next: ImageState,
+        create: impl FnOnce(u64) -> Result<Escape<Buffer<B>>, BufferCreationError>,
+    ) -> Result<Option<UploadToken>, UploadError> {
+        let queue = self.upload_queue(next.queue, last.is_idle());
+        let (mut family_uploads, target_uploads) = self.lock_uploads(queue, next.queue);
+        let mut staging = self.staging[queue.family.index].as_ref().unwrap().lock();
+
+        // Buffer offset must be multiple of 4 and of texel block size.
+        let block_size = (image.format().surface_desc().bits as u64 / 8).max(1);
+        let align = if block_size % 4 == 0 {
+            block_size
+        } else if block_size % 2 == 0 {
+            block_size * 2
+        } else {
+            block_size * 4
+        };
+
+        let staging_token = family_uploads.next_token(queue);
+        let region = {
+            let ref family_uploads = family_uploads;
+            staging.stage(
+                device,
+                content,
+                align,
+                staging_token,
+                |token| family_uploads.is_complete(token),
+                create,
+            )?
+        };
+
+        let region = match region {
+            Some(region) => region,
+            None => return Ok(None),
+        };
+
+        let copy = rendy_core::hal::command::BufferImageCopy {
+            buffer_offset: region.offset(),
+            buffer_width: data_width,
+            buffer_height: data_height,
+            image_layers,
+            image_offset,
+            image_extent,
+        };
+
+        match target_uploads {
+            Some(mut target_uploads) => {
+                family_uploads
+                    .record_image_transfer(
+                        device,
+                        queue,
+                        &mut target_uploads,
+                        staging.buffer(region).raw(),
+                        copy,
+                        image,
+                        last,
+                        next,
+                    )
+                    .map_err(UploadError::Upload)?;
+                Ok(Some(target_uploads.next_token(next.queue)))
+            }
+            None => {
+                family_uploads
+                    .record_image_upload(
+                        device,
+                        staging.buffer(region).raw(),
+                        copy,
+                        image,
+                        last,
+                        next,
+                    )
+                    .map_err(UploadError::Upload)?;
+                Ok(Some(staging_token))
+            }
+        }
+    }
+
+    /// Record copying of the buffer range to the `staging` buffer.
+    ///
+    /// # Safety
+    ///
+    /// `device` must be the same that was used to create this `Uploader`.
+    /// `buffer` and `staging` must belong to the `device`.
+    /// `last` state must be on the `next` queue, see `check_queue`.
+    ///
+    pub(crate) unsafe fn download_buffer(
+        &self,
+        device: &Device<B>,
+        buffer: &Buffer<B>,
+        offset: u64,
+        staging: SharedStaging<B>,
+        last: Option<BufferState>,
+        next: BufferState,
+    ) -> Result<UploadToken, OutOfMemory> {
         let mut family_uploads = self.family_uploads[next.queue.family.index]
             .as_ref()
             .unwrap()
             .lock();
 
-        let whole_extent = if image_layers.level == 0 {
-            image.kind().extent()
-        } else {
-            image.kind().level_extent(image_layers.level)
-        };
+        debug_assert_eq!(check_queue(last.map(|l| l.queue), next.queue), Ok(()));
+
+        // Content of the buffer is read, so device writes must be made available.
+        family_uploads.barriers.add_buffer(
+            last.map_or(rendy_core::hal::pso::PipelineStage::empty(), |l| l.stage),
+            last.map_or(rendy_core::hal::buffer::Access::empty(), |l| l.access),
+            next.stage,
+            next.access,
+        );
+        family_uploads.add_host_read_barrier();
+
+        let token = family_uploads.next_token(next.queue);
+        let next_upload = family_uploads.next_upload(device, next.queue.index)?;
+        let mut encoder = next_upload.command_buffer.encoder();
+        {
+            let staging = staging.lock();
+            encoder.copy_buffer(
+                buffer.raw(),
+                staging.raw(),
+                Some(rendy_core::hal::command::BufferCopy {
+                    src: offset,
+                    dst: 0,
+                    size: staging.size(),
+                }),
+            );
+        }
 
-        let whole_level =
-            image_offset == rendy_core::hal::image::Offset::ZERO && image_extent == whole_extent;
+        next_upload.downloads.push(staging);
+
+        Ok(token)
+    }
+
+    /// Record copying of the image region to the `staging` buffer.
+    ///
+    /// # Safety
+    ///
+    /// `device` must be the same that was used to create this `Uploader`.
+    /// `image` and `staging` must belong to the `device`.
+    /// `last` state must be on the `next` queue, see `check_queue`.
+    ///
+    pub(crate) unsafe fn download_image(
+        &self,
+        device: &Device<B>,
+        image: Handle<Image<B>>,
+        data_width: u32,
+        image_layers: rendy_core::hal::image::SubresourceLayers,
+        image_offset: rendy_core::hal::image::Offset,
+        image_extent: rendy_core::hal::image::Extent,
+        staging: SharedStaging<B>,
+        last: ImageStateOrLayout,
+        next: ImageState,
+    ) -> Result<UploadToken, OutOfMemory> {
+        use rendy_core::hal::image::{Access, Layout};
+
+        let mut family_uploads = self.family_uploads[next.queue.family.index]
+            .as_ref()
+            .unwrap()
+            .lock();
 
         let image_range = rendy_core::hal::image::SubresourceRange {
             aspects: image_layers.aspects,
@@ -299,43 +879,25 @@ where
             layers: image_layers.layers.clone(),
         };
 
-        let (last_stage, mut last_access, last_layout) = match last.into() {
+        let (last_stage, last_access, last_layout) = match last {
             ImageStateOrLayout::State(last) => {
-                if last.queue != next.queue {
-                    unimplemented!("Can't sync resources across queues");
-                }
-                (
-                    last.stage,
-                    last.access,
-                    if whole_level {
-                        Layout::Undefined
-                    } else {
-                        last.layout
-                    },
-                )
+                debug_assert_eq!(check_queue(Some(last.queue), next.queue), Ok(()));
+                (last.stage, last.access, last.layout)
             }
             ImageStateOrLayout::Layout(last_layout) => (
                 rendy_core::hal::pso::PipelineStage::TOP_OF_PIPE,
                 Access::empty(),
-                if whole_level {
-                    Layout::Undefined
-                } else {
-                    last_layout
-                },
+                last_layout,
             ),
         };
 
         let target_layout = match (last_layout, next.layout) {
-            (Layout::TransferDstOptimal, _) => Layout::TransferDstOptimal,
+            (Layout::TransferSrcOptimal, _) => Layout::TransferSrcOptimal,
             (_, Layout::General) => Layout::General,
             (Layout::General, _) => Layout::General,
-            _ => Layout::TransferDstOptimal,
+            _ => Layout::TransferSrcOptimal,
         };
 
-        if last_layout == Layout::Undefined || last_layout == target_layout {
-            last_access = Access::empty();
-        }
-
         family_uploads.barriers.add_image(
             image.clone(),
             image_range,
@@ -347,25 +909,90 @@ where
             next.access,
             next.layout,
         );
+        family_uploads.add_host_read_barrier();
 
+        let token = family_uploads.next_token(next.queue);
         let next_upload = family_uploads.next_upload(device, next.queue.index)?;
         let mut encoder = next_upload.command_buffer.encoder();
-        encoder.copy_buffer_to_image(
-            staging.raw(),
+        encoder.copy_image_to_buffer(
             image.raw(),
             target_layout,
+            staging.lock().raw(),
             Some(rendy_core::hal::command::BufferImageCopy {
                 buffer_offset: 0,
                 buffer_width: data_width,
-                buffer_height: data_height,
+                buffer_height: 0,
                 image_layers,
                 image_offset,
                 image_extent,
             }),
         );
 
-        next_upload.staging_buffers.push(staging);
-        Ok(())
+        next_upload.downloads.push(staging);
+        Ok(token)
+    }
+
+    /// Check if submission the `token` belongs to is complete.
+    ///
+    /// # Safety
+    ///
+    /// `device` must be the same that was used to create this `Uploader`.
+    ///
+    pub(crate) unsafe fn is_complete(&self, device: &Device<B>, token: UploadToken) -> bool {
+        let mut family_uploads = self.family_uploads[token.queue.family.index]
+            .as_ref()
+            .unwrap()
+            .lock();
+
+        if !family_uploads.is_complete(token) {
+            family_uploads.cleanup(device);
+        }
+        family_uploads.is_complete(token)
+    }
+
+    /// Wait for submission the `token` belongs to.
+    /// Returns `false` if timeout expired
+    /// or if submission wasn't flushed yet.
+    ///
+    /// # Safety
+    ///
+    /// `device` must be the same that was used to create this `Uploader`.
+    ///
+    pub(crate) unsafe fn wait(
+        &self,
+        device: &Device<B>,
+        token: UploadToken,
+        timeout_ns: u64,
+    ) -> Result<bool, OomOrDeviceLost> {
+        let family_uploads = self.family_uploads[token.queue.family.index]
+            .as_ref()
+            .unwrap();
+
+        let fence =
+            {
+                let family_uploads = family_uploads.lock();
+
+                if family_uploads.is_complete(token) {
+                    return Ok(true);
+                }
+
+                match family_uploads.pending.iter().find(|pending| {
+                    pending.queue == token.queue.index && pending.epoch == token.epoch
+                }) {
+                    Some(pending) => pending.fence.clone(),
+                    None => return Ok(false),
+                }
+            };
+
+        // Uploads are not locked while waiting.
+        // Shared fence is not reset and reused until waiting is over.
+        let signalled = device.wait_for_fence(&fence, timeout_ns)?;
+        drop(fence);
+
+        if signalled {
+            family_uploads.lock().cleanup(device);
+        }
+        Ok(signalled)
     }
 
     /// Cleanup pending updates.
@@ -389,11 +1016,48 @@ where
     /// `families` must be the same that was used to create this `Uploader`.
     ///
     pub(crate) unsafe fn flush(&mut self, families: &mut Families<B>) {
+        // Transfer family releases resources to other families.
+        // Its submission must be made first as it signals semaphores
+        // waited by submissions that acquire the resources.
+        if let Some(transfer) = self.transfer {
+            let mut transfer_uploads = self.family_uploads[transfer.index]
+                .take()
+                .expect("Uploader must be initialized for all families")
+                .into_inner();
+
+            // Uploads are recorded on the first queue of transfer family
+            // before acquiring submissions that wait for these semaphores are created.
+            // So if there are semaphores to signal then there is a submission to signal them.
+            let signals = self
+                .family_uploads
+                .iter_mut()
+                .filter_map(Option::as_mut)
+                .flat_map(|uploader| {
+                    uploader
+                        .get_mut()
+                        .next
+                        .iter()
+                        .filter_map(|next| next.as_ref()?.semaphore.as_ref())
+                })
+                .collect::<SmallVec<[_; 8]>>();
+
+            assert!(
+                signals.is_empty() || transfer_uploads.next.get(0).map_or(false, Option::is_some),
+                "Acquiring submissions wait for transfer submission that doesn't exist"
+            );
+
+            transfer_uploads.flush(families.family_mut(transfer), &signals);
+            self.family_uploads[transfer.index] = Some(parking_lot::Mutex::new(transfer_uploads));
+        }
+
         for family in families.as_slice_mut() {
+            if Some(family.id()) == self.transfer {
+                continue;
+            }
             let uploader = self.family_uploads[family.id().index]
                 .as_mut()
                 .expect("Uploader must be initialized for all families");
-            uploader.get_mut().flush(family);
+            uploader.get_mut().flush(family, &[]);
         }
     }
 
@@ -406,43 +1070,64 @@ where
         self.family_uploads.drain(..).for_each(|fu| {
             fu.map(|fu| fu.into_inner().dispose(device));
         });
+        // Staging chunks are destroyed with other resources.
+        self.staging.clear();
     }
 }
 
 #[derive(Debug)]
 pub(crate) struct FamilyUploads<B: rendy_core::hal::Backend> {
-    pool: CommandPool<B, Transfer, IndividualReset>,
+    pool: CommandPool<B, QueueType, IndividualReset>,
     command_buffers:
-        Vec<[CommandBuffer<B, Transfer, InitialState, PrimaryLevel, IndividualReset>; 2]>,
+        Vec<[CommandBuffer<B, QueueType, InitialState, PrimaryLevel, IndividualReset>; 2]>,
     next: Vec<Option<NextUploads<B>>>,
     pending: VecDeque<PendingUploads<B>>,
+    next_epochs: Vec<u64>,
+    complete_epochs: Vec<u64>,
     fences: Vec<B::Fence>,
+    semaphores: Vec<B::Semaphore>,
     barriers: Barriers<B>,
 }
 
 #[derive(Debug)]
 pub(crate) struct PendingUploads<B: rendy_core::hal::Backend> {
-    barrier_buffer: CommandBuffer<B, Transfer, PendingOnceState, PrimaryLevel, IndividualReset>,
-    command_buffer: CommandBuffer<B, Transfer, PendingOnceState, PrimaryLevel, IndividualReset>,
+    barrier_buffer: CommandBuffer<B, QueueType, PendingOnceState, PrimaryLevel, IndividualReset>,
+    command_buffer: CommandBuffer<B, QueueType, PendingOnceState, PrimaryLevel, IndividualReset>,
     staging_buffers: Vec<Escape<Buffer<B>>>,
-    fence: B::Fence,
+    downloads: Vec<SharedStaging<B>>,
+
+    /// Fence shared with threads that wait for it.
+    fence: Arc<B::Fence>,
+    semaphore: Option<B::Semaphore>,
+    queue: usize,
+    epoch: u64,
 }
 
 #[derive(Debug)]
 struct NextUploads<B: rendy_core::hal::Backend> {
     barrier_buffer:
-        CommandBuffer<B, Transfer, RecordingState<OneShot>, PrimaryLevel, IndividualReset>,
+        CommandBuffer<B, QueueType, RecordingState<OneShot>, PrimaryLevel, IndividualReset>,
     command_buffer:
-        CommandBuffer<B, Transfer, RecordingState<OneShot>, PrimaryLevel, IndividualReset>,
+        CommandBuffer<B, QueueType, RecordingState<OneShot>, PrimaryLevel, IndividualReset>,
     staging_buffers: Vec<Escape<Buffer<B>>>,
+    downloads: Vec<SharedStaging<B>>,
     fence: B::Fence,
+
+    /// Semaphore signalled by transfer family submission
+    /// that releases resources acquired by this submission.
+    semaphore: Option<B::Semaphore>,
+
+    /// Stages that wait for the `semaphore`.
+    wait_stages: rendy_core::hal::pso::PipelineStage,
 }
 
 impl<B> FamilyUploads<B>
 where
     B: rendy_core::hal::Backend,
 {
-    unsafe fn flush(&mut self, family: &mut Family<B>) {
+    /// Submit recorded uploads.
+    /// `signals` are signalled by submission to the first queue.
+    unsafe fn flush(&mut self, family: &mut Family<B>, signals: &[&B::Semaphore]) {
         for (queue, mut next) in self
             .next
             .drain(..)
@@ -458,28 +1143,366 @@ where
             let (barriers_submit, barrier_buffer) = next.barrier_buffer.finish().submit_once();
             let (submit, command_buffer) = next.command_buffer.finish().submit_once();
 
+            let wait_stages = next.wait_stages;
+            let signals = if queue == 0 { signals } else { &[] };
             family.queue_mut(queue).submit_raw_fence(
-                Some(Submission::new().submits(once(barriers_submit).chain(once(submit)))),
+                Some(
+                    Submission::new()
+                        .wait(
+                            next.semaphore
+                                .iter()
+                                .map(|semaphore| (semaphore, wait_stages)),
+                        )
+                        .submits(once(barriers_submit).chain(once(submit)))
+                        .signal(signals.iter()),
+                ),
                 Some(&next.fence),
             );
 
+            let epoch = self.next_epochs[queue];
+            self.next_epochs[queue] += 1;
+
             self.pending.push_back(PendingUploads {
                 barrier_buffer,
                 command_buffer,
                 staging_buffers: next.staging_buffers,
-                fence: next.fence,
+                downloads: next.downloads,
+                fence: Arc::new(next.fence),
+                semaphore: next.semaphore,
+                queue,
+                epoch,
             });
         }
     }
 
-    unsafe fn next_upload(
+    /// Get token of the next submission to the `queue`.
+    fn next_token(&mut self, queue: QueueId) -> UploadToken {
+        self.reserve_queue(queue.index);
+        UploadToken::new(queue, self.next_epochs[queue.index])
+    }
+
+    fn reserve_queue(&mut self, queue: usize) {
+        while self.next.len() <= queue {
+            self.next.push(None);
+        }
+        while self.next_epochs.len() <= queue {
+            self.next_epochs.push(0);
+            self.complete_epochs.push(0);
+        }
+    }
+
+    /// Check if submission the `token` belongs to is complete.
+    fn is_complete(&self, token: UploadToken) -> bool {
+        self.complete_epochs
+            .get(token.queue.index)
+            .map_or(false, |&complete| token.epoch < complete)
+    }
+
+    /// Make writes to download staging buffers visible to the host.
+    fn add_host_read_barrier(&mut self) {
+        self.barriers.add_buffer(
+            rendy_core::hal::pso::PipelineStage::empty(),
+            rendy_core::hal::buffer::Access::empty(),
+            rendy_core::hal::pso::PipelineStage::HOST,
+            rendy_core::hal::buffer::Access::HOST_READ,
+        );
+    }
+
+    /// Record copying of the `src` buffer region to the `dst` buffer
+    /// with barriers for `dst` from `last` to `next` state.
+    unsafe fn record_buffer_upload(
+        &mut self,
+        device: &Device<B>,
+        src: &B::Buffer,
+        region: rendy_core::hal::command::BufferCopy,
+        dst: &Buffer<B>,
+        last: Option<BufferState>,
+        next: BufferState,
+    ) -> Result<&mut NextUploads<B>, OutOfMemory> {
+        self.add_buffer_write_barrier(last, next);
+
+        let next_upload = self.next_upload(device, next.queue.index)?;
+        let mut encoder = next_upload.command_buffer.encoder();
+        encoder.copy_buffer(src, dst.raw(), Some(region));
+        Ok(next_upload)
+    }
+
+    /// Add barriers for buffer written by transfer operation
+    /// from `last` to `next` state.
+    fn add_buffer_write_barrier(&mut self, last: Option<BufferState>, next: BufferState) {
+        if let Some(last) = last {
+            if last.queue != next.queue {
+                unimplemented!("Can't sync resources across queues");
+            }
+        }
+
+        self.barriers.add_buffer(
+            last.map_or(rendy_core::hal::pso::PipelineStage::empty(), |l| l.stage),
+            rendy_core::hal::buffer::Access::empty(),
+            next.stage,
+            next.access,
+        );
+    }
+
+    /// Record copying of the `src` buffer region to the `image`
+    /// with barriers for the image from `last` to `next` state.
+    unsafe fn record_image_upload(
+        &mut self,
+        device: &Device<B>,
+        src: &B::Buffer,
+        region: rendy_core::hal::command::BufferImageCopy,
+        image: Handle<Image<B>>,
+        last: ImageStateOrLayout,
+        next: ImageState,
+    ) -> Result<&mut NextUploads<B>, OutOfMemory> {
+        let whole_level = is_whole_level(&image, &region);
+
+        let image_range = rendy_core::hal::image::SubresourceRange {
+            aspects: region.image_layers.aspects,
+            levels: region.image_layers.level..region.image_layers.level + 1,
+            layers: region.image_layers.layers.clone(),
+        };
+
+        let target_layout =
+            self.add_image_write_barrier(image.clone(), image_range, last, next, whole_level);
+
+        let next_upload = self.next_upload(device, next.queue.index)?;
+        let mut encoder = next_upload.command_buffer.encoder();
+        encoder.copy_buffer_to_image(src, image.raw(), target_layout, Some(region));
+        Ok(next_upload)
+    }
+
+    /// Add barriers for image subresource range written by transfer operation
+    /// from `last` to `next` state.
+    /// If `discard` is set previous content of the range is not preserved.
+    ///
+    /// Returns layout the range should be in for the transfer operation.
+    fn add_image_write_barrier(
+        &mut self,
+        image: Handle<Image<B>>,
+        image_range: rendy_core::hal::image::SubresourceRange,
+        last: ImageStateOrLayout,
+        next: ImageState,
+        discard: bool,
+    ) -> rendy_core::hal::image::Layout {
+        use rendy_core::hal::image::{Access, Layout};
+
+        let (last_stage, mut last_access, last_layout) = match last {
+            ImageStateOrLayout::State(last) => {
+                if last.queue != next.queue {
+                    unimplemented!("Can't sync resources across queues");
+                }
+                (
+                    last.stage,
+                    last.access,
+                    if discard {
+                        Layout::Undefined
+                    } else {
+                        last.layout
+                    },
+                )
+            }
+            ImageStateOrLayout::Layout(last_layout) => (
+                rendy_core::hal::pso::PipelineStage::TOP_OF_PIPE,
+                Access::empty(),
+                if discard {
+                    Layout::Undefined
+                } else {
+                    last_layout
+                },
+            ),
+        };
+
+        let target_layout = match (last_layout, next.layout) {
+            (Layout::TransferDstOptimal, _) => Layout::TransferDstOptimal,
+            (_, Layout::General) => Layout::General,
+            (Layout::General, _) => Layout::General,
+            _ => Layout::TransferDstOptimal,
+        };
+
+        if last_layout == Layout::Undefined || last_layout == target_layout {
+            last_access = Access::empty();
+        }
+
+        self.barriers.add_image(
+            image,
+            image_range,
+            last_stage,
+            last_access,
+            last_layout,
+            target_layout,
+            next.stage,
+            next.access,
+            next.layout,
+        );
+
+        target_layout
+    }
+
+    /// Record copying of the `src` buffer region to the `dst` buffer
+    /// that is not used by device yet
+    /// on the first queue of this transfer family.
+    /// Ownership of the buffer range is released to the family of the `next` queue
+    /// and acquired by submission of `target` uploads.
+    unsafe fn record_buffer_transfer(
+        &mut self,
+        device: &Device<B>,
+        queue: QueueId,
+        target: &mut FamilyUploads<B>,
+        src: &B::Buffer,
+        region: rendy_core::hal::command::BufferCopy,
+        dst: &Buffer<B>,
+        next: BufferState,
+    ) -> Result<&mut NextUploads<B>, OutOfMemory> {
+        use rendy_core::hal::{
+            buffer::Access,
+            memory::{Barrier, Dependencies},
+            pso::PipelineStage,
+        };
+
+        let families = Some(queue.family.into()..next.queue.family.into());
+        let range = Some(region.dst)..Some(region.dst + region.size);
+
+        // Release is recorded first, so that transfer submission exists
+        // to signal the semaphore the acquiring submission waits for.
+        let next_upload = self.next_upload(device, queue.index)?;
+        let mut encoder = next_upload.command_buffer.encoder();
+        encoder.copy_buffer(src, dst.raw(), Some(region));
+        encoder.pipeline_barrier(
+            PipelineStage::TRANSFER..PipelineStage::BOTTOM_OF_PIPE,
+            Dependencies::empty(),
+            Some(Barrier::Buffer {
+                states: Access::TRANSFER_WRITE..Access::empty(),
+                target: dst.raw(),
+                families: families.clone(),
+                range: range.clone(),
+            }),
+        );
+
+        let acquire = target.next_acquire(device, next.queue.index, next.stage)?;
+        acquire.command_buffer.encoder().pipeline_barrier(
+            PipelineStage::TOP_OF_PIPE..next.stage,
+            Dependencies::empty(),
+            Some(Barrier::Buffer {
+                states: Access::empty()..next.access,
+                target: dst.raw(),
+                families,
+                range,
+            }),
+        );
+
+        Ok(next_upload)
+    }
+
+    /// Record copying of the `src` buffer region to the `image`
+    /// that is not used by device yet
+    /// on the first queue of this transfer family.
+    /// Ownership of the image subresources is released to the family of the `next` queue
+    /// and acquired by submission of `target` uploads.
+    unsafe fn record_image_transfer(
+        &mut self,
+        device: &Device<B>,
+        queue: QueueId,
+        target: &mut FamilyUploads<B>,
+        src: &B::Buffer,
+        region: rendy_core::hal::command::BufferImageCopy,
+        image: Handle<Image<B>>,
+        last: ImageStateOrLayout,
+        next: ImageState,
+    ) -> Result<&mut NextUploads<B>, OutOfMemory> {
+        use rendy_core::hal::{
+            image::{Access, Layout},
+            memory::{Barrier, Dependencies},
+            pso::PipelineStage,
+        };
+
+        let last_layout = match last {
+            ImageStateOrLayout::Layout(_) if is_whole_level(&image, &region) => Layout::Undefined,
+            ImageStateOrLayout::Layout(last_layout) => last_layout,
+            ImageStateOrLayout::State(_) => {
+                unreachable!("Only images not used by device are uploaded on transfer family")
+            }
+        };
+
+        let families = Some(queue.family.into()..next.queue.family.into());
+        let image_range = rendy_core::hal::image::SubresourceRange {
+            aspects: region.image_layers.aspects,
+            levels: region.image_layers.level..region.image_layers.level + 1,
+            layers: region.image_layers.layers.clone(),
+        };
+
+        // Release is recorded first, so that transfer submission exists
+        // to signal the semaphore the acquiring submission waits for.
+        // Layout transition is performed by release and acquire barriers.
+        let next_upload = self.next_upload(device, queue.index)?;
+        let mut encoder = next_upload.command_buffer.encoder();
+        encoder.pipeline_barrier(
+            PipelineStage::TOP_OF_PIPE..PipelineStage::TRANSFER,
+            Dependencies::empty(),
+            Some(Barrier::Image {
+                states: (Access::empty(), last_layout)
+                    ..(Access::TRANSFER_WRITE, Layout::TransferDstOptimal),
+                target: image.raw(),
+                families: None,
+                range: image_range.clone(),
+            }),
+        );
+        encoder.copy_buffer_to_image(src, image.raw(), Layout::TransferDstOptimal, Some(region));
+        encoder.pipeline_barrier(
+            PipelineStage::TRANSFER..PipelineStage::BOTTOM_OF_PIPE,
+            Dependencies::empty(),
+            Some(Barrier::Image {
+                states: (Access::TRANSFER_WRITE, Layout::TransferDstOptimal)
+                    ..(Access::empty(), next.layout),
+                target: image.raw(),
+                families: families.clone(),
+                range: image_range.clone(),
+            }),
+        );
+
+        let acquire = target.next_acquire(device, next.queue.index, next.stage)?;
+        acquire.command_buffer.encoder().pipeline_barrier(
+            PipelineStage::TOP_OF_PIPE..next.stage,
+            Dependencies::empty(),
+            Some(Barrier::Image {
+                states: (Access::empty(), Layout::TransferDstOptimal)..(next.access, next.layout),
+                target: image.raw(),
+                families,
+                range: image_range,
+            }),
+        );
+
+        Ok(next_upload)
+    }
+
+    /// Get next submission to the `queue` that acquires resources
+    /// released by transfer family.
+    /// Acquiring submission waits for the transfer submission on `stage`.
+    unsafe fn next_acquire(
         &mut self,
         device: &Device<B>,
         queue: usize,
+        stage: rendy_core::hal::pso::PipelineStage,
     ) -> Result<&mut NextUploads<B>, OutOfMemory> {
-        while self.next.len() <= queue {
-            self.next.push(None);
+        self.next_upload(device, queue)?;
+        let next = self.next[queue].as_mut().unwrap();
+        if next.semaphore.is_none() {
+            next.semaphore = Some(
+                self.semaphores
+                    .pop()
+                    .map_or_else(|| device.create_semaphore(), Ok)?,
+            );
         }
+        next.wait_stages |= stage;
+        Ok(next)
+    }
+
+    unsafe fn next_upload(
+        &mut self,
+        device: &Device<B>,
+        queue: usize,
+    ) -> Result<&mut NextUploads<B>, OutOfMemory> {
+        self.reserve_queue(queue);
 
         let pool = &mut self.pool;
 
@@ -498,7 +1521,10 @@ where
                     barrier_buffer: buf_a.begin(OneShot, ()),
                     command_buffer: buf_b.begin(OneShot, ()),
                     staging_buffers: Vec::new(),
+                    downloads: Vec::new(),
                     fence,
+                    semaphore: None,
+                    wait_stages: rendy_core::hal::pso::PipelineStage::empty(),
                 });
 
                 Ok(slot.as_mut().unwrap())
@@ -513,20 +1539,33 @@ where
     /// `device` must be the same that was used with other methods of this instance.
     ///
     unsafe fn cleanup(&mut self, device: &Device<B>) {
-        while let Some(pending) = self.pending.pop_front() {
+        // Submissions to different queues may complete in any order.
+        for _ in 0..self.pending.len() {
+            let pending = self.pending.pop_front().unwrap();
             match device.get_fence_status(&pending.fence) {
                 Ok(false) => {
-                    self.pending.push_front(pending);
-                    return;
+                    self.pending.push_back(pending);
                 }
                 Err(rendy_core::hal::device::DeviceLost) => {
                     panic!("Device lost error is not handled yet");
                 }
                 Ok(true) => {
+                    let ref mut complete = self.complete_epochs[pending.queue];
+                    *complete = (*complete).max(pending.epoch + 1);
+
+                    if Arc::strong_count(&pending.fence) > 1 {
+                        // Some thread waits for the fence, recycle it later.
+                        self.pending.push_back(pending);
+                        continue;
+                    }
+
+                    let fence = Arc::try_unwrap(pending.fence)
+                        .unwrap_or_else(|_| unreachable!("Fence is not shared"));
                     device
-                        .reset_fence(&pending.fence)
+                        .reset_fence(&fence)
                         .expect("Can always reset signalled fence");
-                    self.fences.push(pending.fence);
+                    self.fences.push(fence);
+                    self.semaphores.extend(pending.semaphore);
                     self.command_buffers.push([
                         pending.command_buffer.mark_complete().reset(),
                         pending.barrier_buffer.mark_complete().reset(),
@@ -543,7 +1582,13 @@ where
     unsafe fn dispose(mut self, device: &Device<B>) {
         let pool = &mut self.pool;
         self.pending.drain(..).for_each(|pending| {
-            device.destroy_fence(pending.fence);
+            device.destroy_fence(
+                Arc::try_unwrap(pending.fence)
+                    .unwrap_or_else(|_| unreachable!("Fence is not waited while device is idle")),
+            );
+            if let Some(semaphore) = pending.semaphore {
+                device.destroy_semaphore(semaphore);
+            }
             pool.free_buffers(Some(pending.command_buffer.mark_complete()));
             pool.free_buffers(Some(pending.barrier_buffer.mark_complete()));
         });
@@ -551,6 +1596,9 @@ where
         self.fences
             .drain(..)
             .for_each(|fence| device.destroy_fence(fence));
+        self.semaphores
+            .drain(..)
+            .for_each(|semaphore| device.destroy_semaphore(semaphore));
         pool.free_buffers(
             self.command_buffers
                 .drain(..)
@@ -559,9 +1607,163 @@ where
 
         pool.free_buffers(self.next.drain(..).filter_map(|n| n).flat_map(|next| {
             device.destroy_fence(next.fence);
+            if let Some(semaphore) = next.semaphore {
+                device.destroy_semaphore(semaphore);
+            }
             once(next.command_buffer).chain(once(next.barrier_buffer))
         }));
         drop(pool);
         self.pool.dispose(device);
     }
 }
+
+/// Check if copy region covers whole mip level of the image.
+fn is_whole_level<B: rendy_core::hal::Backend>(
+    image: &Image<B>,
+    region: &rendy_core::hal::command::BufferImageCopy,
+) -> bool {
+    let whole_extent = if region.image_layers.level == 0 {
+        image.kind().extent()
+    } else {
+        image.kind().level_extent(region.image_layers.level)
+    };
+
+    region.image_offset == rendy_core::hal::image::Offset::ZERO
+        && region.image_extent == whole_extent
+}
+
+/// Check that resource used on `last` queue can be used on `next` queue.
+/// Ownership transfer between queues is not recorded by `Uploader`,
+/// so resource must stay on the same queue.
+pub(crate) fn check_queue(last: Option<QueueId>, next: QueueId) -> Result<(), UploadError> {
+    match last {
+        Some(last) if last != next => Err(UploadError::QueueMismatch { last, next }),
+        _ => Ok(()),
+    }
+}
+
+/// Get queue to record upload on for resource that will be used on `next` queue.
+/// See `Uploader::upload_queue`.
+fn route_upload(transfer: Option<FamilyId>, next: QueueId, idle: bool) -> QueueId {
+    match transfer {
+        Some(family) if idle && family != next.family => QueueId { family, index: 0 },
+        _ => next,
+    }
+}
+
+/// Lock slot of the family of the `queue`
+/// and slot of the family of the `next` queue if it is different.
+/// Family of the `queue` is always locked first.
+fn lock_pair<T>(
+    slots: &[Option<parking_lot::Mutex<T>>],
+    queue: QueueId,
+    next: QueueId,
+) -> (
+    parking_lot::MutexGuard<'_, T>,
+    Option<parking_lot::MutexGuard<'_, T>>,
+) {
+    let first = slots[queue.family.index].as_ref().unwrap().lock();
+    let second = if queue.family != next.family {
+        Some(slots[next.family.index].as_ref().unwrap().lock())
+    } else {
+        None
+    };
+    (first, second)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{check_queue, lock_pair, route_upload},
+        crate::{
+            command::{FamilyId, QueueId},
+            core::{DeviceId, InstanceId},
+            UploadError,
+        },
+    };
+
+    fn queue(device: DeviceId, family: usize, index: usize) -> QueueId {
+        QueueId {
+            family: FamilyId {
+                index: family,
+                device,
+            },
+            index,
+        }
+    }
+
+    #[test]
+    fn upload_without_transfer_family() {
+        let device = DeviceId::new(InstanceId::new());
+        let next = queue(device, 1, 2);
+        assert_eq!(route_upload(None, next, true), next);
+        assert_eq!(route_upload(None, next, false), next);
+    }
+
+    #[test]
+    fn idle_upload_on_transfer_family() {
+        let device = DeviceId::new(InstanceId::new());
+        let transfer = queue(device, 0, 0);
+        let next = queue(device, 1, 2);
+        assert_eq!(route_upload(Some(transfer.family), next, true), transfer);
+    }
+
+    #[test]
+    fn used_upload_on_next_queue() {
+        let device = DeviceId::new(InstanceId::new());
+        let transfer = queue(device, 0, 0);
+        let next = queue(device, 1, 2);
+        assert_eq!(route_upload(Some(transfer.family), next, false), next);
+    }
+
+    #[test]
+    fn upload_on_transfer_family_queue() {
+        let device = DeviceId::new(InstanceId::new());
+        let transfer = queue(device, 0, 0);
+        let next = queue(device, 0, 1);
+        assert_eq!(route_upload(Some(transfer.family), next, true), next);
+    }
+
+    #[test]
+    fn lock_same_family_once() {
+        let device = DeviceId::new(InstanceId::new());
+        let slots = vec![Some(parking_lot::Mutex::new(0)), None];
+        let (first, second) = lock_pair(&slots, queue(device, 0, 0), queue(device, 0, 1));
+        assert_eq!(*first, 0);
+        assert!(second.is_none());
+    }
+
+    #[test]
+    fn lock_both_families() {
+        let device = DeviceId::new(InstanceId::new());
+        let slots = vec![
+            Some(parking_lot::Mutex::new(0)),
+            None,
+            Some(parking_lot::Mutex::new(2)),
+        ];
+        let (first, second) = lock_pair(&slots, queue(device, 0, 0), queue(device, 2, 1));
+        assert_eq!(*first, 0);
+        assert_eq!(second.map(|second| *second), Some(2));
+        assert!(slots[0].as_ref().unwrap().try_lock().is_none());
+    }
+
+    #[test]
+    fn same_queue() {
+        let device = DeviceId::new(InstanceId::new());
+        let next = queue(device, 0, 1);
+        assert_eq!(check_queue(None, next), Ok(()));
+        assert_eq!(check_queue(Some(next), next), Ok(()));
+    }
+
+    #[test]
+    fn queue_mismatch() {
+        let device = DeviceId::new(InstanceId::new());
+        let next = queue(device, 0, 1);
+        for &last in &[queue(device, 0, 0), queue(device, 1, 1)] {
+            assert_eq!(
+                check_queue(Some(last), next),
+                Err(UploadError::QueueMismatch { last, next })
+            );
+        }
+    }
+}
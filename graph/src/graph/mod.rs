@@ -1,14 +1,17 @@
 use {
     crate::{
         chain,
-        command::{Families, FamilyId, QueueId},
+        command::{
+            CommandBuffer, CommandPool, ExecutableState, Families, FamilyId, MultiShot,
+            PendingState, QueueId, QueueType, SimultaneousUse, Submission, Submit,
+        },
         core::{device_owned, DeviceId},
         factory::Factory,
         frame::{Fences, Frame, Frames},
-        memory::Data,
+        memory::{Data, MemoryUsageValue},
         node::{
-            BufferBarrier, DynNode, ImageBarrier, NodeBuffer, NodeBuildError, NodeBuilder,
-            NodeImage,
+            debug_name, BufferBarrier, DynNode, ImageBarrier, NodeBuffer, NodeBuildError,
+            NodeBuilder, NodeImage,
         },
         resource::{
             Buffer, BufferCreationError, BufferInfo, Handle, Image, ImageCreationError, ImageInfo,
@@ -23,6 +26,59 @@ use {
 struct GraphNode<B: Backend, T: ?Sized> {
     node: Box<dyn DynNode<B, T>>,
     queue: (usize, usize),
+    label: Option<NodeLabel<B>>,
+}
+
+/// Command buffers that open and close debug label region around node's submissions.
+/// Recorded once and submitted every frame.
+#[derive(Debug)]
+struct NodeLabel<B: Backend> {
+    begin_submit: Submit<B, SimultaneousUse>,
+    begin: CommandBuffer<B, QueueType, PendingState<ExecutableState<MultiShot<SimultaneousUse>>>>,
+    end_submit: Submit<B, SimultaneousUse>,
+    end: CommandBuffer<B, QueueType, PendingState<ExecutableState<MultiShot<SimultaneousUse>>>>,
+}
+
+impl<B> NodeLabel<B>
+where
+    B: Backend,
+{
+    fn new(pool: &mut CommandPool<B, QueueType>, name: &str) -> Self {
+        let mut buffers = pool.allocate_buffers(2).into_iter();
+
+        let mut begin = buffers
+            .next()
+            .unwrap()
+            .begin(MultiShot(SimultaneousUse), ());
+        unsafe {
+            // Region is closed by `end` buffer submitted after to the same queue.
+            begin.encoder().push_debug_label(&name.replace('\0', ""), 0);
+        }
+        let (begin_submit, begin) = begin.finish().submit();
+
+        let mut end = buffers
+            .next()
+            .unwrap()
+            .begin(MultiShot(SimultaneousUse), ());
+        unsafe {
+            // Region is opened by `begin` buffer submitted before to the same queue.
+            end.encoder().pop_debug_label();
+        }
+        let (end_submit, end) = end.finish().submit();
+
+        NodeLabel {
+            begin_submit,
+            begin,
+            end_submit,
+            end,
+        }
+    }
+
+    unsafe fn dispose(self, pool: &mut CommandPool<B, QueueType>) {
+        drop(self.begin_submit);
+        drop(self.end_submit);
+        pool.free_buffers(vec![self.begin.mark_complete(), self.end.mark_complete()]);
+    }
 }
 
 /// Graph that renders whole frame.
@@ -30,6 +86,7 @@ struct GraphNode<B: Backend, T: ?Sized> {
 pub struct Graph<B: Backend, T: ?Sized> {
     device: DeviceId,
     nodes: Vec<GraphNode<B, T>>,
+    label_pools: Vec<CommandPool<B, QueueType>>,
     schedule: chain::Schedule<chain::SyncData<usize, usize>>,
     semaphores: Vec<B::Semaphore>,
     frames: Frames<B>,
@@ -51,6 +108,8 @@ pub enum GraphBuildError {
     Semaphore(rendy_core::hal::device::OutOfMemory),
     /// Failed to build a node.
     Node(NodeBuildError),
+    /// Failed to create a command pool.
+    CommandPool(rendy_core::hal::device::OutOfMemory),
 }
 
 impl std::fmt::Display for GraphBuildError {
@@ -76,6 +135,11 @@ impl std::fmt::Display for GraphBuildError {
                 "Failed to build graph because of failure to build a node: {:?}",
                 err
             ),
+            GraphBuildError::CommandPool(err) => write!(
+                fmt,
+                "Failed to build graph because of failure to create a command pool: {:?}",
+                err
+            ),
         }
     }
 }
@@ -87,6 +151,7 @@ impl std::error::Error for GraphBuildError {
             GraphBuildError::Image(err) => Some(err),
             GraphBuildError::Semaphore(err) => Some(err),
             GraphBuildError::Node(err) => Some(err),
+            GraphBuildError::CommandPool(err) => Some(err),
         }
     }
 }
@@ -148,13 +213,23 @@ impl<B: Backend> GraphContext<B> {
                     .images
                     .get(&chain::Id(index))
                     .map(|image| {
+                        let (usage, memory_usage) = if transient_attachment(image, clear.is_some())
+                        {
+                            (
+                                image.usage() | rendy_core::hal::image::Usage::TRANSIENT_ATTACHMENT,
+                                MemoryUsageValue::Transient,
+                            )
+                        } else {
+                            (image.usage(), MemoryUsageValue::Data)
+                        };
+
                         factory
                             .create_image(
                                 ImageInfo {
-                                    usage: image.usage(),
+                                    usage,
                                     ..info.clone()
                                 },
-                                Data,
+                                memory_usage,
                             )
                             .map(|image| Some((image.into(), *clear)))
                     })
@@ -195,6 +270,38 @@ impl<B: Backend> GraphContext<B> {
     }
 }
 
+/// Check if image is used only as attachment by single submission
+/// and is `cleared` by it.
+/// Content of such image never leaves render pass
+/// and isn't loaded from the previous frame,
+/// so it can be allocated from lazily allocated memory.
+fn transient_attachment(chain: &chain::Chain<chain::Image>, cleared: bool) -> bool {
+    let attachment = rendy_core::hal::image::Usage::COLOR_ATTACHMENT
+        | rendy_core::hal::image::Usage::DEPTH_STENCIL_ATTACHMENT
+        | rendy_core::hal::image::Usage::INPUT_ATTACHMENT;
+
+    // Not cleared attachment is loaded with content left from previous frame.
+    if !cleared {
+        return false;
+    }
+
+    let mut submissions = chain.links().iter().map(|link| {
+        if !attachment.contains(link.usage()) {
+            return None;
+        }
+        let mut queues = link.queues();
+        match (queues.next(), queues.next()) {
+            (Some((queue, state)), None) if state.first == state.last => Some((queue, state.first)),
+            _ => None,
+        }
+    });
+
+    match submissions.next() {
+        Some(Some(first)) => submissions.all(|submission| submission == Some(first)),
+        _ => false,
+    }
+}
+
 impl<B, T> Graph<B, T>
 where
     B: Backend,
@@ -202,6 +309,12 @@ where
 {
     /// Perform graph execution.
     /// Run every node of the graph and submit resulting command buffers to the queues.
+    ///
+    /// If debug labels are enabled, submissions of each node are wrapped
+    /// into debug label region named after the node.
+    /// See [`GraphBuilder::with_debug_labels`].
+    ///
+    /// [`GraphBuilder::with_debug_labels`]: struct.GraphBuilder.html#method.with_debug_labels
     pub fn run(&mut self, factory: &mut Factory<B>, families: &mut Families<B>, aux: &T) {
         profile_scope!("run");
 
@@ -225,7 +338,7 @@ where
             let sid = submission.id();
             let qid = sid.queue();
 
-            let GraphNode { node, queue } = self
+            let GraphNode { node, queue, label } = self
                 .nodes
                 .get_mut(submission.node())
                 .expect("Submission references node with out of bound index");
@@ -246,11 +359,22 @@ where
                 None
             };
 
+            let queue = families.family_by_index_mut(queue.0).queue_mut(queue.1);
+
+            if let Some(label) = label.as_ref() {
+                unsafe {
+                    queue.submit(
+                        Some(Submission::new().submits(Some(&label.begin_submit))),
+                        None,
+                    );
+                }
+            }
+
             unsafe {
                 node.run(
                     &self.ctx,
                     factory,
-                    families.family_by_index_mut(queue.0).queue_mut(queue.1),
+                    queue,
                     aux,
                     &self.frames,
                     &submission
@@ -282,6 +406,15 @@ where
                     fence,
                 )
             }
+
+            if let Some(label) = label.as_ref() {
+                unsafe {
+                    queue.submit(
+                        Some(Submission::new().submits(Some(&label.end_submit))),
+                        None,
+                    );
+                }
+            }
         }
 
         fences.truncate(fences_used);
@@ -311,10 +444,22 @@ where
 
         unsafe {
             // Device is idle.
+            let mut label_pools = self.label_pools;
             for node in self.nodes {
+                if let Some(label) = node.label {
+                    let pool = label_pools
+                        .iter_mut()
+                        .find(|pool| pool.family_id().index == node.queue.0)
+                        .expect("Label pool must exist for node's family");
+                    label.dispose(pool);
+                }
                 node.node.dispose(factory, data);
             }
 
+            for pool in label_pools {
+                factory.destroy_command_pool(pool);
+            }
+
             for semaphore in self.semaphores {
                 factory.destroy_semaphore(semaphore);
             }
@@ -333,6 +478,7 @@ pub struct GraphBuilder<B: Backend, T: ?Sized> {
     buffers: Vec<BufferInfo>,
     images: Vec<(ImageInfo, Option<rendy_core::hal::command::ClearValue>)>,
     frames_in_flight: u32,
+    debug_labels: bool,
 }
 
 impl<B, T> Default for GraphBuilder<B, T>
@@ -346,6 +492,7 @@ where
             buffers: Vec::default(),
             images: Vec::default(),
             frames_in_flight: u32::default(),
+            debug_labels: false,
         }
     }
 }
@@ -361,6 +508,7 @@ where
             .field("buffers", &self.buffers)
             .field("images", &self.images)
             .field("frames_in_flight", &self.frames_in_flight)
+            .field("debug_labels", &self.debug_labels)
             .finish()
     }
 }
@@ -377,6 +525,7 @@ where
             buffers: Vec::new(),
             images: Vec::new(),
             frames_in_flight: 3,
+            debug_labels: false,
         }
     }
 
@@ -432,6 +581,24 @@ where
         self
     }
 
+    /// Choose whether submissions of each node are wrapped into debug label region
+    /// shown by graphics debuggers.
+    /// Region is named by [`NodeBuilder::name`] or by the type name of the node.
+    ///
+    /// Nodes record and submit their own command buffers, often several per frame
+    /// or none when there is nothing to draw, so the region can't be opened and closed
+    /// inside them without changes to every node.
+    /// Instead command buffers that push and pop the label are recorded once per node
+    /// and submitted to the node's queue right before and after it runs.
+    /// That costs two more `queue.submit` calls per node every frame,
+    /// which is why labels are disabled by default.
+    ///
+    /// [`NodeBuilder::name`]: ../node/trait.NodeBuilder.html#method.name
+    pub fn with_debug_labels(mut self, debug_labels: bool) -> Self {
+        self.debug_labels = debug_labels;
+        self
+    }
+
     /// Build `Graph`.
     ///
     /// # Parameters
@@ -486,6 +653,7 @@ where
 
         log::trace!("Build nodes");
         let mut built_nodes: Vec<_> = (0..self.nodes.len()).map(|_| None).collect();
+        let mut node_names: Vec<Option<String>> = (0..self.nodes.len()).map(|_| None).collect();
         let mut node_descs: Vec<_> = self.nodes.into_iter().map(Some).collect();
 
         {
@@ -499,6 +667,7 @@ where
                         log::trace!("For submission {:#?}", submission.id());
                         let builder = node_descs[submission.node()].take().unwrap();
                         log::trace!("Build node {:#?}", builder);
+                        node_names[submission.node()] = builder.name().map(str::to_owned);
                         let node = build_node(
                             &mut ctx,
                             builder,
@@ -517,6 +686,45 @@ where
             }
         }
 
+        let mut label_pools: Vec<CommandPool<B, QueueType>> = Vec::new();
+        let mut nodes = Vec::with_capacity(built_nodes.len());
+        for (index, ((node, qid), name)) in built_nodes
+            .into_iter()
+            .map(Option::unwrap)
+            .zip(node_names)
+            .enumerate()
+        {
+            let label = if self.debug_labels {
+                let family = qid.family().0;
+                let pool = match label_pools
+                    .iter()
+                    .position(|pool| pool.family_id().index == family)
+                {
+                    Some(pool) => pool,
+                    None => {
+                        let pool = factory
+                            .create_command_pool(families.family_by_index(family))
+                            .map_err(GraphBuildError::CommandPool)?;
+                        label_pools.push(pool);
+                        label_pools.len() - 1
+                    }
+                };
+                let name = name.unwrap_or_else(|| match debug_name(&format!("{:?}", node)) {
+                    "" => format!("Node {}", index),
+                    name => name.to_owned(),
+                });
+                Some(NodeLabel::new(&mut label_pools[pool], &name))
+            } else {
+                None
+            };
+
+            nodes.push(GraphNode {
+                node,
+                queue: (qid.family().0, qid.index()),
+                label,
+            });
+        }
+
         log::debug!("Create {} semaphores", semaphores.start);
         let semaphores = (0..semaphores.start)
             .map(|_| factory.create_semaphore())
@@ -526,14 +734,8 @@ where
         Ok(Graph {
             device: factory.device().id(),
             ctx,
-            nodes: built_nodes
-                .into_iter()
-                .map(Option::unwrap)
-                .map(|(node, qid)| GraphNode {
-                    node,
-                    queue: (qid.family().0, qid.index()),
-                })
-                .collect(),
+            nodes,
+            label_pools,
             schedule,
             semaphores,
             inflight: self.frames_in_flight,
@@ -354,6 +354,13 @@ pub trait NodeBuilder<B: Backend, T: ?Sized>: std::fmt::Debug {
     /// Indices of nodes this one dependes on.
     fn dependencies(&self) -> Vec<NodeId>;
 
+    /// Get name of the node used to label its commands in graphics debuggers.
+    /// Node is labeled by the type name its `Debug` implementation prints
+    /// if `None` is returned.
+    fn name(&self) -> Option<&str> {
+        None
+    }
+
     /// Build node.
     fn build<'a>(
         self: Box<Self>,
@@ -373,6 +380,7 @@ pub struct DescBuilder<B: Backend, T: ?Sized, N> {
     buffers: Vec<BufferId>,
     images: Vec<ImageId>,
     dependencies: Vec<NodeId>,
+    name: Option<String>,
     marker: std::marker::PhantomData<fn(B, &T)>,
 }
 
@@ -388,6 +396,7 @@ where
             .field("buffers", &self.buffers)
             .field("images", &self.images)
             .field("dependencies", &self.dependencies)
+            .field("name", &self.name)
             .finish()
     }
 }
@@ -404,6 +413,7 @@ where
             buffers: Vec::new(),
             images: Vec::new(),
             dependencies: Vec::new(),
+            name: None,
             marker: std::marker::PhantomData,
         }
     }
@@ -448,6 +458,24 @@ where
         self.add_dependency(dependency);
         self
     }
+
+    /// Set name of the node.
+    /// See [`NodeBuilder::name`].
+    ///
+    /// [`NodeBuilder::name`]: trait.NodeBuilder.html#method.name
+    pub fn set_name(&mut self, name: impl Into<String>) -> &mut Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Set name of the node.
+    /// See [`NodeBuilder::name`].
+    ///
+    /// [`NodeBuilder::name`]: trait.NodeBuilder.html#method.name
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.set_name(name);
+        self
+    }
 }
 
 impl<B, T, N> NodeBuilder<B, T> for DescBuilder<B, T, N>
@@ -478,6 +506,10 @@ where
         self.dependencies.clone()
     }
 
+    fn name(&self) -> Option<&str> {
+        self.name.as_ref().map(String::as_str)
+    }
+
     fn build<'a>(
         self: Box<Self>,
         ctx: &GraphContext<B>,
@@ -595,3 +627,34 @@ pub fn gfx_release_barriers<'a, B: Backend>(
 
     (bstart | istart..bend | iend, barriers)
 }
+
+/// Strip module path and generic parameters from type name.
+/// Get type name from `Debug` output of the node,
+/// skipping tuple `DynNode` wrapper and fields.
+pub(crate) fn debug_name(debug: &str) -> &str {
+    let debug = debug.trim_start_matches('(');
+    let end = debug
+        .find(|c: char| c.is_whitespace() || "{(<,)".contains(c))
+        .unwrap_or(debug.len());
+    &debug[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::debug_name;
+
+    #[test]
+    fn debug_name_of_struct() {
+        assert_eq!(debug_name("(MyNode { pipeline: 1 },)"), "MyNode");
+        assert_eq!(debug_name("MyNode { pipeline: 1 }"), "MyNode");
+        assert_eq!(debug_name("(MyNode {\n    pipeline: 1,\n},)"), "MyNode");
+    }
+
+    #[test]
+    fn debug_name_of_unit_and_tuple() {
+        assert_eq!(debug_name("(MyNode,)"), "MyNode");
+        assert_eq!(debug_name("(MyNode(1, 2),)"), "MyNode");
+        assert_eq!(debug_name("MyNode"), "MyNode");
+        assert_eq!(debug_name("((),)"), "");
+    }
+}
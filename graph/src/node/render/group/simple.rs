@@ -304,35 +304,32 @@ where
         };
 
         let graphics_pipeline = unsafe {
-            factory.device().create_graphics_pipelines(
-                Some(rendy_core::hal::pso::GraphicsPipelineDesc {
-                    shaders,
-                    rasterizer: pipeline.rasterizer,
-                    vertex_buffers,
-                    attributes,
-                    input_assembler: pipeline.input_assembler_desc,
-                    blender: rendy_core::hal::pso::BlendDesc {
-                        logic_op: None,
-                        targets: pipeline.colors.clone(),
-                    },
-                    depth_stencil: pipeline.depth_stencil,
-                    multisampling: None,
-                    baked_states: rendy_core::hal::pso::BakedStates {
-                        viewport: Some(rendy_core::hal::pso::Viewport {
-                            rect,
-                            depth: 0.0..1.0,
-                        }),
-                        scissor: Some(rect),
-                        blend_color: None,
-                        depth_bounds: None,
-                    },
-                    layout: &pipeline_layout,
-                    subpass,
-                    flags: rendy_core::hal::pso::PipelineCreationFlags::empty(),
-                    parent: rendy_core::hal::pso::BasePipeline::None,
-                }),
-                None,
-            )
+            factory.create_graphics_pipelines(Some(rendy_core::hal::pso::GraphicsPipelineDesc {
+                shaders,
+                rasterizer: pipeline.rasterizer,
+                vertex_buffers,
+                attributes,
+                input_assembler: pipeline.input_assembler_desc,
+                blender: rendy_core::hal::pso::BlendDesc {
+                    logic_op: None,
+                    targets: pipeline.colors.clone(),
+                },
+                depth_stencil: pipeline.depth_stencil,
+                multisampling: None,
+                baked_states: rendy_core::hal::pso::BakedStates {
+                    viewport: Some(rendy_core::hal::pso::Viewport {
+                        rect,
+                        depth: 0.0..1.0,
+                    }),
+                    scissor: Some(rect),
+                    blend_color: None,
+                    depth_bounds: None,
+                },
+                layout: &pipeline_layout,
+                subpass,
+                flags: rendy_core::hal::pso::PipelineCreationFlags::empty(),
+                parent: rendy_core::hal::pso::BasePipeline::None,
+            }))
         }
         .remove(0)
         .map_err(|e| {
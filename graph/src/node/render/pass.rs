@@ -201,6 +201,7 @@ pub struct RenderPassNodeBuilder<B: Backend, T: ?Sized> {
         rendy_core::hal::window::Extent2D,
         Option<rendy_core::hal::command::ClearValue>,
     )>,
+    name: Option<String>,
 }
 
 impl<B, T> std::fmt::Debug for RenderPassNodeBuilder<B, T>
@@ -212,6 +213,7 @@ where
         fmt.debug_struct("RenderPassNodeBuilder")
             .field("subpasses", &self.subpasses)
             .field("surface", &self.surface)
+            .field("name", &self.name)
             .finish()
     }
 }
@@ -225,6 +227,7 @@ where
         RenderPassNodeBuilder {
             subpasses: Vec::default(),
             surface: None,
+            name: None,
         }
     }
 }
@@ -276,6 +279,24 @@ where
         self.add_surface(surface, suggested_extent, clear);
         self
     }
+
+    /// Set name of the render pass node.
+    /// See [`NodeBuilder::name`].
+    ///
+    /// [`NodeBuilder::name`]: ../trait.NodeBuilder.html#method.name
+    pub fn set_name(&mut self, name: impl Into<String>) -> &mut Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Set name of the render pass node.
+    /// See [`NodeBuilder::name`].
+    ///
+    /// [`NodeBuilder::name`]: ../trait.NodeBuilder.html#method.name
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.set_name(name);
+        self
+    }
 }
 
 impl<B, T> NodeBuilder<B, T> for RenderPassNodeBuilder<B, T>
@@ -388,6 +409,10 @@ where
         dependencies
     }
 
+    fn name(&self) -> Option<&str> {
+        self.name.as_ref().map(String::as_str)
+    }
+
     fn build<'a>(
         self: Box<Self>,
         ctx: &GraphContext<B>,
@@ -0,0 +1,154 @@
+//! Compares allocation of small blocks from `Heaps` shared behind a lock
+//! with allocation through per-thread `BlockCache`s
+//! while number of threads grows.
+//!
+//! Run with `cargo bench -p rendy-memory --bench block_cache`.
+
+use {
+    gfx_hal::{
+        adapter::{MemoryProperties, MemoryType},
+        memory::Properties,
+    },
+    rendy_memory::*,
+    std::{
+        collections::VecDeque,
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    },
+};
+
+const MB: u64 = 1024 * 1024;
+const ALLOCATIONS: usize = 100_000;
+const LIVE: usize = 64;
+const THREADS: &[usize] = &[1, 2, 4, 8];
+
+fn memory_properties() -> MemoryProperties {
+    MemoryProperties {
+        memory_types: vec![
+            MemoryType {
+                properties: Properties::DEVICE_LOCAL,
+                heap_index: 0,
+            },
+            MemoryType {
+                properties: Properties::CPU_VISIBLE | Properties::COHERENT,
+                heap_index: 1,
+            },
+        ],
+        memory_heaps: vec![4096 * MB, 1024 * MB],
+    }
+}
+
+fn create_heaps(properties: &MemoryProperties) -> Heaps<HostBackend> {
+    let types = properties.memory_types.iter().map(|mt| {
+        let config = HeapsConfig {
+            linear: None,
+            dynamic: Some(DynamicConfig {
+                block_size_granularity: 256,
+                max_chunk_size: 4 * MB,
+                min_device_allocation: 64 * 1024,
+            }),
+            buddy: None,
+        };
+        (mt.properties, mt.heap_index as u32, config)
+    });
+
+    unsafe { Heaps::new(types, properties.memory_heaps.iter().cloned()) }
+}
+
+/// Size of the `index`th allocation.
+fn size(index: usize) -> u64 {
+    256 << (index % 5)
+}
+
+/// Allocate and free small blocks locking `Heaps` for every operation.
+fn locked(device: &HostDevice, heaps: &Mutex<Heaps<HostBackend>>) {
+    let mut live = VecDeque::with_capacity(LIVE);
+    for index in 0..ALLOCATIONS {
+        if live.len() == LIVE {
+            let block = live.pop_front().unwrap();
+            heaps.lock().unwrap().free(device, block);
+        }
+        let block = heaps
+            .lock()
+            .unwrap()
+            .allocate(device, !0, Data, size(index), 256)
+            .unwrap();
+        live.push_back(block);
+    }
+
+    let mut heaps = heaps.lock().unwrap();
+    for block in live {
+        heaps.free(device, block);
+    }
+}
+
+/// Allocate and free small blocks through thread's own `BlockCache`.
+fn cached(device: &HostDevice, heaps: &Mutex<Heaps<HostBackend>>) {
+    let mut cache = BlockCache::new(&heaps.lock().unwrap(), BlockCacheConfig::default());
+    let mut live = VecDeque::with_capacity(LIVE);
+    for index in 0..ALLOCATIONS {
+        if live.len() == LIVE {
+            let block = live.pop_front().unwrap();
+            cache.free(device, || heaps.lock().unwrap(), block);
+        }
+        let block = cache
+            .allocate(device, || heaps.lock().unwrap(), !0, Data, size(index), 256)
+            .unwrap();
+        live.push_back(block);
+    }
+
+    let mut heaps = heaps.lock().unwrap();
+    for block in live {
+        heaps.free(device, block);
+    }
+    cache.dispose(device, &mut heaps);
+}
+
+fn run(threads: usize, f: fn(&HostDevice, &Mutex<Heaps<HostBackend>>)) -> Duration {
+    let properties = memory_properties();
+    let device = Arc::new(HostDevice::new(properties.clone()));
+    let heaps = Arc::new(Mutex::new(create_heaps(&properties)));
+
+    let start = Instant::now();
+    let handles = (0..threads)
+        .map(|_| {
+            let device = device.clone();
+            let heaps = heaps.clone();
+            std::thread::spawn(move || f(&device, &heaps))
+        })
+        .collect::<Vec<_>>();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    let elapsed = start.elapsed();
+
+    Arc::try_unwrap(heaps)
+        .unwrap()
+        .into_inner()
+        .unwrap()
+        .dispose(&device);
+    assert_eq!(device.allocations(), 0);
+    elapsed
+}
+
+fn main() {
+    println!(
+        "{} allocations per thread, {} live blocks per thread",
+        ALLOCATIONS, LIVE
+    );
+    println!(
+        "{:>8} {:>14} {:>14} {:>8}",
+        "threads", "locked", "cached", "speedup"
+    );
+    for &threads in THREADS {
+        let locked = run(threads, locked);
+        let cached = run(threads, cached);
+        println!(
+            "{:>8} {:>12.2?} {:>12.2?} {:>7.2}x",
+            threads,
+            locked,
+            cached,
+            locked.as_secs_f64() / cached.as_secs_f64()
+        );
+    }
+}
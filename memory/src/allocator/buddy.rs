@@ -0,0 +1,445 @@
+use std::{collections::BTreeSet, ops::Range, ptr::NonNull, thread};
+
+use crate::{
+    allocator::{Allocator, Kind},
+    block::Block,
+    device::{MemoryBackend, MemoryDevice as _},
+    mapping::*,
+    memory::*,
+    snapshot::{BuddyChunkSnapshot, BuddySnapshot},
+    util::*,
+};
+
+/// Memory block allocated from `BuddyAllocator`
+#[derive(Debug)]
+pub struct BuddyBlock<B: MemoryBackend> {
+    chunk_index: u32,
+    order: u32,
+    memory: *const Memory<B>,
+    ptr: Option<NonNull<u8>>,
+    range: Range<u64>,
+    relevant: relevant::Relevant,
+}
+
+unsafe impl<B> Send for BuddyBlock<B> where B: MemoryBackend {}
+unsafe impl<B> Sync for BuddyBlock<B> where B: MemoryBackend {}
+
+impl<B> BuddyBlock<B>
+where
+    B: MemoryBackend,
+{
+    fn shared_memory(&self) -> &Memory<B> {
+        // Memory won't be freed until last block created from it deallocated.
+        unsafe { &*self.memory }
+    }
+
+    fn size(&self) -> u64 {
+        self.range.end - self.range.start
+    }
+
+    fn dispose(self) {
+        self.relevant.dispose();
+    }
+}
+
+impl<B> Block<B> for BuddyBlock<B>
+where
+    B: MemoryBackend,
+{
+    #[inline]
+    fn properties(&self) -> gfx_hal::memory::Properties {
+        self.shared_memory().properties()
+    }
+
+    #[inline]
+    fn memory(&self) -> &B::Memory {
+        self.shared_memory().raw()
+    }
+
+    #[inline]
+    fn range(&self) -> Range<u64> {
+        self.range.clone()
+    }
+
+    #[inline]
+    fn map<'a>(
+        &'a mut self,
+        _device: &B::Device,
+        range: Range<u64>,
+    ) -> Result<MappedRange<'a, B>, gfx_hal::device::MapError> {
+        debug_assert!(
+            range.start < range.end,
+            "Memory mapping region must have valid size"
+        );
+        if !self.shared_memory().host_visible() {
+            //TODO: invalid access error
+            return Err(gfx_hal::device::MapError::MappingFailed);
+        }
+
+        if let Some(ptr) = self.ptr {
+            if let Some((ptr, range)) = mapped_sub_range(ptr, self.range.clone(), range) {
+                let mapping = unsafe { MappedRange::from_raw(self.shared_memory(), ptr, range) };
+                Ok(mapping)
+            } else {
+                Err(gfx_hal::device::MapError::OutOfBounds)
+            }
+        } else {
+            Err(gfx_hal::device::MapError::MappingFailed)
+        }
+    }
+
+    #[inline]
+    fn unmap(&mut self, _device: &B::Device) {}
+}
+
+/// Config for `BuddyAllocator`.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BuddyConfig {
+    /// Size of the smallest block.
+    /// All requests are rounded up to power of two not less than this value.
+    pub min_block_size: u64,
+
+    /// Size of memory chunk allocated from device.
+    /// This is also the size of the largest block.
+    pub chunk_size: u64,
+}
+
+/// Buddy allocator.
+/// Splits device memory chunks into power-of-two sized blocks
+/// and merges freed neighbours ("buddies") back together.
+///
+/// Suitable for medium-sized allocations of varying size
+/// that are too big for `DynamicAllocator`
+/// and too small to be worth dedicated memory object.
+/// Wastes up to half of the block size because of rounding.
+#[derive(Debug)]
+pub struct BuddyAllocator<B: MemoryBackend> {
+    /// Memory type that this allocator allocates.
+    memory_type: gfx_hal::MemoryTypeId,
+
+    /// Memory properties of the memory type.
+    memory_properties: gfx_hal::memory::Properties,
+
+    /// Size of the smallest block.
+    min_block_size: u64,
+
+    /// Size of chunks allocated from device.
+    chunk_size: u64,
+
+    /// Number of block orders. Block of order `n` has size `min_block_size << n`.
+    orders: u32,
+
+    /// Allocated chunks.
+    chunks: slab::Slab<BuddyChunk<B>>,
+}
+
+unsafe impl<B> Send for BuddyAllocator<B> where B: MemoryBackend {}
+unsafe impl<B> Sync for BuddyAllocator<B> where B: MemoryBackend {}
+
+#[derive(Debug)]
+struct BuddyChunk<B: MemoryBackend> {
+    memory: Box<Memory<B>>,
+    ptr: Option<NonNull<u8>>,
+
+    /// Offsets of free blocks for each order.
+    free: Vec<BTreeSet<u64>>,
+
+    /// Count of blocks allocated from this chunk.
+    blocks: u64,
+}
+
+impl<B> BuddyChunk<B>
+where
+    B: MemoryBackend,
+{
+    /// Find smallest order not less than `order` that has free blocks.
+    fn free_order(&self, order: u32) -> Option<u32> {
+        (order..self.free.len() as u32).find(|&o| !self.free[o as usize].is_empty())
+    }
+}
+
+impl<B> BuddyAllocator<B>
+where
+    B: MemoryBackend,
+{
+    /// Create new `BuddyAllocator`
+    /// for `memory_type` with `memory_properties` specified,
+    /// with `BuddyConfig` provided.
+    pub fn new(
+        memory_type: gfx_hal::MemoryTypeId,
+        memory_properties: gfx_hal::memory::Properties,
+        config: BuddyConfig,
+    ) -> Self {
+        log::trace!(
+            "Create new 'buddy' allocator: type: '{:?}', properties: '{:#?}' config: '{:#?}'",
+            memory_type,
+            memory_properties,
+            config
+        );
+
+        assert!(
+            config.min_block_size.is_power_of_two(),
+            "Min block size must be power of two"
+        );
+
+        assert!(
+            config.chunk_size.is_power_of_two(),
+            "Chunk size must be power of two"
+        );
+
+        assert!(
+            config.min_block_size <= config.chunk_size,
+            "Min block size must be less than or equal to chunk size"
+        );
+
+        if memory_properties.contains(gfx_hal::memory::Properties::CPU_VISIBLE) {
+            debug_assert!(
+                fits_usize(config.chunk_size),
+                "Chunk size must fit usize for mapping"
+            );
+        }
+
+        BuddyAllocator {
+            memory_type,
+            memory_properties,
+            min_block_size: config.min_block_size,
+            chunk_size: config.chunk_size,
+            orders: (config.chunk_size / config.min_block_size).trailing_zeros() + 1,
+            chunks: slab::Slab::new(),
+        }
+    }
+
+    /// Maximum allocation size.
+    /// Alignment of the allocation can't exceed it either,
+    /// as blocks are aligned to their size.
+    pub fn max_allocation(&self) -> u64 {
+        self.chunk_size
+    }
+
+    /// Size of the block of given order.
+    fn block_size(&self, order: u32) -> u64 {
+        self.min_block_size << order
+    }
+
+    /// Allocate memory chunk from device.
+    fn alloc_chunk(
+        &mut self,
+        device: &B::Device,
+    ) -> Result<usize, gfx_hal::device::AllocationError> {
+        log::trace!("Allocate chunk of size: {} from device", self.chunk_size);
+
+        let (memory, ptr) = unsafe {
+            // Valid memory type specified.
+            let raw = device.allocate_memory(self.memory_type, self.chunk_size)?;
+
+            let ptr = if self
+                .memory_properties
+                .contains(gfx_hal::memory::Properties::CPU_VISIBLE)
+            {
+                log::trace!("Map new memory object");
+                match device.map_memory(&raw, 0..self.chunk_size) {
+                    Ok(ptr) => Some(NonNull::new_unchecked(ptr)),
+                    Err(gfx_hal::device::MapError::OutOfMemory(error)) => {
+                        device.free_memory(raw);
+                        return Err(error.into());
+                    }
+                    Err(_) => panic!("Unexpected mapping failure"),
+                }
+            } else {
+                None
+            };
+            let memory = Memory::from_raw(raw, self.chunk_size, self.memory_properties);
+            (memory, ptr)
+        };
+
+        let mut free = vec![BTreeSet::new(); self.orders as usize];
+        free[self.orders as usize - 1].insert(0);
+
+        Ok(self.chunks.insert(BuddyChunk {
+            memory: Box::new(memory),
+            ptr,
+            free,
+            blocks: 0,
+        }))
+    }
+
+    /// Free memory chunk.
+    fn free_chunk(&mut self, device: &B::Device, chunk_index: u32) -> u64 {
+        let chunk = self.chunks.remove(chunk_index as usize);
+        log::trace!("Free chunk: {:#?}", chunk);
+        debug_assert_eq!(chunk.blocks, 0);
+        let size = chunk.memory.size();
+        unsafe {
+            if chunk.ptr.is_some() {
+                log::trace!("Unmap memory: {:#?}", chunk.memory);
+                device.unmap_memory(chunk.memory.raw());
+            }
+            device.free_memory(chunk.memory.into_raw());
+        }
+        size
+    }
+
+    /// Take free block of `order` from the chunk
+    /// splitting bigger block if necessary.
+    fn alloc_from_chunk(&mut self, chunk_index: u32, order: u32) -> Option<BuddyBlock<B>> {
+        let min_block_size = self.min_block_size;
+        let ref mut chunk = self.chunks[chunk_index as usize];
+        let mut free_order = chunk.free_order(order)?;
+
+        let offset = *chunk.free[free_order as usize].iter().next()?;
+        chunk.free[free_order as usize].remove(&offset);
+
+        // Split the block putting upper halves into free lists.
+        while free_order > order {
+            free_order -= 1;
+            chunk.free[free_order as usize].insert(offset + (min_block_size << free_order));
+        }
+
+        chunk.blocks += 1;
+
+        let block_range = offset..offset + (min_block_size << order);
+
+        log::trace!(
+            "Allocate block of order {} at {:?} from chunk {}",
+            order,
+            block_range,
+            chunk_index
+        );
+
+        Some(BuddyBlock {
+            chunk_index,
+            order,
+            memory: &*chunk.memory,
+            ptr: chunk.ptr.map(|ptr| {
+                mapped_fitting_range(ptr, 0..chunk.memory.size(), block_range.clone())
+                    .expect("Block must be sub-range of chunk")
+            }),
+            range: block_range,
+            relevant: relevant::Relevant,
+        })
+    }
+
+    /// Take snapshot of the allocator state.
+    pub fn snapshot(&self) -> BuddySnapshot {
+        BuddySnapshot {
+            min_block_size: self.min_block_size,
+            chunk_size: self.chunk_size,
+            chunks: self
+                .chunks
+                .iter()
+                .map(|(index, chunk)| BuddyChunkSnapshot {
+                    index: index as u32,
+                    blocks: chunk.blocks,
+                    free: chunk
+                        .free
+                        .iter()
+                        .map(|offsets| offsets.iter().cloned().collect())
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Perform full cleanup of the memory allocated.
+    pub fn dispose(self) {
+        if !thread::panicking() {
+            assert_eq!(self.chunks.len(), 0, "Not all blocks were freed");
+        } else if self.chunks.len() != 0 {
+            log::error!(
+                "Memory leak: {} buddy chunks are still used",
+                self.chunks.len()
+            );
+        }
+    }
+}
+
+impl<B> Allocator<B> for BuddyAllocator<B>
+where
+    B: MemoryBackend,
+{
+    type Block = BuddyBlock<B>;
+
+    fn kind() -> Kind {
+        Kind::Buddy
+    }
+
+    fn alloc(
+        &mut self,
+        device: &B::Device,
+        size: u64,
+        align: u64,
+    ) -> Result<(BuddyBlock<B>, u64), gfx_hal::device::AllocationError> {
+        debug_assert!(align.is_power_of_two());
+        assert!(size <= self.chunk_size, "Block size exceeds chunk size");
+        assert!(
+            align <= self.chunk_size,
+            "Block alignment exceeds chunk size"
+        );
+
+        // Blocks are aligned to their size.
+        let block_size = size.next_power_of_two().max(align).max(self.min_block_size);
+        let order = (block_size / self.min_block_size).trailing_zeros();
+
+        log::trace!(
+            "Allocate buddy block: size: {}, align: {}, block size: {}, type: {}",
+            size,
+            align,
+            block_size,
+            self.memory_type.0
+        );
+
+        debug_assert_eq!(self.block_size(order), block_size);
+        debug_assert!(order < self.orders);
+
+        let candidate = self
+            .chunks
+            .iter()
+            .filter_map(|(index, chunk)| Some((index, chunk.free_order(order)?)))
+            .min_by_key(|&(_, free_order)| free_order)
+            .map(|(index, _)| index);
+
+        let (chunk_index, allocated) = match candidate {
+            Some(index) => (index, 0),
+            None => (self.alloc_chunk(device)?, self.chunk_size),
+        };
+
+        debug_assert!(fits_u32(chunk_index));
+        let block = self
+            .alloc_from_chunk(chunk_index as u32, order)
+            .expect("Chunk must have free block of required order");
+
+        Ok((block, allocated))
+    }
+
+    fn free(&mut self, device: &B::Device, block: BuddyBlock<B>) -> u64 {
+        log::trace!("Free block: {:#?}", block);
+
+        let chunk_index = block.chunk_index;
+        let mut order = block.order;
+        let mut offset = block.range.start;
+        block.dispose();
+
+        let min_block_size = self.min_block_size;
+        let orders = self.orders;
+        let ref mut chunk = self.chunks[chunk_index as usize];
+
+        // Merge with free buddies.
+        while order + 1 < orders {
+            let buddy = offset ^ (min_block_size << order);
+            if !chunk.free[order as usize].remove(&buddy) {
+                break;
+            }
+            offset = offset.min(buddy);
+            order += 1;
+        }
+        chunk.free[order as usize].insert(offset);
+        chunk.blocks -= 1;
+
+        if chunk.blocks == 0 {
+            self.free_chunk(device, chunk_index)
+        } else {
+            0
+        }
+    }
+}
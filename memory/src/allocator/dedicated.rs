@@ -1,28 +1,27 @@
 use std::{ops::Range, ptr::NonNull};
 
-use {
-    crate::{
-        allocator::{Allocator, Kind},
-        block::Block,
-        mapping::{mapped_fitting_range, MappedRange},
-        memory::*,
-    },
-    gfx_hal::{device::Device as _, Backend},
+use crate::{
+    allocator::{Allocator, Kind},
+    block::Block,
+    device::{MemoryBackend, MemoryDevice as _},
+    mapping::{mapped_fitting_range, MappedRange},
+    memory::*,
+    snapshot::DedicatedSnapshot,
 };
 
 /// Memory block allocated from `DedicatedAllocator`
 #[derive(Debug)]
-pub struct DedicatedBlock<B: Backend> {
+pub struct DedicatedBlock<B: MemoryBackend> {
     memory: Memory<B>,
     mapping: Option<(NonNull<u8>, Range<u64>)>,
 }
 
-unsafe impl<B> Send for DedicatedBlock<B> where B: Backend {}
-unsafe impl<B> Sync for DedicatedBlock<B> where B: Backend {}
+unsafe impl<B> Send for DedicatedBlock<B> where B: MemoryBackend {}
+unsafe impl<B> Sync for DedicatedBlock<B> where B: MemoryBackend {}
 
 impl<B> DedicatedBlock<B>
 where
-    B: Backend,
+    B: MemoryBackend,
 {
     /// Get inner memory.
     /// Panics if mapped.
@@ -42,7 +41,7 @@ where
 
 impl<B> Block<B> for DedicatedBlock<B>
 where
-    B: Backend,
+    B: MemoryBackend,
 {
     #[inline]
     fn properties(&self) -> gfx_hal::memory::Properties {
@@ -114,6 +113,7 @@ where
 pub struct DedicatedAllocator {
     memory_type: gfx_hal::MemoryTypeId,
     memory_properties: gfx_hal::memory::Properties,
+    blocks: u64,
     used: u64,
 }
 
@@ -132,14 +132,23 @@ impl DedicatedAllocator {
         DedicatedAllocator {
             memory_type,
             memory_properties,
+            blocks: 0,
             used: 0,
         }
     }
+
+    /// Take snapshot of the allocator state.
+    pub fn snapshot(&self) -> DedicatedSnapshot {
+        DedicatedSnapshot {
+            blocks: self.blocks,
+            used: self.used,
+        }
+    }
 }
 
 impl<B> Allocator<B> for DedicatedAllocator
 where
-    B: Backend,
+    B: MemoryBackend,
 {
     type Block = DedicatedBlock<B>;
 
@@ -162,6 +171,7 @@ where
             )
         };
 
+        self.blocks += 1;
         self.used += size;
 
         Ok((DedicatedBlock::from_memory(memory), size))
@@ -171,6 +181,7 @@ where
     fn free(&mut self, device: &B::Device, mut block: DedicatedBlock<B>) -> u64 {
         block.unmap(device);
         let size = block.memory.size();
+        self.blocks -= 1;
         self.used -= size;
         unsafe {
             device.free_memory(block.memory.into_raw());
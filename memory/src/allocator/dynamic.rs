@@ -9,17 +9,18 @@ use {
     crate::{
         allocator::{Allocator, Kind},
         block::Block,
+        device::{MemoryBackend, MemoryDevice as _},
         mapping::*,
         memory::*,
+        snapshot::{ChunkSnapshot, DynamicSnapshot, SizeEntrySnapshot},
         util::*,
     },
-    gfx_hal::{device::Device as _, Backend},
     hibitset::{BitSet, BitSetLike as _},
 };
 
 /// Memory block allocated from `DynamicAllocator`
 #[derive(Debug)]
-pub struct DynamicBlock<B: Backend> {
+pub struct DynamicBlock<B: MemoryBackend> {
     block_index: u32,
     chunk_index: u32,
     count: u32,
@@ -29,12 +30,12 @@ pub struct DynamicBlock<B: Backend> {
     relevant: relevant::Relevant,
 }
 
-unsafe impl<B> Send for DynamicBlock<B> where B: Backend {}
-unsafe impl<B> Sync for DynamicBlock<B> where B: Backend {}
+unsafe impl<B> Send for DynamicBlock<B> where B: MemoryBackend {}
+unsafe impl<B> Sync for DynamicBlock<B> where B: MemoryBackend {}
 
 impl<B> DynamicBlock<B>
 where
-    B: Backend,
+    B: MemoryBackend,
 {
     fn shared_memory(&self) -> &Memory<B> {
         // Memory won't be freed until last block created from it deallocated.
@@ -45,6 +46,12 @@ where
         self.range.end - self.range.start
     }
 
+    /// Get size of the blocks in the chunk and index of the chunk
+    /// this block was allocated from.
+    pub(crate) fn chunk_key(&self) -> (u64, u32) {
+        (self.size() / self.count as u64, self.chunk_index)
+    }
+
     fn dispose(self) {
         self.relevant.dispose();
     }
@@ -52,7 +59,7 @@ where
 
 impl<B> Block<B> for DynamicBlock<B>
 where
-    B: Backend,
+    B: MemoryBackend,
 {
     #[inline]
     fn properties(&self) -> gfx_hal::memory::Properties {
@@ -119,7 +126,7 @@ pub struct DynamicConfig {
 /// Suitable for any type of small allocations.
 /// Every freed block can be reused.
 #[derive(Debug)]
-pub struct DynamicAllocator<B: Backend> {
+pub struct DynamicAllocator<B: MemoryBackend> {
     /// Memory type that this allocator allocates.
     memory_type: gfx_hal::MemoryTypeId,
 
@@ -142,30 +149,35 @@ pub struct DynamicAllocator<B: Backend> {
     chunks: BTreeSet<u64>,
 }
 
-unsafe impl<B> Send for DynamicAllocator<B> where B: Backend {}
-unsafe impl<B> Sync for DynamicAllocator<B> where B: Backend {}
+unsafe impl<B> Send for DynamicAllocator<B> where B: MemoryBackend {}
+unsafe impl<B> Sync for DynamicAllocator<B> where B: MemoryBackend {}
 
 #[derive(Debug)]
-struct SizeEntry<B: Backend> {
+struct SizeEntry<B: MemoryBackend> {
     /// Total count of allocated blocks with size corresponding to this entry.
     total_blocks: u64,
 
     /// Bits per ready (non-exhausted) chunks with free blocks.
     ready_chunks: BitSet,
 
+    /// Bits per chunks that are being evacuated.
+    /// Those chunks are never ready.
+    evacuating_chunks: BitSet,
+
     /// List of chunks.
     chunks: slab::Slab<Chunk<B>>,
 }
 
 impl<B> Default for SizeEntry<B>
 where
-    B: Backend,
+    B: MemoryBackend,
 {
     fn default() -> Self {
         SizeEntry {
             chunks: Default::default(),
             total_blocks: 0,
             ready_chunks: Default::default(),
+            evacuating_chunks: Default::default(),
         }
     }
 }
@@ -175,7 +187,7 @@ const MIN_BLOCKS_PER_CHUNK: u32 = 8;
 
 impl<B> DynamicAllocator<B>
 where
-    B: Backend,
+    B: MemoryBackend,
 {
     /// Create new `DynamicAllocator`
     /// for `memory_type` with `memory_properties` specified,
@@ -309,6 +321,7 @@ where
         {
             // Allocate block for the chunk.
             let (block, allocated) = self.alloc_from_entry(device, chunk_size, 1, block_size)?;
+            self.pin(&block);
             Ok((Chunk::from_block(block_size, block), allocated))
         } else {
             let total_blocks = self.sizes[&block_size].total_blocks;
@@ -316,6 +329,7 @@ where
                 (max_chunk_size.min(min_chunk_size.max(total_blocks * block_size)) / 2 + 1)
                     .next_power_of_two();
             let (block, allocated) = self.alloc_block(device, chunk_size, block_size)?;
+            self.pin(&block);
             Ok((Chunk::from_block(block_size, block), allocated))
         }
     }
@@ -484,14 +498,106 @@ where
         chunk.release_blocks(block_index, count);
         if chunk.is_unused(block_size) {
             size_entry.ready_chunks.remove(chunk_index);
+            size_entry.evacuating_chunks.remove(chunk_index);
             let chunk = size_entry.chunks.remove(chunk_index as usize);
             self.free_chunk(device, chunk, block_size)
         } else {
-            size_entry.ready_chunks.add(chunk_index);
+            if !size_entry.evacuating_chunks.contains(chunk_index) {
+                size_entry.ready_chunks.add(chunk_index);
+            }
             0
         }
     }
 
+    /// Select chunks that are worth evacuating.
+    /// Chunk is selected if less than half of its blocks are used
+    /// and the used blocks fit into free blocks of chunks of the same size that are left.
+    /// Chunks with pinned blocks are never selected.
+    ///
+    /// Selected chunks stop serving new allocations until `stop_evacuation` is called.
+    /// Chunk is freed as usual when last block allocated from it is freed.
+    ///
+    /// Returns block sizes and indices of selected chunks.
+    pub(crate) fn start_evacuation(&mut self) -> Vec<(u64, u32)> {
+        let mut selected = Vec::new();
+
+        for (&block_size, size_entry) in self.sizes.iter_mut() {
+            let candidates = size_entry
+                .chunks
+                .iter()
+                .filter(|(index, _)| !size_entry.evacuating_chunks.contains(*index as u32))
+                .map(|(index, chunk)| {
+                    let total = chunk.total_blocks(block_size);
+                    let free = chunk.free_blocks();
+                    (index as u32, total - free, free, chunk.is_pinned())
+                })
+                .collect::<Vec<_>>();
+
+            for chunk_index in select_for_evacuation(candidates) {
+                size_entry.ready_chunks.remove(chunk_index);
+                size_entry.evacuating_chunks.add(chunk_index);
+                selected.push((block_size, chunk_index));
+            }
+        }
+
+        log::trace!("Chunks selected for evacuation: {:?}", selected);
+        selected
+    }
+
+    /// Mark block as one that can't be relocated.
+    /// Chunks with such blocks are never selected for evacuation.
+    /// Mark is removed when block is freed.
+    pub(crate) fn pin(&mut self, block: &DynamicBlock<B>) {
+        let (block_size, chunk_index) = block.chunk_key();
+        self.sizes
+            .get_mut(&block_size)
+            .expect("Unable to get size entry from which block was allocated")
+            .chunks[chunk_index as usize]
+            .pin_blocks(block.block_index, block.count);
+    }
+
+    /// Return chunk selected by `start_evacuation` back to service.
+    /// No-op if chunk was freed already.
+    pub(crate) fn stop_evacuation(&mut self, block_size: u64, chunk_index: u32) {
+        if let Some(size_entry) = self.sizes.get_mut(&block_size) {
+            if size_entry.evacuating_chunks.remove(chunk_index)
+                && !size_entry.chunks[chunk_index as usize].is_exhausted()
+            {
+                size_entry.ready_chunks.add(chunk_index);
+            }
+        }
+    }
+
+    /// Take snapshot of the allocator state.
+    pub fn snapshot(&self) -> DynamicSnapshot {
+        let mut sizes = self
+            .sizes
+            .iter()
+            .map(|(&block_size, size_entry)| SizeEntrySnapshot {
+                block_size,
+                total_blocks: size_entry.total_blocks,
+                chunks: size_entry
+                    .chunks
+                    .iter()
+                    .map(|(index, chunk)| ChunkSnapshot {
+                        index: index as u32,
+                        size: chunk.size(),
+                        blocks: chunk.total_blocks(block_size),
+                        free_blocks: chunk.blocks,
+                        dedicated: match chunk.flavor {
+                            ChunkFlavor::Dedicated(..) => true,
+                            ChunkFlavor::Dynamic(..) => false,
+                        },
+                        evacuating: size_entry.evacuating_chunks.contains(index as u32),
+                    })
+                    .collect(),
+            })
+            .collect::<Vec<_>>();
+
+        sizes.sort_by_key(|size_entry| size_entry.block_size);
+        DynamicSnapshot { sizes }
+    }
+
     /// Perform full cleanup of the memory allocated.
     pub fn dispose(self) {
         if !thread::panicking() {
@@ -510,7 +616,7 @@ where
 
 impl<B> Allocator<B> for DynamicAllocator<B>
 where
-    B: Backend,
+    B: MemoryBackend,
 {
     type Block = DynamicBlock<B>;
 
@@ -546,7 +652,7 @@ where
 
 /// Block allocated for chunk.
 #[derive(Debug)]
-enum ChunkFlavor<B: Backend> {
+enum ChunkFlavor<B: MemoryBackend> {
     /// Allocated from device.
     Dedicated(Box<Memory<B>>, Option<NonNull<u8>>),
 
@@ -555,14 +661,16 @@ enum ChunkFlavor<B: Backend> {
 }
 
 #[derive(Debug)]
-struct Chunk<B: Backend> {
+struct Chunk<B: MemoryBackend> {
     flavor: ChunkFlavor<B>,
     blocks: u64,
+    /// Used blocks that can't be relocated.
+    pinned: u64,
 }
 
 impl<B> Chunk<B>
 where
-    B: Backend,
+    B: MemoryBackend,
 {
     fn from_memory(block_size: u64, memory: Memory<B>, mapping: Option<NonNull<u8>>) -> Self {
         let blocks = memory.size() / block_size;
@@ -573,6 +681,7 @@ where
         Chunk {
             flavor: ChunkFlavor::Dedicated(Box::new(memory), mapping),
             blocks: (high_bit - 1) | high_bit,
+            pinned: 0,
         }
     }
 
@@ -584,6 +693,7 @@ where
         Chunk {
             flavor: ChunkFlavor::Dynamic(chunk_block),
             blocks: (high_bit - 1) | high_bit,
+            pinned: 0,
         }
     }
 
@@ -626,6 +736,27 @@ where
         self.blocks == mask
     }
 
+    /// Get number of blocks in the chunk.
+    fn total_blocks(&self, block_size: u64) -> u32 {
+        (self.size() / block_size).min(MAX_BLOCKS_PER_CHUNK as u64) as u32
+    }
+
+    /// Get number of free blocks in the chunk.
+    fn free_blocks(&self) -> u32 {
+        self.blocks.count_ones()
+    }
+
+    /// Check if chunk has blocks that can't be relocated.
+    fn is_pinned(&self) -> bool {
+        self.pinned != 0
+    }
+
+    fn pin_blocks(&mut self, index: u32, count: u32) {
+        let mask = ((1 << count) - 1) << index;
+        debug_assert_eq!(self.blocks & mask, 0);
+        self.pinned |= mask;
+    }
+
     /// Check if there are free blocks.
     fn is_exhausted(&self) -> bool {
         self.blocks == 0
@@ -657,6 +788,7 @@ where
         let mask = ((1 << count) - 1) << index;
         debug_assert_eq!(self.blocks & mask, 0);
         self.blocks |= mask;
+        self.pinned &= !mask;
     }
 
     fn mapping_ptr(&self) -> Option<NonNull<u8>> {
@@ -667,8 +799,69 @@ where
     }
 }
 
+/// Select chunks for evacuation from `(index, used, free, pinned)` candidates
+/// of the same block size.
+/// Chunk is selected if it is not pinned, less than half of its blocks are used
+/// and the used blocks fit into free blocks of the chunks that are left.
+fn select_for_evacuation(mut candidates: Vec<(u32, u32, u32, bool)>) -> Vec<u32> {
+    let mut free_left = candidates.iter().map(|&(_, _, free, _)| free).sum::<u32>();
+
+    // Evacuate least used chunks first.
+    candidates.sort_by_key(|&(_, used, _, _)| used);
+
+    let mut selected = Vec::new();
+    for (chunk_index, used, free, pinned) in candidates {
+        // `free_left` includes free blocks of this chunk.
+        if pinned || used == 0 || used * 2 >= used + free || used + free > free_left {
+            continue;
+        }
+        free_left -= free + used;
+        selected.push(chunk_index);
+    }
+    selected
+}
+
 fn max_chunks_per_size() -> usize {
     let value = (std::mem::size_of::<usize>() * 8).pow(4);
     debug_assert!(fits_u32(value));
     value
 }
+
+#[cfg(test)]
+mod tests {
+    use super::select_for_evacuation;
+
+    #[test]
+    fn evacuation_no_underflow() {
+        // Used blocks of first two chunks fit into the last one,
+        // which then has less free blocks left than its own free blocks.
+        let selected =
+            select_for_evacuation(vec![(0, 1, 9, false), (1, 1, 9, false), (2, 2, 8, false)]);
+        assert_eq!(selected, vec![0, 1]);
+    }
+
+    #[test]
+    fn evacuation_fits_free_blocks() {
+        let selected = select_for_evacuation(vec![
+            (0, 3, 13, false),
+            (1, 2, 14, false),
+            (2, 12, 4, false),
+            (3, 10, 6, false),
+        ]);
+        assert_eq!(selected, vec![1, 0]);
+    }
+
+    #[test]
+    fn evacuation_skips_pinned() {
+        let selected =
+            select_for_evacuation(vec![(0, 1, 15, true), (1, 2, 14, false), (2, 10, 6, false)]);
+        assert_eq!(selected, vec![1]);
+    }
+
+    #[test]
+    fn evacuation_skips_unused_and_dense() {
+        let selected =
+            select_for_evacuation(vec![(0, 0, 16, false), (1, 8, 8, false), (2, 12, 4, false)]);
+        assert!(selected.is_empty());
+    }
+}
@@ -4,16 +4,17 @@ use {
     crate::{
         allocator::{Allocator, Kind},
         block::Block,
+        device::{MemoryBackend, MemoryDevice as _},
         mapping::*,
         memory::*,
+        snapshot::{LineSnapshot, LinearSnapshot},
         util::*,
     },
-    gfx_hal::{device::Device as _, Backend},
     std::sync::Arc,
 };
 
 /// Memory block allocated from `LinearAllocator`
-pub struct LinearBlock<B: Backend> {
+pub struct LinearBlock<B: MemoryBackend> {
     memory: Arc<Memory<B>>,
     linear_index: u64,
     ptr: NonNull<u8>,
@@ -23,7 +24,7 @@ pub struct LinearBlock<B: Backend> {
 
 impl<B> std::fmt::Debug for LinearBlock<B>
 where
-    B: Backend,
+    B: MemoryBackend,
 {
     fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         fmt.debug_struct("LinearBlock")
@@ -35,12 +36,12 @@ where
     }
 }
 
-unsafe impl<B> Send for LinearBlock<B> where B: Backend {}
-unsafe impl<B> Sync for LinearBlock<B> where B: Backend {}
+unsafe impl<B> Send for LinearBlock<B> where B: MemoryBackend {}
+unsafe impl<B> Sync for LinearBlock<B> where B: MemoryBackend {}
 
 impl<B> LinearBlock<B>
 where
-    B: Backend,
+    B: MemoryBackend,
 {
     fn size(&self) -> u64 {
         self.range.end - self.range.start
@@ -53,7 +54,7 @@ where
 
 impl<B> Block<B> for LinearBlock<B>
 where
-    B: Backend,
+    B: MemoryBackend,
 {
     #[inline]
     fn properties(&self) -> gfx_hal::memory::Properties {
@@ -118,7 +119,7 @@ pub struct LinearConfig {
 /// Allocation strategy requires minimal overhead and implementation is fast.
 /// But holding single block will completely stop memory recycling.
 #[derive(Debug)]
-pub struct LinearAllocator<B: Backend> {
+pub struct LinearAllocator<B: MemoryBackend> {
     memory_type: gfx_hal::MemoryTypeId,
     memory_properties: gfx_hal::memory::Properties,
     linear_size: u64,
@@ -127,19 +128,19 @@ pub struct LinearAllocator<B: Backend> {
 }
 
 #[derive(Debug)]
-struct Line<B: Backend> {
+struct Line<B: MemoryBackend> {
     used: u64,
     free: u64,
     memory: Arc<Memory<B>>,
     ptr: NonNull<u8>,
 }
 
-unsafe impl<B> Send for Line<B> where B: Backend {}
-unsafe impl<B> Sync for Line<B> where B: Backend {}
+unsafe impl<B> Send for Line<B> where B: MemoryBackend {}
+unsafe impl<B> Sync for Line<B> where B: MemoryBackend {}
 
 impl<B> LinearAllocator<B>
 where
-    B: Backend,
+    B: MemoryBackend,
 {
     /// Get properties required by the `LinearAllocator`.
     pub fn properties_required() -> gfx_hal::memory::Properties {
@@ -190,6 +191,23 @@ where
         }
     }
 
+    /// Take snapshot of the allocator state.
+    pub fn snapshot(&self) -> LinearSnapshot {
+        LinearSnapshot {
+            linear_size: self.linear_size,
+            lines: self
+                .lines
+                .iter()
+                .enumerate()
+                .map(|(index, line)| LineSnapshot {
+                    index: self.offset + index as u64,
+                    used: line.used,
+                    free: line.free,
+                })
+                .collect(),
+        }
+    }
+
     fn cleanup(&mut self, device: &B::Device, off: usize) -> u64 {
         let mut freed = 0;
         while self.lines.len() > off {
@@ -219,7 +237,7 @@ where
 
 impl<B> Allocator<B> for LinearAllocator<B>
 where
-    B: Backend,
+    B: MemoryBackend,
 {
     type Block = LinearBlock<B>;
 
@@ -1,12 +1,14 @@
 //! This module provides `Allocator` trait and few allocators that implements the trait.
 
+mod buddy;
 mod dedicated;
 mod dynamic;
 mod linear;
 
-use crate::block::Block;
+use crate::{block::Block, device::MemoryBackend};
 
 pub use self::{
+    buddy::{BuddyAllocator, BuddyBlock, BuddyConfig},
     dedicated::{DedicatedAllocator, DedicatedBlock},
     dynamic::{DynamicAllocator, DynamicBlock, DynamicConfig},
     linear::{LinearAllocator, LinearBlock, LinearConfig},
@@ -25,10 +27,16 @@ pub enum Kind {
     /// Fast and low overhead.
     /// Suitable for one-time-use allocations.
     Linear,
+
+    /// Splits power-of-two sized blocks.
+    /// Suitable for medium-sized allocations of varying size.
+    /// Used only if configured, for allocations
+    /// that other sub-allocators can't handle.
+    Buddy,
 }
 
 /// Allocator trait implemented for various allocators.
-pub trait Allocator<B: gfx_hal::Backend> {
+pub trait Allocator<B: MemoryBackend> {
     /// Block type returned by allocator.
     type Block: Block<B>;
 
@@ -1,12 +1,12 @@
 use std::ops::Range;
 
-use crate::mapping::MappedRange;
+use crate::{device::MemoryBackend, mapping::MappedRange};
 
 /// Block that owns a `Range` of the `Memory`.
 /// Implementor must ensure that there can't be any other blocks
 /// with overlapping range (either through type system or safety notes for unsafe functions).
 /// Provides access to safe memory range mapping.
-pub trait Block<B: gfx_hal::Backend> {
+pub trait Block<B: MemoryBackend> {
     /// Get memory properties of the block.
     fn properties(&self) -> gfx_hal::memory::Properties;
 
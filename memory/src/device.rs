@@ -0,0 +1,131 @@
+//! Abstraction over device operations used by allocators.
+//!
+//! Allocators and `Heaps` are generic over `MemoryBackend`
+//! which is implemented for every `gfx_hal::Backend`,
+//! so they can be driven by host memory simulator as well as real device.
+
+use std::{fmt::Debug, ops::Range};
+
+/// Memory related types of the backend.
+/// Implemented for all `gfx_hal::Backend` types.
+pub trait MemoryBackend: Debug + Sized + Send + Sync + 'static {
+    /// Raw memory object type.
+    type Memory: Debug + Send + Sync;
+
+    /// Device type that allocates memory objects.
+    type Device: MemoryDevice<Self>;
+}
+
+/// Device memory operations required by allocators.
+pub trait MemoryDevice<B: MemoryBackend> {
+    /// Allocate memory object of `memory_type`.
+    ///
+    /// # Safety
+    ///
+    /// `memory_type` must be valid memory type index of the device.
+    unsafe fn allocate_memory(
+        &self,
+        memory_type: gfx_hal::MemoryTypeId,
+        size: u64,
+    ) -> Result<B::Memory, gfx_hal::device::AllocationError>;
+
+    /// Free memory object.
+    ///
+    /// # Safety
+    ///
+    /// `memory` must be allocated from this device and must not be used by device.
+    unsafe fn free_memory(&self, memory: B::Memory);
+
+    /// Map range of the memory object to the host.
+    ///
+    /// # Safety
+    ///
+    /// `memory` must be allocated from this device and must not be already mapped.
+    unsafe fn map_memory(
+        &self,
+        memory: &B::Memory,
+        range: Range<u64>,
+    ) -> Result<*mut u8, gfx_hal::device::MapError>;
+
+    /// Unmap memory object.
+    ///
+    /// # Safety
+    ///
+    /// `memory` must be mapped.
+    unsafe fn unmap_memory(&self, memory: &B::Memory);
+
+    /// Make host writes to the mapped range available to the device.
+    ///
+    /// # Safety
+    ///
+    /// `range` must be inside mapped region of the `memory`.
+    unsafe fn flush_memory_range(
+        &self,
+        memory: &B::Memory,
+        range: Range<u64>,
+    ) -> Result<(), gfx_hal::device::OutOfMemory>;
+
+    /// Make device writes to the mapped range visible to the host.
+    ///
+    /// # Safety
+    ///
+    /// `range` must be inside mapped region of the `memory`.
+    unsafe fn invalidate_memory_range(
+        &self,
+        memory: &B::Memory,
+        range: Range<u64>,
+    ) -> Result<(), gfx_hal::device::OutOfMemory>;
+}
+
+impl<B> MemoryBackend for B
+where
+    B: gfx_hal::Backend,
+{
+    type Memory = <B as gfx_hal::Backend>::Memory;
+    type Device = <B as gfx_hal::Backend>::Device;
+}
+
+impl<B> MemoryDevice<B> for <B as gfx_hal::Backend>::Device
+where
+    B: gfx_hal::Backend,
+{
+    unsafe fn allocate_memory(
+        &self,
+        memory_type: gfx_hal::MemoryTypeId,
+        size: u64,
+    ) -> Result<B::Memory, gfx_hal::device::AllocationError> {
+        gfx_hal::device::Device::allocate_memory(self, memory_type, size)
+    }
+
+    unsafe fn free_memory(&self, memory: B::Memory) {
+        gfx_hal::device::Device::free_memory(self, memory)
+    }
+
+    unsafe fn map_memory(
+        &self,
+        memory: &B::Memory,
+        range: Range<u64>,
+    ) -> Result<*mut u8, gfx_hal::device::MapError> {
+        gfx_hal::device::Device::map_memory(self, memory, range)
+    }
+
+    unsafe fn unmap_memory(&self, memory: &B::Memory) {
+        gfx_hal::device::Device::unmap_memory(self, memory)
+    }
+
+    unsafe fn flush_memory_range(
+        &self,
+        memory: &B::Memory,
+        range: Range<u64>,
+    ) -> Result<(), gfx_hal::device::OutOfMemory> {
+        gfx_hal::device::Device::flush_mapped_memory_ranges(self, Some((memory, range)))
+    }
+
+    unsafe fn invalidate_memory_range(
+        &self,
+        memory: &B::Memory,
+        range: Range<u64>,
+    ) -> Result<(), gfx_hal::device::OutOfMemory> {
+        gfx_hal::device::Device::invalidate_mapped_memory_ranges(self, Some((memory, range)))
+    }
+}
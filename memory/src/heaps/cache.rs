@@ -0,0 +1,221 @@
+use {
+    super::{Heaps, HeapsError, MemoryBlock},
+    crate::{
+        allocator::Kind,
+        block::Block,
+        device::MemoryBackend,
+        usage::{DedicatedHint, MemoryUsage},
+    },
+    gfx_hal::memory::Properties,
+    std::{collections::HashMap, ops::DerefMut},
+};
+
+/// Config for `BlockCache`.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BlockCacheConfig {
+    /// Maximum size of cached blocks.
+    /// Larger requests are always served by `Heaps`.
+    pub max_block_size: u64,
+
+    /// Number of blocks reserved from `Heaps` at once.
+    pub batch: usize,
+}
+
+impl Default for BlockCacheConfig {
+    fn default() -> Self {
+        BlockCacheConfig {
+            max_block_size: 64 * 1024,
+            batch: 16,
+        }
+    }
+}
+
+/// Cache of small blocks reserved from `Heaps` in batches.
+///
+/// Intended to be kept per thread in front of `Heaps` shared behind a lock.
+/// Requests that are served by `DynamicAllocator` and fit `max_block_size`
+/// are rounded up to power of two and served from blocks reserved in advance,
+/// so the lock is taken once per `batch` allocations.
+/// Other requests are forwarded to `Heaps`.
+///
+/// Blocks handed out by the cache are regular blocks and can be freed with `Heaps::free`
+/// or with [`free`] that returns them to `Heaps` in batches.
+/// Reserved blocks count as used memory until they are handed out or returned with [`trim`].
+///
+/// [`free`]: #method.free
+/// [`trim`]: #method.trim
+#[derive(Debug)]
+pub struct BlockCache<B: MemoryBackend> {
+    config: BlockCacheConfig,
+    types: Vec<(Properties, Option<u64>)>,
+    blocks: HashMap<(u32, u64), Vec<MemoryBlock<B>>>,
+    freed: Vec<MemoryBlock<B>>,
+}
+
+impl<B> BlockCache<B>
+where
+    B: MemoryBackend,
+{
+    /// Create new cache in front of the `heaps`.
+    pub fn new(heaps: &Heaps<B>, config: BlockCacheConfig) -> Self {
+        assert!(config.batch > 0, "Batch must not be empty");
+
+        BlockCache {
+            config,
+            types: heaps
+                .types
+                .iter()
+                .map(|mt| (mt.properties(), mt.dynamic_max_allocation()))
+                .collect(),
+            blocks: HashMap::new(),
+            freed: Vec::new(),
+        }
+    }
+
+    /// Get memory type and size of cached blocks suitable for the request.
+    /// Returns `None` if request can't be served by the cache,
+    /// that is only blocks of dynamic allocator are cached.
+    fn key(
+        &self,
+        mask: u32,
+        usage: &impl MemoryUsage,
+        size: u64,
+        align: u64,
+    ) -> Option<(u32, u64)> {
+        let block_size = size.max(align).next_power_of_two();
+        if block_size > self.config.max_block_size
+            || usage.tag().is_some()
+            || usage.dedicated() != DedicatedHint::Auto
+        {
+            return None;
+        }
+
+        let dynamic = usage.allocator_fitness(Kind::Dynamic);
+        if dynamic == 0
+            || dynamic < usage.allocator_fitness(Kind::Linear)
+            || dynamic < usage.allocator_fitness(Kind::Buddy)
+        {
+            return None;
+        }
+
+        let memory_index = self
+            .types
+            .iter()
+            .enumerate()
+            .filter(|&(index, (properties, _))| {
+                (mask & (1u32 << index)) != 0 && properties.contains(usage.properties_required())
+            })
+            .max_by_key(|&(_, &(properties, _))| usage.memory_fitness(properties))
+            .filter(|&(_, &(_, dynamic_max))| dynamic_max.map_or(false, |max| block_size <= max))
+            .map(|(index, _)| index as u32)?;
+
+        Some((memory_index, block_size))
+    }
+
+    /// Allocate memory block from the cache
+    /// for intended `usage`,
+    /// with `size`
+    /// and `align` requirements.
+    ///
+    /// `lock` is called to access `Heaps` when cache has to be refilled
+    /// or when request can't be served by the cache.
+    pub fn allocate<H>(
+        &mut self,
+        device: &B::Device,
+        lock: impl FnOnce() -> H,
+        mask: u32,
+        usage: impl MemoryUsage,
+        size: u64,
+        align: u64,
+    ) -> Result<MemoryBlock<B>, HeapsError>
+    where
+        H: DerefMut<Target = Heaps<B>>,
+    {
+        let (memory_index, block_size) = match self.key(mask, &usage, size, align) {
+            Some(key) => key,
+            None => return lock().allocate(device, mask, usage, size, align),
+        };
+
+        let ref mut blocks = self
+            .blocks
+            .entry((memory_index, block_size))
+            .or_insert_with(Vec::new);
+
+        if let Some(block) = blocks.pop() {
+            return Ok(block);
+        }
+
+        log::trace!(
+            "Reserve {} blocks of size {} from memory type {}",
+            self.config.batch,
+            block_size,
+            memory_index
+        );
+
+        let mut heaps = lock();
+
+        // Blocks of other allocators are not worth reserving.
+        let kind =
+            heaps.types[memory_index as usize].pick_allocator(&usage, block_size, block_size);
+        if kind != Some(Kind::Dynamic) {
+            return heaps.allocate(device, mask, usage, size, align);
+        }
+
+        for _ in 0..self.config.batch {
+            match heaps.allocate_from(device, memory_index, &usage, block_size, block_size) {
+                Ok(block) => blocks.push(block),
+                Err(error) => {
+                    log::debug!("Failed to reserve block: {}", error);
+                    break;
+                }
+            }
+        }
+
+        match blocks.pop() {
+            Some(block) => Ok(block),
+            None => heaps.allocate(device, mask, usage, size, align),
+        }
+    }
+
+    /// Free memory block.
+    ///
+    /// Freed blocks are collected and returned to `Heaps` once there are `batch` of them.
+    /// `lock` is called to access `Heaps` only then.
+    pub fn free<H>(&mut self, device: &B::Device, lock: impl FnOnce() -> H, block: MemoryBlock<B>)
+    where
+        H: DerefMut<Target = Heaps<B>>,
+    {
+        self.freed.push(block);
+        if self.freed.len() >= self.config.batch {
+            let mut heaps = lock();
+            for block in self.freed.drain(..) {
+                heaps.free(device, block);
+            }
+        }
+    }
+
+    /// Return freed blocks to the `heaps` without waiting for `batch` of them.
+    /// Reserved blocks are kept.
+    pub fn flush(&mut self, device: &B::Device, heaps: &mut Heaps<B>) {
+        for block in self.freed.drain(..) {
+            heaps.free(device, block);
+        }
+    }
+
+    /// Return all reserved and freed blocks to the `heaps`.
+    pub fn trim(&mut self, device: &B::Device, heaps: &mut Heaps<B>) {
+        self.flush(device, heaps);
+        for (_, blocks) in self.blocks.drain() {
+            for block in blocks {
+                debug_assert!(block.size() <= self.config.max_block_size);
+                heaps.free(device, block);
+            }
+        }
+    }
+
+    /// Return all reserved and freed blocks to the `heaps` and destroy the cache.
+    pub fn dispose(mut self, device: &B::Device, heaps: &mut Heaps<B>) {
+        self.trim(device, heaps);
+    }
+}
@@ -1,10 +1,24 @@
-use crate::utilization::*;
+use crate::{snapshot::MemoryHeapSnapshot, utilization::*};
+
+/// Budget for the memory heap.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HeapBudget {
+    /// Soft limit in bytes.
+    /// Crossing it triggers memory pressure callbacks.
+    pub soft: Option<u64>,
+
+    /// Hard limit in bytes.
+    /// Allocations that would exceed it fail with `HeapsError::BudgetExceeded`.
+    pub hard: Option<u64>,
+}
 
 #[derive(Debug)]
 pub(super) struct MemoryHeap {
     size: u64,
     used: u64,
     effective: u64,
+    budget: HeapBudget,
 }
 
 impl MemoryHeap {
@@ -13,6 +27,7 @@ impl MemoryHeap {
             size,
             used: 0,
             effective: 0,
+            budget: HeapBudget::default(),
         }
     }
 
@@ -25,6 +40,30 @@ impl MemoryHeap {
         }
     }
 
+    pub(super) fn used(&self) -> u64 {
+        self.used
+    }
+
+    pub(super) fn budget(&self) -> HeapBudget {
+        self.budget
+    }
+
+    pub(super) fn set_budget(&mut self, budget: HeapBudget) {
+        self.budget = budget;
+    }
+
+    /// Check if memory allocated from the heap exceeds hard limit.
+    pub(super) fn exceeds_budget(&self) -> bool {
+        self.budget.hard.map_or(false, |hard| self.used > hard)
+    }
+
+    /// Check if soft limit was crossed since usage was `used_before`.
+    pub(super) fn soft_limit_crossed(&self, used_before: u64) -> bool {
+        self.budget
+            .soft
+            .map_or(false, |soft| used_before <= soft && self.used > soft)
+    }
+
     pub(super) fn allocated(&mut self, used: u64, effective: u64) {
         self.used += used;
         self.effective += effective;
@@ -44,6 +83,16 @@ impl MemoryHeap {
                 effective: self.effective,
             },
             size: self.size,
+            budget: self.budget,
+        }
+    }
+
+    pub(super) fn snapshot(&self) -> MemoryHeapSnapshot {
+        MemoryHeapSnapshot {
+            size: self.size,
+            used: self.used,
+            effective: self.effective,
+            budget: self.budget,
         }
     }
 }
@@ -1,24 +1,32 @@
 use {
     super::{BlockFlavor, HeapsConfig},
-    crate::{allocator::*, usage::MemoryUsage, utilization::*},
+    crate::{
+        allocator::*,
+        device::MemoryBackend,
+        snapshot::MemoryTypeSnapshot,
+        usage::{DedicatedHint, MemoryUsage},
+        utilization::*,
+    },
     gfx_hal::memory::Properties,
 };
 
 #[derive(Debug)]
-pub(super) struct MemoryType<B: gfx_hal::Backend> {
+pub(super) struct MemoryType<B: MemoryBackend> {
     heap_index: usize,
     properties: Properties,
     dedicated: DedicatedAllocator,
     linear: Option<LinearAllocator<B>>,
     dynamic: Option<DynamicAllocator<B>>,
+    buddy: Option<BuddyAllocator<B>>,
     // chunk: Option<ChunkAllocator>,
+    preferred_dedicated: u64,
     used: u64,
     effective: u64,
 }
 
 impl<B> MemoryType<B>
 where
-    B: gfx_hal::Backend,
+    B: MemoryBackend,
 {
     pub(super) fn new(
         memory_type: gfx_hal::MemoryTypeId,
@@ -40,6 +48,10 @@ where
             dynamic: config
                 .dynamic
                 .map(|config| DynamicAllocator::new(memory_type, properties, config)),
+            buddy: config
+                .buddy
+                .map(|config| BuddyAllocator::new(memory_type, properties, config)),
+            preferred_dedicated: 0,
             used: 0,
             effective: 0,
         }
@@ -53,81 +65,167 @@ where
         self.heap_index
     }
 
-    pub(super) fn alloc(
+    /// Get maximum size of allocations dynamic allocator can serve.
+    /// Returns `None` if memory type has no dynamic allocator.
+    pub(super) fn dynamic_max_allocation(&self) -> Option<u64> {
+        self.dynamic.as_ref().map(DynamicAllocator::max_allocation)
+    }
+
+    /// Allocate block with specified allocator.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if memory type has no allocator of that kind.
+    pub(super) fn alloc_with(
         &mut self,
         device: &B::Device,
-        usage: impl MemoryUsage,
+        kind: Kind,
+        hint: DedicatedHint,
         size: u64,
         align: u64,
     ) -> Result<(BlockFlavor<B>, u64), gfx_hal::device::AllocationError> {
-        let (block, allocated) = self.alloc_impl(device, usage, size, align)?;
+        let (block, allocated) = self.alloc_impl(device, kind, size, align)?;
         self.effective += block.size();
         self.used += allocated;
+        if kind == Kind::Dedicated && hint == DedicatedHint::Prefer {
+            self.preferred_dedicated += 1;
+        }
         Ok((block, allocated))
     }
 
     fn alloc_impl(
         &mut self,
         device: &B::Device,
-        usage: impl MemoryUsage,
+        kind: Kind,
         size: u64,
         align: u64,
     ) -> Result<(BlockFlavor<B>, u64), gfx_hal::device::AllocationError> {
-        match (self.dynamic.as_mut(), self.linear.as_mut()) {
+        match kind {
+            Kind::Dynamic => self
+                .dynamic
+                .as_mut()
+                .unwrap()
+                .alloc(device, size, align)
+                .map(|(block, size)| (BlockFlavor::Dynamic(block), size)),
+            Kind::Linear => self
+                .linear
+                .as_mut()
+                .unwrap()
+                .alloc(device, size, align)
+                .map(|(block, size)| (BlockFlavor::Linear(block), size)),
+            Kind::Buddy => self
+                .buddy
+                .as_mut()
+                .unwrap()
+                .alloc(device, size, align)
+                .map(|(block, size)| (BlockFlavor::Buddy(block), size)),
+            Kind::Dedicated => self
+                .dedicated
+                .alloc(device, size, align)
+                .map(|(block, size)| (BlockFlavor::Dedicated(block), size)),
+        }
+    }
+
+    /// Pick allocator for the `usage`.
+    /// Dynamic and linear allocators are picked by fitness for the `usage`
+    /// if they can handle `size`.
+    /// Buddy allocator, if configured, takes allocations that would otherwise
+    /// get dedicated memory object, unless `size` or `align` exceed its chunk.
+    /// Falls back to dedicated allocator unless `usage` forbids it.
+    /// Picks dedicated allocator right away if `usage` prefers it.
+    pub(super) fn pick_allocator(
+        &self,
+        usage: &impl MemoryUsage,
+        size: u64,
+        align: u64,
+    ) -> Option<Kind> {
+        let hint = usage.dedicated();
+        if hint == DedicatedHint::Prefer {
+            return Some(Kind::Dedicated);
+        }
+
+        let sub = match (self.dynamic.as_ref(), self.linear.as_ref()) {
             (Some(dynamic), Some(linear)) => {
                 if dynamic.max_allocation() >= size
                     && usage.allocator_fitness(Kind::Dynamic)
                         > usage.allocator_fitness(Kind::Linear)
                 {
-                    dynamic
-                        .alloc(device, size, align)
-                        .map(|(block, size)| (BlockFlavor::Dynamic(block), size))
+                    Some(Kind::Dynamic)
                 } else if linear.max_allocation() >= size
                     && usage.allocator_fitness(Kind::Linear) > 0
                 {
-                    linear
-                        .alloc(device, size, align)
-                        .map(|(block, size)| (BlockFlavor::Linear(block), size))
+                    Some(Kind::Linear)
                 } else {
-                    self.dedicated
-                        .alloc(device, size, align)
-                        .map(|(block, size)| (BlockFlavor::Dedicated(block), size))
+                    None
                 }
             }
             (Some(dynamic), None) => {
                 if dynamic.max_allocation() >= size && usage.allocator_fitness(Kind::Dynamic) > 0 {
-                    dynamic
-                        .alloc(device, size, align)
-                        .map(|(block, size)| (BlockFlavor::Dynamic(block), size))
+                    Some(Kind::Dynamic)
                 } else {
-                    self.dedicated
-                        .alloc(device, size, align)
-                        .map(|(block, size)| (BlockFlavor::Dedicated(block), size))
+                    None
                 }
             }
             (None, Some(linear)) => {
                 if linear.max_allocation() >= size && usage.allocator_fitness(Kind::Linear) > 0 {
-                    linear
-                        .alloc(device, size, align)
-                        .map(|(block, size)| (BlockFlavor::Linear(block), size))
+                    Some(Kind::Linear)
                 } else {
-                    self.dedicated
-                        .alloc(device, size, align)
-                        .map(|(block, size)| (BlockFlavor::Dedicated(block), size))
+                    None
                 }
             }
-            (None, None) => self
-                .dedicated
-                .alloc(device, size, align)
-                .map(|(block, size)| (BlockFlavor::Dedicated(block), size)),
-        }
+            (None, None) => None,
+        };
+
+        sub.or_else(|| {
+            self.buddy
+                .as_ref()
+                .filter(|buddy| buddy.max_allocation() >= size.max(align))
+                .filter(|_| usage.allocator_fitness(Kind::Buddy) > 0)
+                .map(|_| Kind::Buddy)
+        })
+        .or_else(|| match hint {
+            DedicatedHint::Never => None,
+            _ => Some(Kind::Dedicated),
+        })
     }
 
-    pub(super) fn free(&mut self, device: &B::Device, block: BlockFlavor<B>) -> u64 {
+    pub(super) fn free(
+        &mut self,
+        device: &B::Device,
+        block: BlockFlavor<B>,
+        hint: DedicatedHint,
+    ) -> u64 {
         match block {
-            BlockFlavor::Dedicated(block) => self.dedicated.free(device, block),
+            BlockFlavor::Dedicated(block) => {
+                if hint == DedicatedHint::Prefer {
+                    self.preferred_dedicated -= 1;
+                }
+                self.dedicated.free(device, block)
+            }
             BlockFlavor::Linear(block) => self.linear.as_mut().unwrap().free(device, block),
             BlockFlavor::Dynamic(block) => self.dynamic.as_mut().unwrap().free(device, block),
+            BlockFlavor::Buddy(block) => self.buddy.as_mut().unwrap().free(device, block),
+        }
+    }
+
+    /// Select chunks of dynamic allocator for evacuation.
+    pub(super) fn start_evacuation(&mut self) -> Vec<(u64, u32)> {
+        self.dynamic
+            .as_mut()
+            .map_or_else(Vec::new, DynamicAllocator::start_evacuation)
+    }
+
+    /// Mark block of dynamic allocator as one that can't be relocated.
+    pub(super) fn pin(&mut self, block: &DynamicBlock<B>) {
+        if let Some(dynamic) = self.dynamic.as_mut() {
+            dynamic.pin(block);
+        }
+    }
+
+    /// Return chunk of dynamic allocator back to service.
+    pub(super) fn stop_evacuation(&mut self, block_size: u64, chunk_index: u32) {
+        if let Some(dynamic) = self.dynamic.as_mut() {
+            dynamic.stop_evacuation(block_size, chunk_index);
         }
     }
 
@@ -142,6 +240,10 @@ where
             dynamic.dispose();
             log::trace!("Dynamic allocator disposed");
         }
+        if let Some(buddy) = self.buddy {
+            buddy.dispose();
+            log::trace!("Buddy allocator disposed");
+        }
     }
 
     pub(super) fn utilization(&self) -> MemoryTypeUtilization {
@@ -152,6 +254,27 @@ where
             },
             properties: self.properties,
             heap_index: self.heap_index,
+            dedicated: {
+                let snapshot = self.dedicated.snapshot();
+                DedicatedUtilization {
+                    blocks: snapshot.blocks,
+                    preferred: self.preferred_dedicated,
+                    size: snapshot.used,
+                }
+            },
+        }
+    }
+
+    pub(super) fn snapshot(&self) -> MemoryTypeSnapshot {
+        MemoryTypeSnapshot {
+            properties: self.properties,
+            heap_index: self.heap_index,
+            used: self.used,
+            effective: self.effective,
+            dedicated: self.dedicated.snapshot(),
+            linear: self.linear.as_ref().map(LinearAllocator::snapshot),
+            dynamic: self.dynamic.as_ref().map(DynamicAllocator::snapshot),
+            buddy: self.buddy.as_ref().map(BuddyAllocator::snapshot),
         }
     }
 }
@@ -1,10 +1,28 @@
+mod cache;
 mod heap;
 mod memory_type;
 
+pub use self::{
+    cache::{BlockCache, BlockCacheConfig},
+    heap::HeapBudget,
+};
+
 use {
     self::{heap::MemoryHeap, memory_type::MemoryType},
-    crate::{allocator::*, block::Block, mapping::*, usage::MemoryUsage, util::*, utilization::*},
-    std::ops::Range,
+    crate::{
+        allocator::*,
+        block::Block,
+        device::MemoryBackend,
+        mapping::*,
+        snapshot::HeapsSnapshot,
+        usage::{DedicatedHint, MemoryUsage},
+        util::*,
+        utilization::*,
+    },
+    std::{
+        collections::{HashMap, HashSet},
+        ops::Range,
+    },
 };
 
 /// Possible errors returned by `Heaps`.
@@ -15,6 +33,10 @@ pub enum HeapsError {
     AllocationError(gfx_hal::device::AllocationError),
     /// No memory types among required for resource with requested properties was found.
     NoSuitableMemory(u32, gfx_hal::memory::Properties),
+    /// Allocation would exceed hard limit of the heap budget.
+    BudgetExceeded(usize),
+    /// Allocation of that size requires dedicated memory object, but usage forbids it.
+    DedicatedForbidden(u64),
 }
 
 impl std::fmt::Display for HeapsError {
@@ -26,6 +48,14 @@ impl std::fmt::Display for HeapsError {
                 "Memory type among ({}) with properties ({:?}) not found",
                 e, e2
             ),
+            HeapsError::BudgetExceeded(heap_index) => {
+                write!(f, "Budget of memory heap {} exceeded", heap_index)
+            }
+            HeapsError::DedicatedForbidden(size) => write!(
+                f,
+                "Allocation of {} bytes requires dedicated memory object, but it was forbidden",
+                size
+            ),
         }
     }
 }
@@ -52,18 +82,47 @@ pub struct HeapsConfig {
 
     /// Config for dynamic sub-allocator.
     pub dynamic: Option<DynamicConfig>,
+
+    /// Config for buddy sub-allocator.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub buddy: Option<BuddyConfig>,
+}
+
+/// Memory pressure reported when heap usage crosses soft limit of its budget.
+#[derive(Clone, Copy, Debug)]
+pub struct MemoryPressure {
+    /// Index of the heap.
+    pub heap_index: usize,
+
+    /// Number of bytes allocated from the heap.
+    pub used: u64,
+
+    /// Budget of the heap.
+    pub budget: HeapBudget,
+}
+
+struct PressureCallbacks(Vec<Box<dyn Fn(MemoryPressure) + Send + Sync>>);
+
+impl std::fmt::Debug for PressureCallbacks {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(fmt, "PressureCallbacks({})", self.0.len())
+    }
 }
 
 /// Heaps available on particular physical device.
 #[derive(Debug)]
-pub struct Heaps<B: gfx_hal::Backend> {
+pub struct Heaps<B: MemoryBackend> {
     types: Vec<MemoryType<B>>,
     heaps: Vec<MemoryHeap>,
+    pressure_callbacks: PressureCallbacks,
+    tags: HashMap<Option<&'static str>, TagMemoryUtilization>,
+    #[cfg(feature = "leak-report")]
+    live: crate::leak::LiveBlocks,
 }
 
 impl<B> Heaps<B>
 where
-    B: gfx_hal::Backend,
+    B: MemoryBackend,
 {
     /// This must be called with `gfx_hal::memory::Properties` fetched from physical device.
     pub unsafe fn new<P, H>(types: P, heaps: H) -> Self
@@ -95,14 +154,46 @@ where
                 })
                 .collect(),
             heaps,
+            pressure_callbacks: PressureCallbacks(Vec::new()),
+            tags: HashMap::new(),
+            #[cfg(feature = "leak-report")]
+            live: Default::default(),
         }
     }
 
+    /// Get budget of the heap.
+    pub fn budget(&self, heap_index: usize) -> HeapBudget {
+        self.heaps[heap_index].budget()
+    }
+
+    /// Set budget for the heap.
+    pub fn set_budget(&mut self, heap_index: usize, budget: HeapBudget) {
+        log::trace!("Set budget for heap {}: {:?}", heap_index, budget);
+        self.heaps[heap_index].set_budget(budget);
+    }
+
+    /// Register callback to be called when usage of any heap crosses soft limit of its budget.
+    ///
+    /// Callback is called during allocation while `Heaps` are borrowed,
+    /// so it can't allocate or free memory itself.
+    /// Instead it should signal caches to evict their content later.
+    pub fn on_memory_pressure(
+        &mut self,
+        callback: impl Fn(MemoryPressure) + Send + Sync + 'static,
+    ) {
+        self.pressure_callbacks.0.push(Box::new(callback));
+    }
+
     /// Allocate memory block
     /// from one of memory types specified by `mask`,
     /// for intended `usage`,
     /// with `size`
     /// and `align` requirements.
+    ///
+    /// Block is tagged with `usage.tag()` for [`utilization_by_tag`] report.
+    /// `usage.dedicated()` hint controls whether block gets dedicated memory object.
+    ///
+    /// [`utilization_by_tag`]: #method.utilization_by_tag
     pub fn allocate(
         &mut self,
         device: &B::Device,
@@ -113,7 +204,7 @@ where
     ) -> Result<MemoryBlock<B>, HeapsError> {
         debug_assert!(fits_u32(self.types.len()));
 
-        let (memory_index, _, _) = {
+        let candidates = {
             let suitable_types = self
                 .types
                 .iter()
@@ -136,17 +227,38 @@ where
                 ));
             }
 
-            suitable_types
+            let available_types = suitable_types
                 .into_iter()
                 .filter(|(_, mt, _)| self.heaps[mt.heap_index()].available() > size + align)
-                .max_by_key(|&(_, _, fitness)| fitness)
-                .ok_or_else(|| {
-                    log::error!("All suitable heaps are exhausted. {:#?}", self);
-                    gfx_hal::device::OutOfMemory::Device
-                })?
+                .collect::<smallvec::SmallVec<[_; 64]>>();
+
+            if available_types.is_empty() {
+                log::error!("All suitable heaps are exhausted. {:#?}", self);
+                return Err(gfx_hal::device::OutOfMemory::Device.into());
+            }
+
+            // Most fit first, the last one among equally fit.
+            let mut candidates = available_types
+                .into_iter()
+                .rev()
+                .map(|(index, _, fitness)| (index, fitness))
+                .collect::<smallvec::SmallVec<[_; 64]>>();
+            candidates.sort_by_key(|&(_, fitness)| std::cmp::Reverse(fitness));
+            candidates
         };
 
-        self.allocate_from(device, memory_index as u32, usage, size, align)
+        // Try less fit memory types if budget of the best one would be exceeded.
+        let mut budget_exceeded = None;
+        for (memory_index, _) in candidates {
+            match self.allocate_from(device, memory_index as u32, &usage, size, align) {
+                Err(HeapsError::BudgetExceeded(heap_index)) => {
+                    budget_exceeded.get_or_insert(heap_index);
+                }
+                result => return result,
+            }
+        }
+
+        Err(HeapsError::BudgetExceeded(budget_exceeded.unwrap()))
     }
 
     /// Allocate memory block
@@ -171,6 +283,31 @@ where
         );
         assert!(fits_usize(memory_index));
 
+        let kind = self.types[memory_index as usize]
+            .pick_allocator(&usage, size, align)
+            .ok_or(HeapsError::DedicatedForbidden(size))?;
+
+        let hints = Hints {
+            tag: usage.tag(),
+            dedicated: usage.dedicated(),
+        };
+        self.allocate_with(device, memory_index, kind, size, align, hints)
+    }
+
+    /// Allocate memory block
+    /// from `memory_index` specified,
+    /// with allocator of `kind` specified,
+    /// with `size`
+    /// and `align` requirements.
+    fn allocate_with(
+        &mut self,
+        device: &B::Device,
+        memory_index: u32,
+        kind: Kind,
+        size: u64,
+        align: u64,
+        hints: Hints,
+    ) -> Result<MemoryBlock<B>, HeapsError> {
         let ref mut memory_type = self.types[memory_index as usize];
         let ref mut memory_heap = self.heaps[memory_type.heap_index()];
 
@@ -178,15 +315,138 @@ where
             return Err(gfx_hal::device::OutOfMemory::Device.into());
         }
 
-        let (block, allocated) = memory_type.alloc(device, usage, size, align)?;
+        let used_before = memory_heap.used();
+
+        let (block, allocated) =
+            memory_type.alloc_with(device, kind, hints.dedicated, size, align)?;
         memory_heap.allocated(allocated, block.size());
 
+        // Sub-allocators may allocate whole chunk from device or nothing at all,
+        // so budget is checked against memory actually allocated.
+        if allocated > 0 && memory_heap.exceeds_budget() {
+            let size = block.size();
+            let freed = memory_type.free(device, block, hints.dedicated);
+            memory_heap.freed(freed, size);
+            return Err(HeapsError::BudgetExceeded(memory_type.heap_index()));
+        }
+
+        if memory_heap.soft_limit_crossed(used_before) {
+            let heap_index = memory_type.heap_index();
+            self.memory_pressure(heap_index);
+        }
+
+        self.tags
+            .entry(hints.tag)
+            .or_default()
+            .allocated(block.size());
+
         Ok(MemoryBlock {
+            #[cfg(feature = "leak-report")]
+            leak_id: self.live.track(block.size(), memory_index, hints.tag),
             block,
             memory_index,
+            hints,
         })
     }
 
+    /// Start defragmentation.
+    ///
+    /// Selects sparsely used chunks of dynamic allocators
+    /// which blocks can be moved to other chunks.
+    /// Selected chunks stop serving new allocations
+    /// until plan is passed to [`finish_defragmentation`].
+    /// Chunks with blocks marked by [`pin`] are never selected.
+    ///
+    /// Caller should relocate resources bound to blocks for which
+    /// [`DefragmentationPlan::contains`] returns `true`
+    /// using [`relocate`] to allocate new blocks,
+    /// copy the content and free the old blocks once copying is complete.
+    /// Emptied chunks are returned to the device when their last block is freed.
+    ///
+    /// [`finish_defragmentation`]: #method.finish_defragmentation
+    /// [`pin`]: #method.pin
+    /// [`DefragmentationPlan::contains`]: struct.DefragmentationPlan.html#method.contains
+    /// [`relocate`]: #method.relocate
+    pub fn plan_defragmentation(&mut self) -> DefragmentationPlan {
+        let mut chunks = HashSet::new();
+        for (memory_index, memory_type) in self.types.iter_mut().enumerate() {
+            chunks.extend(
+                memory_type
+                    .start_evacuation()
+                    .into_iter()
+                    .map(|(block_size, chunk_index)| {
+                        (memory_index as u32, block_size, chunk_index)
+                    }),
+            );
+        }
+        DefragmentationPlan { chunks }
+    }
+
+    /// Allocate new block to move content of the `block` into.
+    /// New block is allocated from the same memory type and with the same allocator,
+    /// but never from chunks selected for evacuation.
+    /// New block inherits tag and dedicated allocation hint of the old one.
+    ///
+    /// `size` and `align` are requirements of the resource that will be bound to the new block.
+    pub fn relocate(
+        &mut self,
+        device: &B::Device,
+        block: &MemoryBlock<B>,
+        size: u64,
+        align: u64,
+    ) -> Result<MemoryBlock<B>, HeapsError> {
+        let memory_index = block.memory_index;
+        let kind = match &block.block {
+            BlockFlavor::Dedicated(_) => Kind::Dedicated,
+            BlockFlavor::Linear(_) => Kind::Linear,
+            BlockFlavor::Dynamic(_) => Kind::Dynamic,
+            BlockFlavor::Buddy(_) => Kind::Buddy,
+        };
+
+        log::trace!(
+            "Relocate memory block: type '{}', kind '{:?}', size: '{}', align: '{}'",
+            memory_index,
+            kind,
+            size,
+            align
+        );
+
+        self.allocate_with(device, memory_index, kind, size, align, block.hints)
+    }
+
+    /// Mark block as one that can't be relocated,
+    /// for example because it is bound to an image.
+    /// Chunks with such blocks are never selected for evacuation.
+    /// Mark is removed when block is freed.
+    pub fn pin(&mut self, block: &MemoryBlock<B>) {
+        if let BlockFlavor::Dynamic(dynamic) = &block.block {
+            self.types[block.memory_index as usize].pin(dynamic);
+        }
+    }
+
+    /// Finish defragmentation.
+    /// Chunks from the plan that still have blocks in use return to service.
+    pub fn finish_defragmentation(&mut self, plan: DefragmentationPlan) {
+        for (memory_index, block_size, chunk_index) in plan.chunks {
+            self.types[memory_index as usize].stop_evacuation(block_size, chunk_index);
+        }
+    }
+
+    /// Report memory pressure to registered callbacks.
+    fn memory_pressure(&self, heap_index: usize) {
+        let ref heap = self.heaps[heap_index];
+        let pressure = MemoryPressure {
+            heap_index,
+            used: heap.used(),
+            budget: heap.budget(),
+        };
+
+        log::debug!("Memory pressure: {:?}", pressure);
+        for callback in &self.pressure_callbacks.0 {
+            callback(pressure);
+        }
+    }
+
     /// Free memory block.
     ///
     /// Memory block must be allocated from this heap.
@@ -198,14 +458,32 @@ where
 
         let ref mut memory_type = self.types[memory_index as usize];
         let ref mut memory_heap = self.heaps[memory_type.heap_index()];
-        let freed = memory_type.free(device, block.block);
+        let freed = memory_type.free(device, block.block, block.hints.dedicated);
         memory_heap.freed(freed, size);
+
+        if let Some(tag) = self.tags.get_mut(&block.hints.tag) {
+            tag.freed(size);
+        }
+
+        #[cfg(feature = "leak-report")]
+        self.live.untrack(block.leak_id);
     }
 
     /// Dispose of allocator.
     /// Cleanup allocators before dropping.
     /// Will panic if memory instances are left allocated.
+    ///
+    /// With `leak-report` feature enabled
+    /// blocks that weren't freed are reported grouped by allocation site.
     pub fn dispose(self, device: &B::Device) {
+        #[cfg(feature = "leak-report")]
+        {
+            let report = self.leak_report();
+            if !report.is_empty() {
+                log::error!("{}", report);
+            }
+        }
+
         for mt in self.types {
             mt.dispose(device)
         }
@@ -218,30 +496,130 @@ where
             types: self.types.iter().map(MemoryType::utilization).collect(),
         }
     }
+
+    /// Take detailed snapshot of the allocators state.
+    /// Includes block maps of every chunk allocated from device.
+    /// With `serde-1` feature enabled it can be serialized for offline analysis.
+    pub fn snapshot(&self) -> HeapsSnapshot {
+        HeapsSnapshot {
+            heaps: self.heaps.iter().map(MemoryHeap::snapshot).collect(),
+            types: self.types.iter().map(MemoryType::snapshot).collect(),
+        }
+    }
+
+    /// Get report of live blocks grouped by allocation site.
+    /// Resolving backtraces is slow, this should be used for diagnostics only.
+    #[cfg(feature = "leak-report")]
+    pub fn leak_report(&self) -> crate::leak::LeakReport {
+        self.live.report()
+    }
+
+    /// Get memory utilization broken down by allocation tags.
+    /// Untagged allocations are reported with `None` tag.
+    /// Sorted from the largest consumer to the smallest.
+    pub fn utilization_by_tag(&self) -> Vec<TagMemoryUtilization> {
+        let mut tags = self
+            .tags
+            .iter()
+            .filter(|(_, utilization)| utilization.blocks > 0)
+            .map(|(&tag, utilization)| TagMemoryUtilization {
+                tag,
+                ..*utilization
+            })
+            .collect::<Vec<_>>();
+        tags.sort_by(|a, b| b.size.cmp(&a.size));
+        tags
+    }
 }
 
 /// Memory block allocated from `Heaps`.
 #[derive(Debug)]
-pub struct MemoryBlock<B: gfx_hal::Backend> {
+pub struct MemoryBlock<B: MemoryBackend> {
     block: BlockFlavor<B>,
     memory_index: u32,
+    hints: Hints,
+    #[cfg(feature = "leak-report")]
+    leak_id: u64,
+}
+
+/// Hints from the usage the block was allocated for.
+/// Relocated blocks inherit them.
+#[derive(Clone, Copy, Debug)]
+struct Hints {
+    tag: Option<&'static str>,
+    dedicated: DedicatedHint,
 }
 
 impl<B> MemoryBlock<B>
 where
-    B: gfx_hal::Backend,
+    B: MemoryBackend,
 {
     /// Get memory type id.
     pub fn memory_type(&self) -> u32 {
         self.memory_index
     }
+
+    /// Get tag of the allocation.
+    pub fn tag(&self) -> Option<&'static str> {
+        self.hints.tag
+    }
+
+    /// Get dedicated allocation hint the block was allocated with.
+    pub fn dedicated_hint(&self) -> DedicatedHint {
+        self.hints.dedicated
+    }
+
+    /// Check if block owns whole dedicated memory object.
+    pub fn is_dedicated(&self) -> bool {
+        match self.block {
+            BlockFlavor::Dedicated(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Chunks selected for evacuation by [`Heaps::plan_defragmentation`].
+///
+/// [`Heaps::plan_defragmentation`]: struct.Heaps.html#method.plan_defragmentation
+#[derive(Clone, Debug, Default)]
+pub struct DefragmentationPlan {
+    chunks: HashSet<(u32, u64, u32)>,
+}
+
+impl DefragmentationPlan {
+    /// Check if there is nothing to evacuate.
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Get number of chunks selected for evacuation.
+    pub fn chunks(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Check if block is allocated from one of the chunks selected for evacuation
+    /// and should be relocated.
+    pub fn contains<B>(&self, block: &MemoryBlock<B>) -> bool
+    where
+        B: MemoryBackend,
+    {
+        match &block.block {
+            BlockFlavor::Dynamic(dynamic) => {
+                let (block_size, chunk_index) = dynamic.chunk_key();
+                self.chunks
+                    .contains(&(block.memory_index, block_size, chunk_index))
+            }
+            _ => false,
+        }
+    }
 }
 
 #[derive(Debug)]
-enum BlockFlavor<B: gfx_hal::Backend> {
+enum BlockFlavor<B: MemoryBackend> {
     Dedicated(DedicatedBlock<B>),
     Linear(LinearBlock<B>),
     Dynamic(DynamicBlock<B>),
+    Buddy(BuddyBlock<B>),
     // Chunk(ChunkBlock<B>),
 }
 
@@ -252,6 +630,7 @@ macro_rules! any_block {
             Dedicated($block) => $expr,
             Linear($block) => $expr,
             Dynamic($block) => $expr,
+            Buddy($block) => $expr,
             // Chunk($block) => $expr,
         }
     }};
@@ -261,6 +640,7 @@ macro_rules! any_block {
             Dedicated($block) => $expr,
             Linear($block) => $expr,
             Dynamic($block) => $expr,
+            Buddy($block) => $expr,
             // Chunk($block) => $expr,
         }
     }};
@@ -270,6 +650,7 @@ macro_rules! any_block {
             Dedicated($block) => $expr,
             Linear($block) => $expr,
             Dynamic($block) => $expr,
+            Buddy($block) => $expr,
             // Chunk($block) => $expr,
         }
     }};
@@ -277,7 +658,7 @@ macro_rules! any_block {
 
 impl<B> BlockFlavor<B>
 where
-    B: gfx_hal::Backend,
+    B: MemoryBackend,
 {
     #[inline]
     fn size(&self) -> u64 {
@@ -286,6 +667,7 @@ where
             Dedicated(block) => block.size(),
             Linear(block) => block.size(),
             Dynamic(block) => block.size(),
+            Buddy(block) => block.size(),
             // Chunk(block) => block.size(),
         }
     }
@@ -293,7 +675,7 @@ where
 
 impl<B> Block<B> for MemoryBlock<B>
 where
-    B: gfx_hal::Backend,
+    B: MemoryBackend,
 {
     #[inline]
     fn properties(&self) -> gfx_hal::memory::Properties {
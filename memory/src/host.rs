@@ -0,0 +1,242 @@
+//! Host memory simulator.
+//!
+//! `HostDevice` allocates memory objects from host memory
+//! and validates their usage, so allocators can be exercised without GPU.
+//! Available with `host-sim` feature.
+
+use {
+    crate::device::{MemoryBackend, MemoryDevice},
+    gfx_hal::{adapter::MemoryProperties, memory::Properties},
+    std::{collections::HashMap, ops::Range, sync::Mutex},
+};
+
+/// Backend of the host memory simulator.
+/// Allocators and `Heaps` parametrized with it use `HostDevice`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HostBackend {}
+
+impl MemoryBackend for HostBackend {
+    type Memory = HostMemory;
+    type Device = HostDevice;
+}
+
+/// Memory object allocated by `HostDevice`.
+#[allow(missing_copy_implementations)]
+#[derive(Debug)]
+pub struct HostMemory {
+    id: u64,
+    size: u64,
+}
+
+impl HostMemory {
+    /// Get unique id of the memory object.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Get size of the memory object.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+#[derive(Debug)]
+struct HostAllocation {
+    memory_type: usize,
+    size: u64,
+    data: Option<Box<[u8]>>,
+    mapped: Option<Range<u64>>,
+}
+
+#[derive(Debug, Default)]
+struct HostState {
+    next_id: u64,
+    heaps_used: Vec<u64>,
+    allocations: HashMap<u64, HostAllocation>,
+    maps: u64,
+    flushes: u64,
+    invalidations: u64,
+}
+
+/// Device simulator that allocates memory objects from host memory.
+///
+/// Tracks allocations, mappings and flushes
+/// and panics on invalid usage such as double free,
+/// mapping memory twice or flushing range that isn't mapped.
+/// Allocations fail with `OutOfMemory` error when heap size would be exceeded.
+#[derive(Debug)]
+pub struct HostDevice {
+    properties: MemoryProperties,
+    state: Mutex<HostState>,
+}
+
+impl HostDevice {
+    /// Create new device simulator with memory properties specified.
+    pub fn new(properties: MemoryProperties) -> Self {
+        HostDevice {
+            state: Mutex::new(HostState {
+                heaps_used: vec![0; properties.memory_heaps.len()],
+                ..HostState::default()
+            }),
+            properties,
+        }
+    }
+
+    /// Get memory properties of the device.
+    pub fn properties(&self) -> &MemoryProperties {
+        &self.properties
+    }
+
+    /// Get number of memory objects allocated.
+    pub fn allocations(&self) -> usize {
+        self.state.lock().unwrap().allocations.len()
+    }
+
+    /// Get number of bytes allocated from the heap.
+    pub fn heap_used(&self, heap_index: usize) -> u64 {
+        self.state.lock().unwrap().heaps_used[heap_index]
+    }
+
+    /// Get number of memory objects currently mapped.
+    pub fn mapped(&self) -> usize {
+        self.state
+            .lock()
+            .unwrap()
+            .allocations
+            .values()
+            .filter(|allocation| allocation.mapped.is_some())
+            .count()
+    }
+
+    /// Get total number of `map`, `flush` and `invalidate` calls.
+    pub fn calls(&self) -> (u64, u64, u64) {
+        let state = self.state.lock().unwrap();
+        (state.maps, state.flushes, state.invalidations)
+    }
+
+    fn memory_properties(&self, memory_type: usize) -> Properties {
+        self.properties.memory_types[memory_type].properties
+    }
+
+    fn assert_mapped(allocation: &HostAllocation, range: &Range<u64>) {
+        let mapped = allocation
+            .mapped
+            .as_ref()
+            .expect("Memory must be mapped to flush or invalidate");
+        assert!(
+            range.start >= mapped.start && range.end <= mapped.end,
+            "Range {:?} is outside of mapped range {:?}",
+            range,
+            mapped
+        );
+    }
+}
+
+impl MemoryDevice<HostBackend> for HostDevice {
+    unsafe fn allocate_memory(
+        &self,
+        memory_type: gfx_hal::MemoryTypeId,
+        size: u64,
+    ) -> Result<HostMemory, gfx_hal::device::AllocationError> {
+        assert!(size > 0, "Memory object size must be non-zero");
+        let heap_index = self
+            .properties
+            .memory_types
+            .get(memory_type.0)
+            .expect("Invalid memory type")
+            .heap_index;
+
+        let ref mut state = *self.state.lock().unwrap();
+        if self.properties.memory_heaps[heap_index] - state.heaps_used[heap_index] < size {
+            return Err(gfx_hal::device::OutOfMemory::Device.into());
+        }
+        state.heaps_used[heap_index] += size;
+
+        let id = state.next_id;
+        state.next_id += 1;
+        state.allocations.insert(
+            id,
+            HostAllocation {
+                memory_type: memory_type.0,
+                size,
+                data: None,
+                mapped: None,
+            },
+        );
+
+        Ok(HostMemory { id, size })
+    }
+
+    unsafe fn free_memory(&self, memory: HostMemory) {
+        let ref mut state = *self.state.lock().unwrap();
+        let allocation = state
+            .allocations
+            .remove(&memory.id)
+            .expect("Memory object freed twice or never allocated");
+        let heap_index = self.properties.memory_types[allocation.memory_type].heap_index;
+        state.heaps_used[heap_index] -= allocation.size;
+    }
+
+    unsafe fn map_memory(
+        &self,
+        memory: &HostMemory,
+        range: Range<u64>,
+    ) -> Result<*mut u8, gfx_hal::device::MapError> {
+        let ref mut state = *self.state.lock().unwrap();
+        state.maps += 1;
+        let allocation = state
+            .allocations
+            .get_mut(&memory.id)
+            .expect("Memory object is not allocated");
+
+        if !self
+            .memory_properties(allocation.memory_type)
+            .contains(Properties::CPU_VISIBLE)
+        {
+            return Err(gfx_hal::device::MapError::MappingFailed);
+        }
+
+        assert!(allocation.mapped.is_none(), "Memory is already mapped");
+        if range.start >= range.end || range.end > allocation.size {
+            return Err(gfx_hal::device::MapError::OutOfBounds);
+        }
+
+        let size = allocation.size as usize;
+        let data = allocation
+            .data
+            .get_or_insert_with(|| vec![0; size].into_boxed_slice());
+        allocation.mapped = Some(range.clone());
+        Ok(data.as_mut_ptr().add(range.start as usize))
+    }
+
+    unsafe fn unmap_memory(&self, memory: &HostMemory) {
+        let ref mut state = *self.state.lock().unwrap();
+        let allocation = state
+            .allocations
+            .get_mut(&memory.id)
+            .expect("Memory object is not allocated");
+        assert!(allocation.mapped.take().is_some(), "Memory is not mapped");
+    }
+
+    unsafe fn flush_memory_range(
+        &self,
+        memory: &HostMemory,
+        range: Range<u64>,
+    ) -> Result<(), gfx_hal::device::OutOfMemory> {
+        let ref mut state = *self.state.lock().unwrap();
+        state.flushes += 1;
+        Self::assert_mapped(&state.allocations[&memory.id], &range);
+        Ok(())
+    }
+
+    unsafe fn invalidate_memory_range(
+        &self,
+        memory: &HostMemory,
+        range: Range<u64>,
+    ) -> Result<(), gfx_hal::device::OutOfMemory> {
+        let ref mut state = *self.state.lock().unwrap();
+        state.invalidations += 1;
+        Self::assert_mapped(&state.allocations[&memory.id], &range);
+        Ok(())
+    }
+}
@@ -0,0 +1,157 @@
+//! Tracking of live memory blocks for leak reports.
+//! Enabled with `leak-report` feature.
+
+use std::{collections::HashMap, fmt};
+
+/// Maximum number of frames of allocation site kept in the report.
+const MAX_SITE_FRAMES: usize = 16;
+
+#[derive(Debug)]
+struct LiveBlock {
+    size: u64,
+    memory_index: u32,
+    tag: Option<&'static str>,
+    backtrace: backtrace::Backtrace,
+}
+
+/// Live blocks allocated from `Heaps`.
+#[derive(Debug, Default)]
+pub(crate) struct LiveBlocks {
+    next_id: u64,
+    blocks: HashMap<u64, LiveBlock>,
+}
+
+impl LiveBlocks {
+    /// Record allocation site of the new block.
+    /// Returns id to untrack block with.
+    pub(crate) fn track(&mut self, size: u64, memory_index: u32, tag: Option<&'static str>) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.blocks.insert(
+            id,
+            LiveBlock {
+                size,
+                memory_index,
+                tag,
+                backtrace: backtrace::Backtrace::new_unresolved(),
+            },
+        );
+        id
+    }
+
+    /// Forget freed block.
+    pub(crate) fn untrack(&mut self, id: u64) {
+        let removed = self.blocks.remove(&id);
+        debug_assert!(removed.is_some(), "Block wasn't tracked");
+    }
+
+    /// Group live blocks by allocation site.
+    pub(crate) fn report(&self) -> LeakReport {
+        let mut groups: HashMap<Vec<String>, LeakGroup> = HashMap::new();
+
+        for block in self.blocks.values() {
+            let site = allocation_site(&block.backtrace);
+            let group = groups.entry(site.clone()).or_insert_with(|| LeakGroup {
+                site,
+                tags: Vec::new(),
+                memory_types: Vec::new(),
+                blocks: 0,
+                size: 0,
+            });
+
+            group.blocks += 1;
+            group.size += block.size;
+            if !group.tags.contains(&block.tag) {
+                group.tags.push(block.tag);
+            }
+            if !group.memory_types.contains(&block.memory_index) {
+                group.memory_types.push(block.memory_index);
+            }
+        }
+
+        let mut groups = groups
+            .into_iter()
+            .map(|(_, group)| group)
+            .collect::<Vec<_>>();
+        groups.sort_by(|a, b| b.size.cmp(&a.size));
+        LeakReport { groups }
+    }
+}
+
+/// Resolve backtrace and format frames outside of this crate.
+fn allocation_site(backtrace: &backtrace::Backtrace) -> Vec<String> {
+    let mut backtrace = backtrace.clone();
+    backtrace.resolve();
+
+    backtrace
+        .frames()
+        .iter()
+        .flat_map(|frame| frame.symbols())
+        .filter_map(|symbol| {
+            let name = symbol.name()?.to_string();
+            match (symbol.filename(), symbol.lineno()) {
+                (Some(file), Some(line)) => Some(format!("{} ({}:{})", name, file.display(), line)),
+                _ => Some(name),
+            }
+        })
+        .skip_while(|frame| frame.starts_with("backtrace::") || frame.contains("rendy_memory::"))
+        .take(MAX_SITE_FRAMES)
+        .collect()
+}
+
+/// Leaked blocks allocated from the same site.
+#[derive(Clone, Debug)]
+pub struct LeakGroup {
+    /// Frames of the allocation site, innermost first.
+    pub site: Vec<String>,
+
+    /// Tags of the leaked blocks.
+    pub tags: Vec<Option<&'static str>>,
+
+    /// Memory types of the leaked blocks.
+    pub memory_types: Vec<u32>,
+
+    /// Number of leaked blocks.
+    pub blocks: u64,
+
+    /// Total size of leaked blocks.
+    pub size: u64,
+}
+
+/// Report of memory blocks that weren't freed.
+/// Blocks are grouped by allocation site, largest groups first.
+#[derive(Clone, Debug)]
+pub struct LeakReport {
+    /// Groups of leaked blocks.
+    pub groups: Vec<LeakGroup>,
+}
+
+impl LeakReport {
+    /// Check if there are no leaks.
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+}
+
+impl fmt::Display for LeakReport {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            fmt,
+            "Memory leak: {} blocks of {} bytes total were not freed",
+            self.groups.iter().map(|group| group.blocks).sum::<u64>(),
+            self.groups.iter().map(|group| group.size).sum::<u64>(),
+        )?;
+
+        for group in &self.groups {
+            writeln!(
+                fmt,
+                "{} blocks of {} bytes, memory types: {:?}, tags: {:?}, allocated at:",
+                group.blocks, group.size, group.memory_types, group.tags,
+            )?;
+            for frame in &group.site {
+                writeln!(fmt, "    {}", frame)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -13,9 +13,15 @@
 )]
 mod allocator;
 mod block;
+mod device;
 mod heaps;
+#[cfg(feature = "host-sim")]
+mod host;
+#[cfg(feature = "leak-report")]
+mod leak;
 mod mapping;
 mod memory;
+mod snapshot;
 mod usage;
 mod util;
 mod utilization;
@@ -23,9 +29,20 @@ mod utilization;
 pub use crate::{
     allocator::*,
     block::Block,
-    heaps::{Heaps, HeapsConfig, HeapsError, MemoryBlock},
-    mapping::{write::Write, Coherent, MappedRange, MaybeCoherent, NonCoherent},
+    device::{MemoryBackend, MemoryDevice},
+    heaps::{
+        BlockCache, BlockCacheConfig, DefragmentationPlan, HeapBudget, Heaps, HeapsConfig,
+        HeapsError, MemoryBlock, MemoryPressure,
+    },
+    mapping::{write::Write, Coherent, DirtyRanges, MappedRange, MaybeCoherent, NonCoherent},
     memory::Memory,
+    snapshot::*,
     usage::*,
     utilization::*,
 };
+
+#[cfg(feature = "leak-report")]
+pub use crate::leak::{LeakGroup, LeakReport};
+
+#[cfg(feature = "host-sim")]
+pub use crate::host::{HostBackend, HostDevice, HostMemory};
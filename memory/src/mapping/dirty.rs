@@ -0,0 +1,80 @@
+use std::ops::Range;
+
+/// Ranges of mapped memory modified by the host
+/// that have to be flushed before device can access them.
+#[derive(Clone, Debug, Default)]
+pub struct DirtyRanges {
+    ranges: Vec<Range<u64>>,
+}
+
+impl DirtyRanges {
+    /// Create empty set of ranges.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check if there are no dirty ranges.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Mark range as dirty.
+    /// Adjacent and overlapping ranges are merged lazily.
+    pub fn insert(&mut self, range: Range<u64>) {
+        if range.start >= range.end {
+            return;
+        }
+
+        match self.ranges.last_mut() {
+            Some(last) if last.start <= range.end && range.start <= last.end => {
+                last.start = last.start.min(range.start);
+                last.end = last.end.max(range.end);
+            }
+            _ => self.ranges.push(range),
+        }
+    }
+
+    /// Forget all dirty ranges.
+    pub fn clear(&mut self) {
+        self.ranges.clear();
+    }
+
+    /// Get dirty ranges as they were inserted.
+    pub fn ranges(&self) -> &[Range<u64>] {
+        &self.ranges
+    }
+
+    /// Get minimal set of disjoint ranges covering all dirty ranges
+    /// shifted by `offset`,
+    /// with bounds expanded to multiple of `non_coherent_atom_size`
+    /// and clamped to `size`.
+    /// Ranges that overlap after expansion are merged.
+    pub fn coalesce(&self, offset: u64, non_coherent_atom_size: u64, size: u64) -> Vec<Range<u64>> {
+        assert_ne!(non_coherent_atom_size, 0);
+
+        let mut expanded = self
+            .ranges
+            .iter()
+            .map(|range| {
+                let start = range.start + offset;
+                let end = range.end + offset;
+                let start = start - start % non_coherent_atom_size;
+                let end = match end % non_coherent_atom_size {
+                    0 => end,
+                    rem => end - rem + non_coherent_atom_size,
+                };
+                start..end.min(size)
+            })
+            .collect::<Vec<_>>();
+        expanded.sort_by_key(|range| range.start);
+
+        let mut merged: Vec<Range<u64>> = Vec::with_capacity(expanded.len());
+        for range in expanded {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        merged
+    }
+}
@@ -1,12 +1,17 @@
+mod dirty;
 mod range;
 pub(crate) mod write;
 
 use {
-    crate::{memory::Memory, util::fits_usize},
-    gfx_hal::{device::Device as _, Backend},
+    crate::{
+        device::{MemoryBackend, MemoryDevice as _},
+        memory::Memory,
+        util::fits_usize,
+    },
     std::{ops::Range, ptr::NonNull},
 };
 
+pub use self::dirty::DirtyRanges;
 pub(crate) use self::range::{
     mapped_fitting_range, mapped_slice, mapped_slice_mut, mapped_sub_range,
 };
@@ -27,7 +32,7 @@ pub struct MaybeCoherent(bool);
 /// Represents range of the memory mapped to the host.
 /// Provides methods for safer host access to the memory.
 #[derive(Debug)]
-pub struct MappedRange<'a, B: Backend, C = MaybeCoherent> {
+pub struct MappedRange<'a, B: MemoryBackend, C = MaybeCoherent> {
     /// Memory object that is mapped.
     memory: &'a Memory<B>,
 
@@ -43,7 +48,7 @@ pub struct MappedRange<'a, B: Backend, C = MaybeCoherent> {
 
 impl<'a, B> MappedRange<'a, B>
 where
-    B: Backend,
+    B: MemoryBackend,
 {
     // /// Map range of memory.
     // /// `range` is in memory object space.
@@ -143,8 +148,7 @@ where
         let size = (range.end - range.start) as usize;
 
         if !self.coherent.0 {
-            device
-                .invalidate_mapped_memory_ranges(Some((self.memory.raw(), self.range.clone())))?;
+            device.invalidate_memory_range(self.memory.raw(), self.range.clone())?;
         }
 
         let slice = mapped_slice::<T>(ptr, size);
@@ -188,7 +192,7 @@ where
             flush: if !self.coherent.0 {
                 Some(move || {
                     device
-                        .flush_mapped_memory_ranges(Some((memory.raw(), range)))
+                        .flush_memory_range(memory.raw(), range)
                         .expect("Should flush successfully");
                 })
             } else {
@@ -197,6 +201,61 @@ where
         })
     }
 
+    /// Flush `dirty` sub-ranges of the mapping.
+    /// Ranges are expanded to multiple of `non_coherent_atom_size` in memory object space
+    /// and merged, so that each memory range is flushed once.
+    /// No-op if memory is coherent.
+    ///
+    /// # Safety
+    ///
+    /// * Expanded ranges must be mapped.
+    ///   This holds for blocks allocated from `Heaps` and mapped entirely.
+    pub unsafe fn flush(
+        &self,
+        device: &B::Device,
+        dirty: &DirtyRanges,
+        non_coherent_atom_size: u64,
+    ) -> Result<(), gfx_hal::device::OutOfMemory> {
+        if self.coherent.0 {
+            return Ok(());
+        }
+
+        let size = self.range.end - self.range.start;
+        debug_assert!(dirty.ranges().iter().all(|range| range.end <= size));
+
+        for range in dirty.coalesce(self.range.start, non_coherent_atom_size, self.memory.size()) {
+            device.flush_memory_range(self.memory.raw(), range)?;
+        }
+        Ok(())
+    }
+
+    /// Invalidate sub-range of the mapping
+    /// making device writes to it visible to the host.
+    /// Range is expanded to multiple of `non_coherent_atom_size` in memory object space.
+    /// No-op if memory is coherent.
+    ///
+    /// # Safety
+    ///
+    /// * Expanded range must be mapped.
+    ///   This holds for blocks allocated from `Heaps` and mapped entirely.
+    pub unsafe fn invalidate(
+        &self,
+        device: &B::Device,
+        range: Range<u64>,
+        non_coherent_atom_size: u64,
+    ) -> Result<(), gfx_hal::device::OutOfMemory> {
+        if self.coherent.0 {
+            return Ok(());
+        }
+
+        let mut dirty = DirtyRanges::new();
+        dirty.insert(range);
+        for range in dirty.coalesce(self.range.start, non_coherent_atom_size, self.memory.size()) {
+            device.invalidate_memory_range(self.memory.raw(), range)?;
+        }
+        Ok(())
+    }
+
     /// Convert into mapped range with statically known coherency.
     pub fn coherent(self) -> Result<MappedRange<'a, B, Coherent>, MappedRange<'a, B, NonCoherent>> {
         if self.coherent.0 {
@@ -219,7 +278,7 @@ where
 
 impl<'a, B> From<MappedRange<'a, B, Coherent>> for MappedRange<'a, B>
 where
-    B: Backend,
+    B: MemoryBackend,
 {
     fn from(range: MappedRange<'a, B, Coherent>) -> Self {
         MappedRange {
@@ -233,7 +292,7 @@ where
 
 impl<'a, B> From<MappedRange<'a, B, NonCoherent>> for MappedRange<'a, B>
 where
-    B: Backend,
+    B: MemoryBackend,
 {
     fn from(range: MappedRange<'a, B, NonCoherent>) -> Self {
         MappedRange {
@@ -247,7 +306,7 @@ where
 
 impl<'a, B> MappedRange<'a, B, Coherent>
 where
-    B: Backend,
+    B: MemoryBackend,
 {
     /// Fetch writer to the sub-region.
     ///
@@ -1,9 +1,11 @@
 // use std::fmt;
 
+use crate::device::MemoryBackend;
+
 /// Memory object wrapper.
 /// Contains size and properties of the memory.
 #[derive(Debug)]
-pub struct Memory<B: gfx_hal::Backend> {
+pub struct Memory<B: MemoryBackend> {
     raw: B::Memory,
     size: u64,
     properties: gfx_hal::memory::Properties,
@@ -12,7 +14,7 @@ pub struct Memory<B: gfx_hal::Backend> {
 
 impl<B> Memory<B>
 where
-    B: gfx_hal::Backend,
+    B: MemoryBackend,
 {
     /// Get memory properties.
     pub fn properties(&self) -> gfx_hal::memory::Properties {
@@ -0,0 +1,173 @@
+//! Detailed snapshot of the allocators state.
+//! All types are serializable with `serde-1` feature enabled,
+//! so snapshot can be exported to JSON or any other format supported by serde ecosystem.
+
+use crate::heaps::HeapBudget;
+
+/// Snapshot of the `Heaps`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HeapsSnapshot {
+    /// Snapshots of memory heaps.
+    pub heaps: Vec<MemoryHeapSnapshot>,
+
+    /// Snapshots of memory types.
+    pub types: Vec<MemoryTypeSnapshot>,
+}
+
+/// Snapshot of the memory heap.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemoryHeapSnapshot {
+    /// Memory heap size.
+    pub size: u64,
+
+    /// Total number of bytes allocated from the heap.
+    pub used: u64,
+
+    /// Effective number of bytes allocated from the heap.
+    pub effective: u64,
+
+    /// Memory heap budget.
+    pub budget: HeapBudget,
+}
+
+/// Snapshot of the memory type.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemoryTypeSnapshot {
+    /// Memory properties.
+    pub properties: gfx_hal::memory::Properties,
+
+    /// Index of heap this memory type uses.
+    pub heap_index: usize,
+
+    /// Total number of bytes allocated.
+    pub used: u64,
+
+    /// Effective number bytes allocated.
+    pub effective: u64,
+
+    /// Dedicated allocator snapshot.
+    pub dedicated: DedicatedSnapshot,
+
+    /// Linear allocator snapshot.
+    pub linear: Option<LinearSnapshot>,
+
+    /// Dynamic allocator snapshot.
+    pub dynamic: Option<DynamicSnapshot>,
+
+    /// Buddy allocator snapshot.
+    pub buddy: Option<BuddySnapshot>,
+}
+
+/// Snapshot of the `DedicatedAllocator`.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DedicatedSnapshot {
+    /// Number of blocks allocated.
+    pub blocks: u64,
+
+    /// Total size of blocks allocated.
+    pub used: u64,
+}
+
+/// Snapshot of the `LinearAllocator`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LinearSnapshot {
+    /// Size of the line.
+    pub linear_size: u64,
+
+    /// Lines allocated from device, oldest first.
+    pub lines: Vec<LineSnapshot>,
+}
+
+/// Snapshot of the line of `LinearAllocator`.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LineSnapshot {
+    /// Index of the line.
+    pub index: u64,
+
+    /// Number of bytes allocated from the line.
+    pub used: u64,
+
+    /// Number of bytes freed or wasted for alignment.
+    pub free: u64,
+}
+
+/// Snapshot of the `DynamicAllocator`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DynamicSnapshot {
+    /// Size entries ordered by block size.
+    pub sizes: Vec<SizeEntrySnapshot>,
+}
+
+/// Snapshot of the size entry of `DynamicAllocator`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SizeEntrySnapshot {
+    /// Size of blocks.
+    pub block_size: u64,
+
+    /// Total count of blocks allocated with this size.
+    pub total_blocks: u64,
+
+    /// Chunks of the entry ordered by index.
+    pub chunks: Vec<ChunkSnapshot>,
+}
+
+/// Snapshot of the chunk of `DynamicAllocator`.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChunkSnapshot {
+    /// Index of the chunk.
+    pub index: u32,
+
+    /// Size of the chunk.
+    pub size: u64,
+
+    /// Number of blocks in the chunk.
+    pub blocks: u32,
+
+    /// Bitset of free blocks. Bit `i` is set if block `i` is free.
+    pub free_blocks: u64,
+
+    /// `true` if chunk memory is allocated from device directly,
+    /// `false` if chunk is a block of bigger chunk.
+    pub dedicated: bool,
+
+    /// `true` if chunk is being evacuated by defragmentation.
+    pub evacuating: bool,
+}
+
+/// Snapshot of the `BuddyAllocator`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BuddySnapshot {
+    /// Size of the smallest block.
+    pub min_block_size: u64,
+
+    /// Size of the chunk.
+    pub chunk_size: u64,
+
+    /// Chunks ordered by index.
+    pub chunks: Vec<BuddyChunkSnapshot>,
+}
+
+/// Snapshot of the chunk of `BuddyAllocator`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BuddyChunkSnapshot {
+    /// Index of the chunk.
+    pub index: u32,
+
+    /// Number of blocks allocated from the chunk.
+    pub blocks: u64,
+
+    /// Offsets of free blocks for each order.
+    /// Block of order `n` has size `min_block_size << n`.
+    pub free: Vec<Vec<u64>>,
+}
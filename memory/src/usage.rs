@@ -17,6 +17,80 @@ pub trait MemoryUsage: std::fmt::Debug {
 
     /// Get comparable fitness value for memory allocator.
     fn allocator_fitness(&self, kind: Kind) -> u32;
+
+    /// Get tag for allocations with this usage.
+    /// Tags are used to break down memory utilization by consumers.
+    fn tag(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Attach tag to allocations with this usage.
+    fn tagged(self, tag: &'static str) -> Tagged<Self>
+    where
+        Self: Sized,
+    {
+        Tagged { usage: self, tag }
+    }
+
+    /// Get hint whether allocations with this usage should get dedicated memory object.
+    fn dedicated(&self) -> DedicatedHint {
+        DedicatedHint::Auto
+    }
+
+    /// Request dedicated memory object for allocations with this usage.
+    fn prefer_dedicated(self) -> WithDedicatedHint<Self>
+    where
+        Self: Sized,
+    {
+        WithDedicatedHint {
+            usage: self,
+            hint: DedicatedHint::Prefer,
+        }
+    }
+
+    /// Forbid dedicated memory objects for allocations with this usage.
+    fn never_dedicated(self) -> WithDedicatedHint<Self>
+    where
+        Self: Sized,
+    {
+        WithDedicatedHint {
+            usage: self,
+            hint: DedicatedHint::Never,
+        }
+    }
+}
+
+/// Hint whether allocation should get dedicated memory object.
+/// Attach it to any usage with [`MemoryUsage::prefer_dedicated`]
+/// or [`MemoryUsage::never_dedicated`].
+///
+/// Hints are not derived from resource requirements reported by the driver,
+/// as `gfx-hal` doesn't expose whether resource prefers or requires dedicated allocation.
+/// It is up to the caller to pass `Prefer` for such resources.
+/// `Never` must not be used for resources that require dedicated allocation.
+///
+/// [`MemoryUsage::prefer_dedicated`]: trait.MemoryUsage.html#method.prefer_dedicated
+/// [`MemoryUsage::never_dedicated`]: trait.MemoryUsage.html#method.never_dedicated
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DedicatedHint {
+    /// Dedicated memory object is used only if sub-allocators can't handle allocation size.
+    Auto,
+
+    /// Dedicated memory object is used regardless of allocation size.
+    /// Large render targets and resources for which driver prefers dedicated allocation
+    /// benefit from it.
+    Prefer,
+
+    /// Dedicated memory object is never used.
+    /// Allocation fails if sub-allocators can't handle its size.
+    Never,
+}
+
+impl Default for DedicatedHint {
+    fn default() -> Self {
+        DedicatedHint::Auto
+    }
 }
 
 impl<T> MemoryUsage for T
@@ -33,6 +107,81 @@ where
     fn allocator_fitness(&self, kind: Kind) -> u32 {
         (&**self).allocator_fitness(kind)
     }
+    fn tag(&self) -> Option<&'static str> {
+        (&**self).tag()
+    }
+    fn dedicated(&self) -> DedicatedHint {
+        (&**self).dedicated()
+    }
+}
+
+/// Memory usage with tag attached.
+/// See [`MemoryUsage::tagged`].
+///
+/// [`MemoryUsage::tagged`]: trait.MemoryUsage.html#method.tagged
+#[derive(Clone, Copy, Debug)]
+pub struct Tagged<U> {
+    /// Wrapped memory usage.
+    pub usage: U,
+
+    /// Tag for allocations.
+    pub tag: &'static str,
+}
+
+impl<U> MemoryUsage for Tagged<U>
+where
+    U: MemoryUsage,
+{
+    fn properties_required(&self) -> gfx_hal::memory::Properties {
+        self.usage.properties_required()
+    }
+    fn memory_fitness(&self, properties: gfx_hal::memory::Properties) -> u32 {
+        self.usage.memory_fitness(properties)
+    }
+    fn allocator_fitness(&self, kind: Kind) -> u32 {
+        self.usage.allocator_fitness(kind)
+    }
+    fn tag(&self) -> Option<&'static str> {
+        Some(self.tag)
+    }
+    fn dedicated(&self) -> DedicatedHint {
+        self.usage.dedicated()
+    }
+}
+
+/// Memory usage with dedicated allocation hint attached.
+/// See [`MemoryUsage::prefer_dedicated`] and [`MemoryUsage::never_dedicated`].
+///
+/// [`MemoryUsage::prefer_dedicated`]: trait.MemoryUsage.html#method.prefer_dedicated
+/// [`MemoryUsage::never_dedicated`]: trait.MemoryUsage.html#method.never_dedicated
+#[derive(Clone, Copy, Debug)]
+pub struct WithDedicatedHint<U> {
+    /// Wrapped memory usage.
+    pub usage: U,
+
+    /// Dedicated allocation hint.
+    pub hint: DedicatedHint,
+}
+
+impl<U> MemoryUsage for WithDedicatedHint<U>
+where
+    U: MemoryUsage,
+{
+    fn properties_required(&self) -> gfx_hal::memory::Properties {
+        self.usage.properties_required()
+    }
+    fn memory_fitness(&self, properties: gfx_hal::memory::Properties) -> u32 {
+        self.usage.memory_fitness(properties)
+    }
+    fn allocator_fitness(&self, kind: Kind) -> u32 {
+        self.usage.allocator_fitness(kind)
+    }
+    fn tag(&self) -> Option<&'static str> {
+        self.usage.tag()
+    }
+    fn dedicated(&self) -> DedicatedHint {
+        self.hint
+    }
 }
 
 /// Full speed GPU access.
@@ -60,6 +209,40 @@ impl MemoryUsage for Data {
             Kind::Dedicated => 1,
             Kind::Dynamic => 2,
             Kind::Linear => 0,
+            Kind::Buddy => 1,
+        }
+    }
+}
+
+/// Full speed GPU access for attachments that live within single render pass.
+/// Prefers lazily allocated memory that may never be backed by physical memory
+/// on tile-based GPUs.
+/// Falls back to device local memory where lazily allocated memory is unavailable.
+/// Resource must be created with `TRANSIENT_ATTACHMENT` usage
+/// to be compatible with lazily allocated memory types.
+#[derive(Clone, Copy, Debug)]
+pub struct Transient;
+
+impl MemoryUsage for Transient {
+    fn properties_required(&self) -> gfx_hal::memory::Properties {
+        gfx_hal::memory::Properties::DEVICE_LOCAL
+    }
+
+    #[inline]
+    fn memory_fitness(&self, properties: gfx_hal::memory::Properties) -> u32 {
+        assert!(properties.contains(gfx_hal::memory::Properties::DEVICE_LOCAL));
+        0 | (properties.contains(gfx_hal::memory::Properties::LAZILY_ALLOCATED) as u32) << 3
+            | ((!properties.contains(gfx_hal::memory::Properties::CPU_VISIBLE)) as u32) << 2
+            | ((!properties.contains(gfx_hal::memory::Properties::CPU_CACHED)) as u32) << 1
+            | ((!properties.contains(gfx_hal::memory::Properties::COHERENT)) as u32) << 0
+    }
+
+    fn allocator_fitness(&self, kind: Kind) -> u32 {
+        match kind {
+            Kind::Dedicated => 1,
+            Kind::Dynamic => 2,
+            Kind::Linear => 0,
+            Kind::Buddy => 1,
         }
     }
 }
@@ -91,6 +274,7 @@ impl MemoryUsage for Dynamic {
             Kind::Dedicated => 1,
             Kind::Dynamic => 2,
             Kind::Linear => 0,
+            Kind::Buddy => 1,
         }
     }
 }
@@ -121,6 +305,7 @@ impl MemoryUsage for Upload {
             Kind::Dedicated => 0,
             Kind::Dynamic => 1,
             Kind::Linear => 2,
+            Kind::Buddy => 1,
         }
     }
 }
@@ -151,6 +336,7 @@ impl MemoryUsage for Download {
             Kind::Dedicated => 0,
             Kind::Dynamic => 1,
             Kind::Linear => 2,
+            Kind::Buddy => 1,
         }
     }
 }
@@ -177,6 +363,11 @@ pub enum MemoryUsageValue {
     ///
     /// [`Download`]: struct.Download.html
     Download,
+
+    /// See [`Transient`]
+    ///
+    /// [`Transient`]: struct.Transient.html
+    Transient,
 }
 
 /// Memory usage trait.
@@ -184,6 +375,7 @@ impl MemoryUsage for MemoryUsageValue {
     fn properties_required(&self) -> gfx_hal::memory::Properties {
         match self {
             MemoryUsageValue::Data => Data.properties_required(),
+            MemoryUsageValue::Transient => Transient.properties_required(),
             MemoryUsageValue::Dynamic => Dynamic.properties_required(),
             MemoryUsageValue::Upload => Upload.properties_required(),
             MemoryUsageValue::Download => Download.properties_required(),
@@ -193,6 +385,7 @@ impl MemoryUsage for MemoryUsageValue {
     fn memory_fitness(&self, properties: gfx_hal::memory::Properties) -> u32 {
         match self {
             MemoryUsageValue::Data => Data.memory_fitness(properties),
+            MemoryUsageValue::Transient => Transient.memory_fitness(properties),
             MemoryUsageValue::Dynamic => Dynamic.memory_fitness(properties),
             MemoryUsageValue::Upload => Upload.memory_fitness(properties),
             MemoryUsageValue::Download => Download.memory_fitness(properties),
@@ -202,6 +395,7 @@ impl MemoryUsage for MemoryUsageValue {
     fn allocator_fitness(&self, kind: Kind) -> u32 {
         match self {
             MemoryUsageValue::Data => Data.allocator_fitness(kind),
+            MemoryUsageValue::Transient => Transient.allocator_fitness(kind),
             MemoryUsageValue::Dynamic => Dynamic.allocator_fitness(kind),
             MemoryUsageValue::Upload => Upload.allocator_fitness(kind),
             MemoryUsageValue::Download => Download.allocator_fitness(kind),
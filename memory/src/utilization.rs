@@ -1,4 +1,5 @@
 use {
+    crate::heaps::HeapBudget,
     colorful::{core::color_string::CString, Color, Colorful as _},
     gfx_hal::memory::Properties,
 };
@@ -20,6 +21,9 @@ pub struct MemoryHeapUtilization {
 
     /// Memory heap size.
     pub size: u64,
+
+    /// Memory heap budget.
+    pub budget: HeapBudget,
 }
 
 /// Memory utilization of one type.
@@ -33,6 +37,49 @@ pub struct MemoryTypeUtilization {
 
     /// Index of heap this memory type uses.
     pub heap_index: usize,
+
+    /// Utilization of dedicated memory objects.
+    pub dedicated: DedicatedUtilization,
+}
+
+/// Utilization of dedicated memory objects of the memory type.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DedicatedUtilization {
+    /// Number of blocks with dedicated memory object.
+    pub blocks: u64,
+
+    /// Number of blocks that got dedicated memory object
+    /// because it was preferred by usage.
+    pub preferred: u64,
+
+    /// Total size of dedicated memory objects.
+    pub size: u64,
+}
+
+/// Memory utilization by allocations with one tag.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TagMemoryUtilization {
+    /// Tag of allocations.
+    /// `None` for untagged allocations.
+    pub tag: Option<&'static str>,
+
+    /// Number of blocks allocated.
+    pub blocks: u64,
+
+    /// Total size of blocks allocated.
+    pub size: u64,
+}
+
+impl TagMemoryUtilization {
+    pub(crate) fn allocated(&mut self, size: u64) {
+        self.blocks += 1;
+        self.size += size;
+    }
+
+    pub(crate) fn freed(&mut self, size: u64) {
+        self.blocks -= 1;
+        self.size -= size;
+    }
 }
 
 /// Total memory utilization.
@@ -79,6 +126,13 @@ impl std::fmt::Display for TotalMemoryUtilization {
                 line
             )?;
 
+            if let Some(soft) = heap.budget.soft {
+                writeln!(fmt, "         soft limit: {}MB", soft / MB)?;
+            }
+            if let Some(hard) = heap.budget.hard {
+                writeln!(fmt, "         hard limit: {}MB", hard / MB)?;
+            }
+
             for ty in self.types.iter().filter(|ty| ty.heap_index == index) {
                 let properties = ty.properties;
                 let MemoryUtilization { used, effective } = ty.utilization;
@@ -97,6 +151,16 @@ impl std::fmt::Display for TotalMemoryUtilization {
                     format_basis_points_inverted(effective_basis_points),
                     properties,
                 )?;
+
+                if ty.dedicated.blocks > 0 {
+                    writeln!(
+                        fmt,
+                        "           dedicated: {} blocks ({} preferred), {}MB",
+                        ty.dedicated.blocks,
+                        ty.dedicated.preferred,
+                        ty.dedicated.size / MB,
+                    )?;
+                }
             }
         }
 
@@ -0,0 +1,489 @@
+//! Property tests for allocators driven by host memory simulator.
+
+use {
+    gfx_hal::{
+        adapter::{MemoryProperties, MemoryType},
+        memory::Properties,
+    },
+    proptest::prelude::*,
+    rendy_memory::*,
+    std::collections::HashMap,
+};
+
+const MB: u64 = 1024 * 1024;
+
+fn memory_properties() -> MemoryProperties {
+    MemoryProperties {
+        memory_types: vec![
+            MemoryType {
+                properties: Properties::DEVICE_LOCAL,
+                heap_index: 0,
+            },
+            MemoryType {
+                properties: Properties::CPU_VISIBLE | Properties::COHERENT,
+                heap_index: 1,
+            },
+            MemoryType {
+                properties: Properties::CPU_VISIBLE | Properties::CPU_CACHED,
+                heap_index: 1,
+            },
+        ],
+        memory_heaps: vec![4096 * MB, 1024 * MB],
+    }
+}
+
+fn create_heaps(properties: &MemoryProperties) -> Heaps<HostBackend> {
+    let types = properties.memory_types.iter().map(|mt| {
+        let config = HeapsConfig {
+            linear: if mt.properties.contains(Properties::CPU_VISIBLE) {
+                Some(LinearConfig {
+                    linear_size: 16 * MB,
+                })
+            } else {
+                None
+            },
+            dynamic: Some(DynamicConfig {
+                block_size_granularity: 256,
+                max_chunk_size: 4 * MB,
+                min_device_allocation: 64 * 1024,
+            }),
+            buddy: Some(BuddyConfig {
+                min_block_size: 64 * 1024,
+                chunk_size: 16 * MB,
+            }),
+        };
+        (mt.properties, mt.heap_index as u32, config)
+    });
+
+    unsafe { Heaps::new(types, properties.memory_heaps.iter().cloned()) }
+}
+
+#[derive(Clone, Debug)]
+enum Op {
+    Alloc {
+        usage: MemoryUsageValue,
+        size: u64,
+        align: u64,
+    },
+    Free(prop::sample::Index),
+}
+
+fn usage() -> impl Strategy<Value = MemoryUsageValue> {
+    prop_oneof![
+        Just(MemoryUsageValue::Data),
+        Just(MemoryUsageValue::Transient),
+        Just(MemoryUsageValue::Dynamic),
+        Just(MemoryUsageValue::Upload),
+        Just(MemoryUsageValue::Download),
+    ]
+}
+
+/// Sizes handled by each kind of allocators.
+fn size() -> impl Strategy<Value = u64> {
+    prop_oneof![
+        6 => 1u64..64 * 1024,
+        3 => 64 * 1024..4 * MB,
+        1 => 4 * MB..20 * MB,
+    ]
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        3 => (usage(), size(), 0..12u32).prop_map(|(usage, size, align)| Op::Alloc {
+            usage,
+            size,
+            align: 1 << align,
+        }),
+        2 => any::<prop::sample::Index>().prop_map(Op::Free),
+    ]
+}
+
+/// Check that live blocks from the same memory object never overlap.
+fn assert_disjoint(blocks: &[(MemoryBlock<HostBackend>, u8)]) {
+    let mut ranges = HashMap::new();
+    for (block, _) in blocks {
+        ranges
+            .entry(block.memory().id())
+            .or_insert_with(Vec::new)
+            .push(block.range());
+    }
+
+    for ranges in ranges.values_mut() {
+        ranges.sort_by_key(|range| range.start);
+        for pair in ranges.windows(2) {
+            assert!(
+                pair[0].end <= pair[1].start,
+                "Blocks {:?} and {:?} overlap",
+                pair[0],
+                pair[1]
+            );
+        }
+    }
+}
+
+fn fill(device: &HostDevice, block: &mut MemoryBlock<HostBackend>, value: u8) {
+    if !block.properties().contains(Properties::CPU_VISIBLE) {
+        return;
+    }
+    let size = block.size();
+    unsafe {
+        let mut mapped = block.map(device, 0..size).unwrap();
+        mapped
+            .write::<u8>(device, 0..size)
+            .unwrap()
+            .write(&vec![value; size as usize]);
+    }
+    block.unmap(device);
+}
+
+fn check(device: &HostDevice, block: &mut MemoryBlock<HostBackend>, value: u8) {
+    if !block.properties().contains(Properties::CPU_VISIBLE) {
+        return;
+    }
+    let size = block.size();
+    unsafe {
+        let mut mapped = block.map(device, 0..size).unwrap();
+        let data = mapped.read::<u8>(device, 0..size).unwrap();
+        assert!(
+            data.iter().all(|&byte| byte == value),
+            "Block content was corrupted"
+        );
+    }
+    block.unmap(device);
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn alloc_free(ops in prop::collection::vec(op(), 1..500)) {
+        let properties = memory_properties();
+        let device = HostDevice::new(properties.clone());
+        let mut heaps = create_heaps(&properties);
+        let mut blocks = Vec::new();
+
+        for (step, op) in ops.into_iter().enumerate() {
+            match op {
+                Op::Alloc { usage, size, align } => {
+                    let mut block = heaps.allocate(&device, !0, usage, size, align).unwrap();
+                    prop_assert!(block.size() >= size);
+                    prop_assert_eq!(block.range().start % align, 0);
+                    prop_assert!(block.range().end <= block.memory().size());
+
+                    let value = step as u8;
+                    fill(&device, &mut block, value);
+                    blocks.push((block, value));
+                }
+                Op::Free(index) => {
+                    if blocks.is_empty() {
+                        continue;
+                    }
+                    let (mut block, value) = blocks.swap_remove(index.index(blocks.len()));
+                    check(&device, &mut block, value);
+                    heaps.free(&device, block);
+                }
+            }
+
+            assert_disjoint(&blocks);
+            let utilization = heaps.utilization();
+            for (index, heap) in utilization.heaps.iter().enumerate() {
+                prop_assert_eq!(heap.utilization.used, device.heap_used(index));
+            }
+        }
+
+        for (mut block, value) in blocks {
+            check(&device, &mut block, value);
+            heaps.free(&device, block);
+        }
+        heaps.dispose(&device);
+
+        prop_assert_eq!(device.allocations(), 0, "Memory leaked");
+        prop_assert_eq!(device.mapped(), 0, "Memory left mapped");
+    }
+
+    #[test]
+    fn defragmentation(
+        sizes in prop::collection::vec((1..4096u64, prop::bool::weighted(0.1)), 1..1000),
+    ) {
+        let properties = memory_properties();
+        let device = HostDevice::new(properties.clone());
+        let mut heaps = create_heaps(&properties);
+
+        let blocks = sizes
+            .iter()
+            .enumerate()
+            .map(|(step, &(size, keep))| {
+                let mut block = heaps.allocate(&device, 0b010, Dynamic, size, 16).unwrap();
+                fill(&device, &mut block, step as u8);
+                (block, step as u8, keep)
+            })
+            .collect::<Vec<_>>();
+
+        // Free most of the blocks leaving chunks sparse.
+        let mut kept = Vec::new();
+        for (block, value, keep) in blocks {
+            if keep {
+                kept.push((block, value));
+            } else {
+                heaps.free(&device, block);
+            }
+        }
+        let mut blocks = kept;
+        let allocations = device.allocations();
+
+        let plan = heaps.plan_defragmentation();
+        for (block, value) in &mut blocks {
+            if plan.contains(block) {
+                let size = block.size();
+                let mut relocated = heaps.relocate(&device, block, size, 16).unwrap();
+                prop_assert!(!plan.contains(&relocated));
+                fill(&device, &mut relocated, *value);
+                let old = std::mem::replace(block, relocated);
+                heaps.free(&device, old);
+            }
+        }
+        heaps.finish_defragmentation(plan);
+
+        prop_assert!(device.allocations() <= allocations);
+        assert_disjoint(&blocks);
+
+        for (mut block, value) in blocks {
+            check(&device, &mut block, value);
+            heaps.free(&device, block);
+        }
+        heaps.dispose(&device);
+        prop_assert_eq!(device.allocations(), 0);
+    }
+}
+
+#[test]
+fn hard_budget() {
+    let properties = memory_properties();
+    let device = HostDevice::new(properties.clone());
+    let mut heaps = create_heaps(&properties);
+    heaps.set_budget(
+        0,
+        HeapBudget {
+            soft: None,
+            hard: Some(64 * MB),
+        },
+    );
+
+    let mut blocks = Vec::new();
+    loop {
+        // Blocks bigger than buddy chunk are allocated from device directly.
+        match heaps.allocate(&device, 0b001, Data, 20 * MB, 256) {
+            Ok(block) => blocks.push(block),
+            Err(HeapsError::BudgetExceeded(0)) => break,
+            Err(error) => panic!("Unexpected error: {}", error),
+        }
+        assert!(device.heap_used(0) <= 64 * MB);
+    }
+    assert!(!blocks.is_empty());
+
+    for block in blocks {
+        heaps.free(&device, block);
+    }
+    heaps.dispose(&device);
+    assert_eq!(device.allocations(), 0);
+}
+
+#[test]
+fn hard_budget_sub_allocations() {
+    let properties = memory_properties();
+    let device = HostDevice::new(properties.clone());
+    let mut heaps = create_heaps(&properties);
+
+    // Blocks smaller than chunk are sub-allocated.
+    let mut blocks = vec![heaps
+        .allocate(&device, 0b001, Data, 256 * 1024, 256)
+        .unwrap()];
+    let used = device.heap_used(0);
+    assert!(used > 256 * 1024);
+
+    heaps.set_budget(
+        0,
+        HeapBudget {
+            soft: None,
+            hard: Some(used),
+        },
+    );
+
+    loop {
+        match heaps.allocate(&device, 0b001, Data, 256 * 1024, 256) {
+            Ok(block) => blocks.push(block),
+            Err(HeapsError::BudgetExceeded(0)) => break,
+            Err(error) => panic!("Unexpected error: {}", error),
+        }
+        // New chunk would exceed the budget.
+        assert_eq!(device.heap_used(0), used);
+    }
+    assert_eq!(device.heap_used(0), used);
+
+    // Blocks that need no new device memory fit into the budget.
+    assert!(blocks.len() > 1);
+
+    for block in blocks {
+        heaps.free(&device, block);
+    }
+    heaps.dispose(&device);
+    assert_eq!(device.allocations(), 0);
+}
+
+#[test]
+fn out_of_memory() {
+    let properties = memory_properties();
+    let device = HostDevice::new(properties.clone());
+    let mut heaps = create_heaps(&properties);
+
+    assert!(heaps
+        .allocate(&device, 0b001, Data, 8192 * MB, 256)
+        .is_err());
+
+    heaps.dispose(&device);
+    assert_eq!(device.allocations(), 0);
+}
+
+#[test]
+fn buddy_falls_back_to_dedicated() {
+    let properties = memory_properties();
+    let device = HostDevice::new(properties.clone());
+    let mut heaps = create_heaps(&properties);
+
+    // Too big for dynamic allocator, but fits buddy chunk.
+    let buddy = heaps.allocate(&device, 0b001, Data, 8 * MB, 256).unwrap();
+    assert!(!buddy.is_dedicated());
+
+    // Alignment exceeds buddy chunk.
+    let dedicated = heaps
+        .allocate(&device, 0b001, Data, 8 * MB, 32 * MB)
+        .unwrap();
+    assert!(dedicated.is_dedicated());
+
+    heaps.free(&device, buddy);
+    heaps.free(&device, dedicated);
+    heaps.dispose(&device);
+    assert_eq!(device.allocations(), 0);
+}
+
+fn effective(heaps: &Heaps<HostBackend>, heap_index: usize) -> u64 {
+    heaps.utilization().heaps[heap_index].utilization.effective
+}
+
+#[test]
+fn block_cache_reserves_dynamic_blocks() {
+    let properties = memory_properties();
+    let device = HostDevice::new(properties.clone());
+    let mut heaps = create_heaps(&properties);
+    let config = BlockCacheConfig::default();
+    let mut cache = BlockCache::new(&heaps, config);
+
+    let block = cache
+        .allocate(&device, || &mut heaps, 0b001, Data, 1024, 256)
+        .unwrap();
+    assert!(!block.is_dedicated());
+    assert_eq!(effective(&heaps, 0), 1024 * config.batch as u64);
+
+    heaps.free(&device, block);
+    cache.dispose(&device, &mut heaps);
+    heaps.dispose(&device);
+    assert_eq!(device.allocations(), 0);
+}
+
+#[test]
+fn block_cache_skips_memory_without_dynamic() {
+    let properties = memory_properties();
+    let device = HostDevice::new(properties.clone());
+    let types = properties.memory_types.iter().map(|mt| {
+        let config = HeapsConfig {
+            linear: None,
+            dynamic: None,
+            buddy: None,
+        };
+        (mt.properties, mt.heap_index as u32, config)
+    });
+    let mut heaps = unsafe { Heaps::new(types, properties.memory_heaps.iter().cloned()) };
+    let mut cache = BlockCache::new(&heaps, BlockCacheConfig::default());
+
+    let block = cache
+        .allocate(&device, || &mut heaps, 0b001, Data, 1024, 256)
+        .unwrap();
+    assert!(block.is_dedicated());
+
+    // No dedicated blocks are reserved.
+    assert_eq!(device.allocations(), 1);
+    assert_eq!(effective(&heaps, 0), 1024);
+
+    heaps.free(&device, block);
+    cache.dispose(&device, &mut heaps);
+    heaps.dispose(&device);
+    assert_eq!(device.allocations(), 0);
+}
+
+#[test]
+fn block_cache_skips_blocks_above_dynamic_max() {
+    let properties = memory_properties();
+    let device = HostDevice::new(properties.clone());
+    let mut heaps = create_heaps(&properties);
+    let mut cache = BlockCache::new(
+        &heaps,
+        BlockCacheConfig {
+            max_block_size: 2 * MB,
+            batch: 16,
+        },
+    );
+
+    // Too big for dynamic allocator.
+    let block = cache
+        .allocate(&device, || &mut heaps, 0b001, Data, MB, 256)
+        .unwrap();
+    assert_eq!(effective(&heaps, 0), MB);
+
+    heaps.free(&device, block);
+    cache.dispose(&device, &mut heaps);
+    heaps.dispose(&device);
+    assert_eq!(device.allocations(), 0);
+}
+
+#[test]
+fn dirty_ranges_coalesce() {
+    let mut dirty = DirtyRanges::new();
+    dirty.insert(10..20);
+    dirty.insert(300..310);
+    dirty.insert(40..50);
+    dirty.insert(200..260);
+
+    assert_eq!(dirty.coalesce(0, 64, 1024), vec![0..64, 192..320]);
+    assert_eq!(dirty.coalesce(0, 64, 300), vec![0..64, 192..300]);
+    assert_eq!(dirty.coalesce(100, 64, 1024), vec![64..192, 256..448]);
+}
+
+#[test]
+fn flush_dirty_ranges() {
+    let properties = memory_properties();
+    let device = HostDevice::new(properties.clone());
+    let mut heaps = create_heaps(&properties);
+
+    // Memory type 2 is not coherent.
+    let mut block = heaps.allocate(&device, 0b100, Dynamic, 4096, 256).unwrap();
+    let size = block.size();
+    let (_, flushes, invalidations) = device.calls();
+
+    unsafe {
+        let mapped = block.map(&device, 0..size).unwrap();
+        let mut dirty = DirtyRanges::new();
+        dirty.insert(0..4);
+        dirty.insert(8..12);
+        dirty.insert(1024..1028);
+        mapped.flush(&device, &dirty, 256).unwrap();
+        mapped.invalidate(&device, 100..200, 256).unwrap();
+    }
+    block.unmap(&device);
+
+    assert_eq!(device.calls().1 - flushes, 2);
+    assert_eq!(device.calls().2 - invalidations, 1);
+
+    heaps.free(&device, block);
+    heaps.dispose(&device);
+    assert_eq!(device.allocations(), 0);
+}
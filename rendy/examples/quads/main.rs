@@ -463,20 +463,16 @@ where
 
         let pipeline = unsafe {
             factory
-                .device()
-                .create_compute_pipeline(
-                    &hal::pso::ComputePipelineDesc {
-                        shader: hal::pso::EntryPoint {
-                            entry: "main",
-                            module: &module,
-                            specialization: hal::pso::Specialization::default(),
-                        },
-                        layout: &pipeline_layout,
-                        flags: hal::pso::PipelineCreationFlags::empty(),
-                        parent: hal::pso::BasePipeline::None,
+                .create_compute_pipeline(&hal::pso::ComputePipelineDesc {
+                    shader: hal::pso::EntryPoint {
+                        entry: "main",
+                        module: &module,
+                        specialization: hal::pso::Specialization::default(),
                     },
-                    None,
-                )
+                    layout: &pipeline_layout,
+                    flags: hal::pso::PipelineCreationFlags::empty(),
+                    parent: hal::pso::BasePipeline::None,
+                })
                 .map_err(NodeBuildError::Pipeline)?
         };
 
@@ -5,10 +5,13 @@ pub use rendy_core::hal::buffer::*;
 use {
     crate::{
         core::{device_owned, Device, DeviceId},
-        memory::{Block, Heaps, MappedRange, MemoryBlock, MemoryUsage},
+        mapped::MappedBuffer,
+        memory::{
+            Block, DefragmentationPlan, Heaps, HeapsError, MappedRange, MemoryBlock, MemoryUsage,
+        },
+        named::{Named, Relevant, ResourceId},
         CreationError,
     },
-    relevant::Relevant,
     rendy_core::hal::{device::Device as _, Backend},
 };
 
@@ -60,21 +63,39 @@ where
         memory_usage: impl MemoryUsage,
     ) -> Result<Self, BufferCreationError> {
         log::trace!("{:#?}@{:#?}", info, memory_usage);
-        assert_ne!(info.size, 0);
 
-        let mut buf = device
-            .create_buffer(info.size, info.usage)
-            .map_err(CreationError::Create)?;
-        let reqs = device.get_buffer_requirements(&buf);
-        let block = heaps
-            .allocate(
+        Self::create_with(device, info, |reqs| {
+            heaps.allocate(
                 device,
                 reqs.type_mask as u32,
                 memory_usage,
                 reqs.size,
                 reqs.alignment,
             )
-            .map_err(CreationError::Allocate)?;
+        })
+    }
+
+    /// Create buffer, allocate memory block for it with `allocate` function and bind.
+    /// `allocate` receives memory requirements of the buffer.
+    ///
+    /// # Safety
+    ///
+    /// Memory block returned by `allocate` must be owned by this `Device`
+    /// and satisfy requirements.
+    pub unsafe fn create_with(
+        device: &Device<B>,
+        info: BufferInfo,
+        allocate: impl FnOnce(
+            &rendy_core::hal::memory::Requirements,
+        ) -> Result<MemoryBlock<B>, HeapsError>,
+    ) -> Result<Self, BufferCreationError> {
+        assert_ne!(info.size, 0);
+
+        let mut buf = device
+            .create_buffer(info.size, info.usage)
+            .map_err(CreationError::Create)?;
+        let reqs = device.get_buffer_requirements(&buf);
+        let block = allocate(&reqs).map_err(CreationError::Allocate)?;
 
         device
             .bind_buffer_memory(block.memory(), block.range().start, &mut buf)
@@ -85,16 +106,72 @@ where
             raw: buf,
             block,
             info,
-            relevant: Relevant,
+            relevant: Relevant::new("Buffer"),
         })
     }
 
+    /// Move buffer to new memory block
+    /// if its current block is selected for relocation by the `plan`.
+    /// New raw buffer is created and bound to the block allocated with `Heaps::relocate`.
+    ///
+    /// Returns old buffer if relocation took place.
+    /// Its content must be copied to this buffer before it get disposed.
+    ///
+    /// # Safety
+    ///
+    /// Same as for `Buffer::create`.
+    /// Raw buffer changes, so any references to the old raw buffer
+    /// (like descriptor sets) must be updated.
+    pub unsafe fn relocate(
+        &mut self,
+        device: &Device<B>,
+        heaps: &mut Heaps<B>,
+        plan: &DefragmentationPlan,
+    ) -> Result<Option<Self>, BufferCreationError> {
+        self.assert_device_owner(device);
+        if !plan.contains(&self.block) {
+            return Ok(None);
+        }
+
+        log::trace!("Relocate {:#?}", self.info);
+
+        let mut buf = device
+            .create_buffer(self.info.size, self.info.usage)
+            .map_err(CreationError::Create)?;
+        let reqs = device.get_buffer_requirements(&buf);
+        let block = heaps
+            .relocate(device, &self.block, reqs.size, reqs.alignment)
+            .map_err(CreationError::Allocate)?;
+
+        device
+            .bind_buffer_memory(block.memory(), block.range().start, &mut buf)
+            .map_err(CreationError::Bind)?;
+
+        if let Some(name) = self.relevant.name() {
+            device.set_buffer_name(&mut buf, name);
+        }
+
+        Ok(Some(Buffer {
+            device: self.device,
+            raw: std::mem::replace(&mut self.raw, buf),
+            block: std::mem::replace(&mut self.block, block),
+            info: self.info,
+            relevant: self.relevant.renew(),
+        }))
+    }
+
     /// Dispose of buffer resource.
     /// Deallocate memory block.
     pub unsafe fn dispose(self, device: &Device<B>, heaps: &mut Heaps<B>) {
+        self.dispose_with(device, |block| heaps.free(device, block))
+    }
+
+    /// Dispose of buffer resource.
+    /// Memory block is passed to `free` function.
+    pub unsafe fn dispose_with(self, device: &Device<B>, free: impl FnOnce(MemoryBlock<B>)) {
         self.assert_device_owner(device);
         device.destroy_buffer(self.raw);
-        heaps.free(device, self.block);
+        free(self.block);
         self.relevant.dispose();
     }
 
@@ -143,8 +220,42 @@ where
         self.block.map(device, range)
     }
 
+    /// Map whole buffer as typed slice that tracks host writes.
+    /// See [`MappedBuffer`] for details.
+    ///
+    /// [`MappedBuffer`]: struct.MappedBuffer.html
+    pub fn map_typed<'a, T>(
+        &'a mut self,
+        device: &Device<B>,
+        non_coherent_atom_size: u64,
+    ) -> Result<MappedBuffer<'a, B, T>, rendy_core::hal::device::MapError>
+    where
+        T: Copy,
+    {
+        MappedBuffer::new(self, device, non_coherent_atom_size)
+    }
+
     /// Get buffer info.
     pub fn size(&self) -> u64 {
         self.info().size
     }
 }
+
+impl<B> Named<B> for Buffer<B>
+where
+    B: Backend,
+{
+    fn id(&self) -> ResourceId {
+        self.relevant.id()
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.relevant.name()
+    }
+
+    unsafe fn set_name(&mut self, device: &Device<B>, name: &str) {
+        self.assert_device_owner(device);
+        device.set_buffer_name(&mut self.raw, name);
+        self.relevant.set_name(name);
+    }
+}
@@ -6,10 +6,10 @@ use {
     crate::{
         core::{device_owned, Device, DeviceId},
         escape::Handle,
-        memory::{Block, Heaps, MemoryBlock, MemoryUsage},
+        memory::{Block, Heaps, HeapsError, MemoryBlock, MemoryUsage},
+        named::{Named, Relevant, ResourceId},
         CreationError,
     },
-    relevant::Relevant,
     rendy_core::hal::{device::Device as _, format, Backend},
 };
 
@@ -71,6 +71,36 @@ where
         heaps: &mut Heaps<B>,
         info: ImageInfo,
         memory_usage: impl MemoryUsage,
+    ) -> Result<Self, ImageCreationError> {
+        log::trace!("{:#?}@{:#?}", info, memory_usage);
+
+        Self::create_with(device, info, |reqs| {
+            let block = heaps.allocate(
+                device,
+                reqs.type_mask as u32,
+                memory_usage,
+                reqs.size,
+                reqs.alignment,
+            )?;
+            // Images are never relocated.
+            heaps.pin(&block);
+            Ok(block)
+        })
+    }
+
+    /// Create image, allocate memory block for it with `allocate` function and bind.
+    /// `allocate` receives memory requirements of the image.
+    ///
+    /// # Safety
+    ///
+    /// Memory block returned by `allocate` must be owned by this `Device`
+    /// and satisfy requirements.
+    pub unsafe fn create_with(
+        device: &Device<B>,
+        info: ImageInfo,
+        allocate: impl FnOnce(
+            &rendy_core::hal::memory::Requirements,
+        ) -> Result<MemoryBlock<B>, HeapsError>,
     ) -> Result<Self, ImageCreationError> {
         assert!(
             info.levels <= info.kind.num_levels(),
@@ -80,8 +110,6 @@ where
             info.kind,
         );
 
-        log::trace!("{:#?}@{:#?}", info, memory_usage);
-
         let mut img = device
             .create_image(
                 info.kind,
@@ -93,15 +121,7 @@ where
             )
             .map_err(CreationError::Create)?;
         let reqs = device.get_image_requirements(&img);
-        let block = heaps
-            .allocate(
-                device,
-                reqs.type_mask as u32,
-                memory_usage,
-                reqs.size,
-                reqs.alignment,
-            )
-            .map_err(CreationError::Allocate)?;
+        let block = allocate(&reqs).map_err(CreationError::Allocate)?;
 
         device
             .bind_image_memory(block.memory(), block.range().start, &mut img)
@@ -112,7 +132,7 @@ where
             raw: img,
             block: Some(block),
             info,
-            relevant: Relevant,
+            relevant: Relevant::new("Image"),
         })
     }
 
@@ -123,15 +143,23 @@ where
             raw,
             block: None,
             info,
-            relevant: Relevant,
+            relevant: Relevant::new("Swapchain image"),
         }
     }
 
     /// Destroy image resource.
     pub unsafe fn dispose(self, device: &Device<B>, heaps: &mut Heaps<B>) {
+        self.dispose_with(device, |block| heaps.free(device, block))
+    }
+
+    /// Destroy image resource.
+    /// Memory block, if any, is passed to `free` function.
+    pub unsafe fn dispose_with(self, device: &Device<B>, free: impl FnOnce(MemoryBlock<B>)) {
         self.assert_device_owner(device);
         device.destroy_image(self.raw);
-        self.block.map(|block| heaps.free(device, block));
+        if let Some(block) = self.block {
+            free(block);
+        }
         self.relevant.dispose();
     }
 
@@ -258,7 +286,7 @@ where
             raw: view,
             image,
             info,
-            relevant: Relevant,
+            relevant: Relevant::new("Image view"),
         })
     }
 
@@ -290,6 +318,43 @@ where
     }
 }
 
+impl<B> Named<B> for Image<B>
+where
+    B: Backend,
+{
+    fn id(&self) -> ResourceId {
+        self.relevant.id()
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.relevant.name()
+    }
+
+    unsafe fn set_name(&mut self, device: &Device<B>, name: &str) {
+        self.assert_device_owner(device);
+        device.set_image_name(&mut self.raw, name);
+        self.relevant.set_name(name);
+    }
+}
+
+impl<B> Named<B> for ImageView<B>
+where
+    B: Backend,
+{
+    fn id(&self) -> ResourceId {
+        self.relevant.id()
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.relevant.name()
+    }
+
+    unsafe fn set_name(&mut self, device: &Device<B>, name: &str) {
+        self.assert_device_owner(device);
+        self.relevant.set_name(name);
+    }
+}
+
 fn match_kind(kind: Kind, view_kind: ViewKind, view_caps: ViewCapabilities) -> bool {
     match kind {
         Kind::D1(..) => match view_kind {
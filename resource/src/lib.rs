@@ -18,12 +18,23 @@ use rendy_memory as memory;
 mod buffer;
 mod escape;
 mod image;
+mod mapped;
+mod named;
 mod set;
 
 mod resources;
 mod sampler;
 
-pub use crate::{buffer::*, escape::*, image::*, resources::*, sampler::*, set::*};
+pub use crate::{
+    buffer::*,
+    escape::*,
+    image::*,
+    mapped::*,
+    named::{Named, ResourceId},
+    resources::*,
+    sampler::*,
+    set::*,
+};
 
 /// Error creating a resource.
 #[derive(Clone, Debug, PartialEq)]
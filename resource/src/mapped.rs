@@ -0,0 +1,180 @@
+//! Typed mapping of the buffer.
+
+use {
+    crate::{
+        buffer::Buffer,
+        core::Device,
+        memory::{Block, DirtyRanges, MappedRange},
+    },
+    rendy_core::hal::{
+        device::{MapError, OutOfMemory},
+        Backend,
+    },
+    std::{
+        marker::PhantomData,
+        mem::{align_of, size_of},
+        ops::Range,
+        ptr::copy_nonoverlapping,
+        slice::{from_raw_parts, from_raw_parts_mut},
+    },
+};
+
+/// Typed view of the buffer mapped to the host memory.
+///
+/// Tracks ranges of elements written by the host
+/// and flushes them at once on [`commit`].
+/// Dirty ranges are merged with respect to `non_coherent_atom_size`
+/// so that every memory range is flushed once.
+/// For coherent memory [`commit`] only forgets dirty ranges.
+///
+/// [`commit`]: #method.commit
+#[derive(Debug)]
+pub struct MappedBuffer<'a, B: Backend, T> {
+    mapping: MappedRange<'a, B>,
+    len: usize,
+    dirty: DirtyRanges,
+    non_coherent_atom_size: u64,
+    marker: PhantomData<fn(T) -> T>,
+}
+
+impl<'a, B, T> MappedBuffer<'a, B, T>
+where
+    B: Backend,
+    T: Copy,
+{
+    /// Map whole buffer as slice of `T`.
+    /// Trailing bytes that don't form whole element are not accessible.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `T` is zero-sized or buffer memory is not aligned for `T`.
+    pub fn new(
+        buffer: &'a mut Buffer<B>,
+        device: &Device<B>,
+        non_coherent_atom_size: u64,
+    ) -> Result<Self, MapError> {
+        assert_ne!(size_of::<T>(), 0, "Zero-sized types can't be mapped");
+        assert_ne!(non_coherent_atom_size, 0);
+
+        let len = (buffer.size() / size_of::<T>() as u64) as usize;
+
+        // Whole block is mapped so that ranges expanded to `non_coherent_atom_size` are mapped too.
+        let size = buffer.block().size();
+        let mapping = buffer.map(device, 0..size)?;
+        assert_eq!(
+            mapping.ptr().as_ptr() as usize % align_of::<T>(),
+            0,
+            "Mapped buffer memory must be aligned for element type"
+        );
+
+        Ok(MappedBuffer {
+            mapping,
+            len,
+            dirty: DirtyRanges::new(),
+            non_coherent_atom_size,
+            marker: PhantomData,
+        })
+    }
+
+    /// Get number of elements in the mapping.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Check if mapping has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Check if there are writes not committed yet.
+    pub fn is_dirty(&self) -> bool {
+        !self.dirty.is_empty()
+    }
+
+    fn bytes(&self, range: &Range<usize>) -> Range<u64> {
+        assert!(
+            range.start <= range.end && range.end <= self.len,
+            "Range {:?} is out of bounds of {} elements",
+            range,
+            self.len
+        );
+        let size = size_of::<T>() as u64;
+        range.start as u64 * size..range.end as u64 * size
+    }
+
+    /// Get mutable slice of the elements range.
+    /// Whole range is considered written.
+    ///
+    /// # Safety
+    ///
+    /// * Caller must ensure that device won't access the range until it is committed.
+    /// * Returned slice must not be read unless it was written by the host.
+    pub unsafe fn slice_mut(&mut self, range: Range<usize>) -> &mut [T] {
+        let bytes = self.bytes(&range);
+        self.dirty.insert(bytes);
+        from_raw_parts_mut(
+            (self.mapping.ptr().as_ptr() as *mut T).add(range.start),
+            range.end - range.start,
+        )
+    }
+
+    /// Write elements starting from `offset`.
+    ///
+    /// # Safety
+    ///
+    /// * Caller must ensure that device won't access the range until it is committed.
+    pub unsafe fn write(&mut self, offset: usize, data: &[T]) {
+        let slice = self.slice_mut(offset..offset + data.len());
+        copy_nonoverlapping(data.as_ptr(), slice.as_mut_ptr(), data.len());
+    }
+
+    /// Flush all ranges written since last commit.
+    ///
+    /// # Safety
+    ///
+    /// * `device` must be the one buffer was created with.
+    pub unsafe fn commit(&mut self, device: &Device<B>) -> Result<(), OutOfMemory> {
+        if self.dirty.is_empty() {
+            return Ok(());
+        }
+
+        self.mapping
+            .flush(device, &self.dirty, self.non_coherent_atom_size)?;
+        self.dirty.clear();
+        Ok(())
+    }
+
+    /// Invalidate range of elements and read it.
+    ///
+    /// # Safety
+    ///
+    /// * `device` must be the one buffer was created with.
+    /// * Caller must ensure that device won't write to the range until the borrowing ends
+    ///   and that device writes were made available to the host, e.g. by waiting for a fence.
+    pub unsafe fn read(
+        &mut self,
+        device: &Device<B>,
+        range: Range<usize>,
+    ) -> Result<&[T], OutOfMemory> {
+        let bytes = self.bytes(&range);
+        if bytes.start < bytes.end {
+            self.mapping
+                .invalidate(device, bytes, self.non_coherent_atom_size)?;
+        }
+        Ok(from_raw_parts(
+            (self.mapping.ptr().as_ptr() as *const T).add(range.start),
+            range.end - range.start,
+        ))
+    }
+}
+
+impl<'a, B, T> Drop for MappedBuffer<'a, B, T>
+where
+    B: Backend,
+{
+    fn drop(&mut self) {
+        if !self.dirty.is_empty() {
+            log::warn!("Mapped buffer dropped with uncommitted writes");
+        }
+    }
+}
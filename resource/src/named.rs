@@ -0,0 +1,148 @@
+//! Ids and debug names of resources.
+
+use {
+    crate::{core::Device, escape::Escape},
+    rendy_core::hal::Backend,
+    std::sync::atomic::{AtomicU64, Ordering},
+};
+
+/// Counter for unique ids of resources.
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Id of the resource unique in the process.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ResourceId(u64);
+
+impl ResourceId {
+    /// Allocate new unique id.
+    pub fn new() -> Self {
+        ResourceId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl Default for ResourceId {
+    fn default() -> Self {
+        ResourceId::new()
+    }
+}
+
+/// Resource with unique id that can be given a name for debugging.
+///
+/// Name is shown in `Debug` output of the resource,
+/// reported if resource is dropped without disposal
+/// and forwarded to the backend where it supports naming the object,
+/// so that validation layers and graphics debuggers can show it.
+///
+/// Backend has no facility to name image views, samplers
+/// and descriptor set layouts, so their names are only kept by the wrapper.
+///
+/// Name can be set only through unique access,
+/// so resources should be named before they are shared with `Escape::share`.
+pub trait Named<B: Backend> {
+    /// Get unique id of the resource.
+    fn id(&self) -> ResourceId;
+
+    /// Get name of the resource.
+    fn name(&self) -> Option<&str>;
+
+    /// Set name of the resource.
+    ///
+    /// # Safety
+    ///
+    /// `device` must be the one resource was created with.
+    unsafe fn set_name(&mut self, device: &Device<B>, name: &str);
+}
+
+impl<B, T> Named<B> for Escape<T>
+where
+    B: Backend,
+    T: Named<B>,
+{
+    fn id(&self) -> ResourceId {
+        T::id(self)
+    }
+
+    fn name(&self) -> Option<&str> {
+        T::name(self)
+    }
+
+    unsafe fn set_name(&mut self, device: &Device<B>, name: &str) {
+        T::set_name(self, device, name)
+    }
+}
+
+/// Marker for resources that must be disposed.
+/// Keeps name of the resource to report it if marker is dropped instead.
+pub(crate) struct Relevant {
+    id: ResourceId,
+    kind: &'static str,
+    name: Option<String>,
+    relevant: Option<relevant::Relevant>,
+}
+
+impl Relevant {
+    /// Create marker for resource of the `kind`.
+    pub(crate) fn new(kind: &'static str) -> Self {
+        Relevant {
+            id: ResourceId::new(),
+            kind,
+            name: None,
+            relevant: Some(relevant::Relevant),
+        }
+    }
+
+    /// Create marker with the same kind and name for another resource.
+    pub(crate) fn renew(&self) -> Self {
+        Relevant {
+            id: ResourceId::new(),
+            kind: self.kind,
+            name: self.name.clone(),
+            relevant: Some(relevant::Relevant),
+        }
+    }
+
+    pub(crate) fn id(&self) -> ResourceId {
+        self.id
+    }
+
+    pub(crate) fn name(&self) -> Option<&str> {
+        self.name.as_ref().map(String::as_str)
+    }
+
+    pub(crate) fn set_name(&mut self, name: &str) {
+        self.name = Some(name.to_owned());
+    }
+
+    /// Mark resource as disposed.
+    pub(crate) fn dispose(mut self) {
+        if let Some(relevant) = self.relevant.take() {
+            relevant.dispose();
+        }
+    }
+}
+
+impl std::fmt::Debug for Relevant {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt.debug_struct("Relevant")
+            .field("id", &self.id)
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+impl Drop for Relevant {
+    fn drop(&mut self) {
+        if let Some(relevant) = self.relevant.take() {
+            if !std::thread::panicking() {
+                match &self.name {
+                    Some(name) => {
+                        log::error!("{} `{}` is dropped without disposal", self.kind, name)
+                    }
+                    None => log::error!("Unnamed {} is dropped without disposal", self.kind),
+                }
+            }
+            // Leak is reported above with the name, don't let inner marker report it again.
+            relevant.dispose();
+        }
+    }
+}
@@ -3,8 +3,10 @@
 mod cache;
 
 use {
-    crate::core::{device_owned, Device, DeviceId},
-    relevant::Relevant,
+    crate::{
+        core::{device_owned, Device, DeviceId},
+        named::{Named, Relevant, ResourceId},
+    },
     rendy_core::hal::{device::Device as _, image::SamplerDesc, Backend},
 };
 
@@ -36,7 +38,7 @@ where
             device: device.id(),
             raw,
             info,
-            relevant: Relevant,
+            relevant: Relevant::new("Sampler"),
         })
     }
 
@@ -56,4 +58,27 @@ where
     pub unsafe fn raw_mut(&mut self) -> &mut B::Sampler {
         &mut self.raw
     }
+
+    /// Get sampler info.
+    pub fn info(&self) -> &SamplerDesc {
+        &self.info
+    }
+}
+
+impl<B> Named<B> for Sampler<B>
+where
+    B: Backend,
+{
+    fn id(&self) -> ResourceId {
+        self.relevant.id()
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.relevant.name()
+    }
+
+    unsafe fn set_name(&mut self, device: &Device<B>, name: &str) {
+        self.assert_device_owner(device);
+        self.relevant.set_name(name);
+    }
 }
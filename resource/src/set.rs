@@ -3,8 +3,8 @@ use {
         core::{device_owned, Device, DeviceId},
         descriptor,
         escape::Handle,
+        named::{Named, Relevant, ResourceId},
     },
-    relevant::Relevant,
     rendy_core::hal::{device::Device as _, pso::DescriptorSetLayoutBinding, Backend},
     smallvec::SmallVec,
 };
@@ -50,7 +50,7 @@ where
             device: device.id(),
             raw,
             info,
-            relevant: Relevant,
+            relevant: Relevant::new("Descriptor set layout"),
         })
     }
 
@@ -107,7 +107,7 @@ where
             device: device.id(),
             set: sets.swap_remove(0),
             layout: layout.clone(),
-            relevant: Relevant,
+            relevant: Relevant::new("Descriptor set"),
         })
     }
 
@@ -135,7 +135,7 @@ where
             device: device.id(),
             set,
             layout: layout.clone(),
-            relevant: Relevant,
+            relevant: Relevant::new("Descriptor set"),
         }));
 
         Ok(())
@@ -162,3 +162,40 @@ where
         &self.layout
     }
 }
+
+impl<B> Named<B> for DescriptorSetLayout<B>
+where
+    B: Backend,
+{
+    fn id(&self) -> ResourceId {
+        self.relevant.id()
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.relevant.name()
+    }
+
+    unsafe fn set_name(&mut self, device: &Device<B>, name: &str) {
+        self.assert_device_owner(device);
+        self.relevant.set_name(name);
+    }
+}
+
+impl<B> Named<B> for DescriptorSet<B>
+where
+    B: Backend,
+{
+    fn id(&self) -> ResourceId {
+        self.relevant.id()
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.relevant.name()
+    }
+
+    unsafe fn set_name(&mut self, device: &Device<B>, name: &str) {
+        self.assert_device_owner(device);
+        device.set_descriptor_set_name(self.set.raw_mut(), name);
+        self.relevant.set_name(name);
+    }
+}
@@ -2,7 +2,7 @@
 use {
     crate::{
         core::{cast_cow, cast_slice},
-        factory::{Factory, ImageState, UploadError},
+        factory::{Factory, ImageState, MipsError, UploadError},
         memory::Data,
         pixel::AsPixel,
         resource::{
@@ -84,7 +84,7 @@ pub enum BuildError {
     Image(ImageCreationError),
     Upload(UploadError),
     ImageView(ImageViewCreationError),
-    Mipmap(rendy_core::hal::device::OutOfMemory),
+    Mipmap(MipsError),
     Sampler(rendy_core::hal::device::AllocationError),
 }
 
@@ -431,11 +431,12 @@ impl<'a> TextureBuilder<'a> {
             profile_scope!("fill_mips");
             unsafe {
                 factory
-                    .blitter()
                     .fill_mips(
-                        factory.device(),
                         image.clone(),
                         image::Filter::Linear,
+                        self.data_width,
+                        self.data_height,
+                        buffer,
                         std::iter::once(mip_state).chain(std::iter::repeat(undef_state)),
                         std::iter::repeat(next_state),
                     )